@@ -3,6 +3,7 @@ use super::symbol_bindings::*;
 use crate::exec::*;
 use crate::parse::*;
 
+use std::sync::*;
 use std::convert::{Infallible};
 use std::result::{Result};
 
@@ -86,16 +87,47 @@ pub enum BindError {
     /// Tried to extend the syntax for something that's not an extendable syntax
     CannotExtendSyntax(String),
 
+    /// Macros were expanded inside each other more deeply than the maximum macro depth
+    MacroDepthLimitReached(usize),
+
     /// A file could not be found
     FileNotFound(String),
 
     /// An IO error occurred
-    IOError
+    IOError,
+
+    /// An error that occurred at a particular location in the source code
+    AtLocation(Arc<SourceLocation>, Box<BindError>)
 }
 
 /// Result of a binding operation
 pub type BindResult<T> = Result<(T, SymbolBindings), (BindError, SymbolBindings)>;
 
+impl BindError {
+    ///
+    /// Attaches a source location to this error
+    ///
+    /// As for `RuntimeError::with_location()`, a location that contains the existing location is ignored and any other location
+    /// is added as context.
+    ///
+    pub fn with_location(self, location: Arc<SourceLocation>) -> BindError {
+        match self.location() {
+            Some(existing_location) if location.contains(&existing_location)    => self,
+            _                                                                   => BindError::AtLocation(location, Box::new(self))
+        }
+    }
+
+    ///
+    /// Retrieves the outermost location attached to this error, if there is one
+    ///
+    pub fn location(&self) -> Option<Arc<SourceLocation>> {
+        match self {
+            BindError::AtLocation(location, _)  => Some(location.clone()),
+            _                                   => None
+        }
+    }
+}
+
 impl From<Infallible> for BindError {
     fn from(_: Infallible) -> BindError {
         BindError::NotInfallible
@@ -119,6 +151,7 @@ impl From<RuntimeError> for BindError {
             IOError                             => BindError::IOError,
             NotANumber(_)                       => BindError::NotANumber,
             BindingError(err)                   => err,
+            AtLocation(location, err)           => BindError::AtLocation(location, Box::new((*err).into())),
             ParseError(_)                       |
             StackIsEmpty                        |
             TypeMismatch(_)                     |
@@ -126,14 +159,29 @@ impl From<RuntimeError> for BindError {
             TooManyArguments(_)                 |
             NotAMonad(_)                        |
             NotAString(_)                       |
+            NotAChar(_)                         |
             NotABTree(_)                        |
             MismatchedMonad(_)                  |
             NotALabel(_)                        |
             NotBitCode(_)                       |
             CannotAllocateLabelsDuringAssembly  |
             BeforeStartOfFile                   |
-            TooManyPasses(_)                    |
+            TooManyPasses(_, _)                 |
+            UnstableLabels(_)                   |
+            OutputTooLarge(_, _)                |
+            RecursionLimitReached(_)            |
             CannotCompare(_, _)                 |
+            AddressTooLarge(_)                  |
+            ValueOutOfRange(_, _)               |
+            UserError(_)                        |
+            ReadPastEndOfFile(_, _)             |
+            UnknownOption(_)                    |
+            IllegalOpcode(_, _)                 |
+            CycleLimitReached(_)                |
+            DivisionByZero                      |
+            IndexOutOfRange(_)                  |
+            RegionOverflow(_, _)                |
+            OverlappingCode(_, _, _)            |
             NotEnoughArguments(_)               => BindError::RuntimeError
         }
     }
@@ -3,11 +3,15 @@ use super::bind_error::*;
 use super::binding_monad::*;
 use super::binding_monad_sugar::*;
 use super::compile_statement::*;
+use super::syntax_compiler::*;
 
 use crate::meta::*;
 use crate::exec::*;
 use crate::syntax::*;
+use crate::parse::*;
+use crate::bitcode::*;
 
+use smallvec::*;
 use std::sync::*;
 use std::result::{Result};
 
@@ -48,7 +52,24 @@ pub fn bind_statement(source: CellRef, bindings: SymbolBindings) -> BindResult<C
 
     match &*source {
         // Lists are processed according to their first value
-        List(car, cdr)  => { bind_list_statement(Arc::clone(car), Arc::clone(cdr), bindings) }
+        List(car, cdr)  => {
+            if let Some(location) = bindings.source_locations.location(&source) {
+                // Statements with a known location are marked so the bitcode they generate can be traced back to the source
+                let marker_id               = reserve_source_marker();
+                let (bound, bindings)       = match bind_list_statement(Arc::clone(car), Arc::clone(cdr), bindings) {
+                    Ok(result)              => result,
+                    Err((err, bindings))    => return Err((err.with_location(location), bindings))
+                };
+
+                if bound.reference_type() == ReferenceType::Monad {
+                    Ok((mark_source(bound, marker_id, location), bindings))
+                } else {
+                    Ok((bound, bindings))
+                }
+            } else {
+                bind_list_statement(Arc::clone(car), Arc::clone(cdr), bindings)
+            }
+        }
 
         // Atoms bind to their atom value
         Atom(atom_id)   => {
@@ -137,6 +158,25 @@ pub fn bind_statement(source: CellRef, bindings: SymbolBindings) -> BindResult<C
     }
 }
 
+///
+/// Wraps a bound statement that generates a monad so that any bitcode it generates is surrounded by source markers
+///
+fn mark_source(bound_statement: CellRef, marker_id: u64, location: Arc<SourceLocation>) -> CellRef {
+    let compile = move |bound_statement: CellRef| {
+        let mut actions = CompiledActions::empty();
+
+        // Call the marker function with the monad generated by the statement
+        actions.push(Action::PushValue(source_marker_fn(marker_id, location.clone())));
+        actions.extend(compile_statement(bound_statement)?);
+        actions.push(Action::Push);
+        actions.push(Action::PopCall(1));
+
+        Ok(actions)
+    };
+
+    SafasCell::BoundSyntax(SyntaxCompiler::with_compiler_and_reftype(compile, bound_statement, ReferenceType::Monad)).into()
+}
+
 ///
 /// Binds a list statement, like `(cons 1 2)`
 ///
@@ -287,6 +327,37 @@ fn bind_call(load_fn: CellRef, args: CellRef, bindings: SymbolBindings) -> BindR
     }
 }
 
+///
+/// Binds a call to a function that accepts keyword options, like `(run_6502 program :load $200)`
+///
+/// Atoms starting with ':' in the argument list name the options and are passed to the function as atoms instead of
+/// being looked up. Only calls bound this way accept them: anywhere else they are reported as unknown symbols.
+///
+pub fn bind_call_with_options(load_fn: CellRef, args: CellRef, bindings: SymbolBindings) -> BindResult<CellRef> {
+    bind_call(load_fn, quote_options(args), bindings)
+}
+
+///
+/// Replaces the option names in an argument list with bound syntax that loads the name as an atom
+///
+fn quote_options(args: CellRef) -> CellRef {
+    match &*args {
+        SafasCell::List(car, cdr) => {
+            let is_option   = match &**car { SafasCell::Atom(atom_id) => name_for_atom_with_id(*atom_id).starts_with(':'), _ => false };
+            let car         = if is_option {
+                let load_option = |option: CellRef| Ok(smallvec![Action::Value(option)].into());
+                SafasCell::BoundSyntax(SyntaxCompiler::with_compiler(load_option, car.clone())).into()
+            } else {
+                car.clone()
+            };
+
+            SafasCell::List(car, quote_options(cdr.clone())).into()
+        }
+
+        _ => args
+    }
+}
+
 ///
 /// Given a partially bound function with a monad parameter, rewrites it as a flat_map binding
 /// 
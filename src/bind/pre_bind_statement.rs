@@ -23,7 +23,7 @@ pub fn pre_bind_statement(source: CellRef, bindings: SymbolBindings) -> (SymbolB
             // Look up the value for this symbol
             let symbol_value = bindings.look_up(*atom_id);
 
-            if let Some((symbol_value, _symbol_level)) = symbol_value {
+            if let Some((symbol_value, symbol_level)) = symbol_value {
                 use self::SafasCell::*;
 
                 match &*symbol_value {
@@ -39,10 +39,22 @@ pub fn pre_bind_statement(source: CellRef, bindings: SymbolBindings) -> (SymbolB
                     List(_, _)              |
                     Monad(_, _)             |
                     FrameMonad(_)           |
-                    Syntax(_, _)            |
                     BoundSyntax(_)          |
                     Error(_)                |
                     FrameReference(_, _, _) => (bindings, symbol_value),
+
+                    // Syntax items can perform custom prebinding when they're used on their own too
+                    Syntax(syntax_compiler, _)  => {
+                        let mut bindings        = bindings.push_interior_frame();
+                        bindings.args           = None;
+                        bindings.depth          = Some(symbol_level);
+                        let (bindings, _bound)  = syntax_compiler.pre_bind(bindings);
+                        let (bindings, imports) = bindings.pop();
+
+                        if imports.len() > 0 { panic!("Should be no imports when pre-binding"); }
+
+                        (bindings, symbol_value)
+                    }
                 }
             } else {
                 // Not a valid symbol, or not defined yet
@@ -1,4 +1,6 @@
 use crate::meta::*;
+use crate::exec::*;
+use crate::parse::*;
 
 use smallvec::*;
 use std::sync::*;
@@ -31,7 +33,16 @@ pub struct SymbolBindings {
     pub num_cells: usize,
 
     /// True if this is an 'interior' binding (shares its cells with its parent)
-    pub is_interior: bool
+    pub is_interior: bool,
+
+    /// The limits that apply while binding (shared with the bindings this was created from)
+    pub limits: SharedLimits,
+
+    /// The number of macro expansions that are being bound (these bindings are inside all of them)
+    pub macro_depth: usize,
+
+    /// The locations that the statements being bound were read from (shared with the bindings this was created from)
+    pub source_locations: SourceLocations
 }
 
 impl SymbolBindings {
@@ -40,14 +51,17 @@ impl SymbolBindings {
     ///
     pub fn new() -> SymbolBindings {
         SymbolBindings {
-            args:           None,
-            depth:          None,
-            symbols:        HashMap::new(),
-            export_symbols: smallvec![],
-            import_symbols: smallvec![],
-            parent:         None,
-            num_cells:      1,
-            is_interior:    false
+            args:               None,
+            depth:              None,
+            symbols:            HashMap::new(),
+            export_symbols:     smallvec![],
+            import_symbols:     smallvec![],
+            parent:             None,
+            num_cells:          1,
+            is_interior:        false,
+            limits:             SharedLimits::default(),
+            macro_depth:        0,
+            source_locations:   SourceLocations::new()
         }
     }
 
@@ -94,14 +108,17 @@ impl SymbolBindings {
     ///
     pub fn push_new_frame(self) -> SymbolBindings {
         SymbolBindings {
-            args:           None,
-            depth:          None,
-            symbols:        HashMap::new(),
-            export_symbols: smallvec![],
-            import_symbols: smallvec![],
-            parent:         Some(Box::new(self)),
-            num_cells:      1,
-            is_interior:    false
+            args:               None,
+            depth:              None,
+            symbols:            HashMap::new(),
+            export_symbols:     smallvec![],
+            import_symbols:     smallvec![],
+            limits:             self.limits.clone(),
+            macro_depth:        self.macro_depth,
+            source_locations:   self.source_locations.clone(),
+            parent:             Some(Box::new(self)),
+            num_cells:          1,
+            is_interior:        false
         }
     }
 
@@ -110,14 +127,17 @@ impl SymbolBindings {
     ///
     pub fn push_interior_frame(self) -> SymbolBindings {
         SymbolBindings {
-            args:           None,
-            depth:          None,
-            symbols:        HashMap::new(),
-            export_symbols: smallvec![],
-            import_symbols: smallvec![],
-            num_cells:      self.num_cells,
-            parent:         Some(Box::new(self)),
-            is_interior:    true
+            args:               None,
+            depth:              None,
+            symbols:            HashMap::new(),
+            export_symbols:     smallvec![],
+            import_symbols:     smallvec![],
+            num_cells:          self.num_cells,
+            limits:             self.limits.clone(),
+            macro_depth:        self.macro_depth,
+            source_locations:   self.source_locations.clone(),
+            parent:             Some(Box::new(self)),
+            is_interior:        true
         }
     }
 
@@ -0,0 +1,251 @@
+use super::label_syntax::*;
+
+use crate::bind::*;
+use crate::meta::*;
+
+use std::sync::*;
+
+lazy_static! {
+    /// The atom that stores the anonymous labels for a block of statements
+    static ref ANONYMOUS_LABELS: u64 = get_id_for_atom_with_name("##anonymous_labels##");
+
+    /// The ID to assign to the next anonymous label
+    static ref NEXT_ANONYMOUS_LABEL_ID: Mutex<u64> = Mutex::new(0);
+}
+
+///
+/// The anonymous labels declared in a block of statements
+///
+/// Anonymous labels are pre-bound along with the rest of the block, so `next` starts out containing all of the labels in
+/// the block. As each label is bound, it moves to the start of `previous`.
+///
+struct AnonymousLabels {
+    /// The labels that have been bound so far (most recent first)
+    previous: Vec<CellRef>,
+
+    /// The labels that have yet to be bound (in the order they appear)
+    next: Vec<CellRef>
+}
+
+impl AnonymousLabels {
+    ///
+    /// Reads the anonymous labels from the value stored in a binding
+    ///
+    fn from_cell(labels: Option<&CellRef>) -> AnonymousLabels {
+        let labels = labels.and_then(|labels| labels.to_vec()).unwrap_or_else(|| vec![]);
+
+        if labels.len() == 2 {
+            AnonymousLabels {
+                previous:   labels[0].to_vec().unwrap_or_else(|| vec![]),
+                next:       labels[1].to_vec().unwrap_or_else(|| vec![])
+            }
+        } else {
+            AnonymousLabels { previous: vec![], next: vec![] }
+        }
+    }
+
+    ///
+    /// Retrieves the anonymous labels for the block that an anonymous label is being bound in
+    ///
+    /// Syntax is bound in an interior frame, so the block is the parent of the bindings that are passed in
+    ///
+    fn for_block(bindings: &SymbolBindings) -> AnonymousLabels {
+        AnonymousLabels::from_cell(bindings.parent.as_ref().and_then(|block| block.symbols.get(&*ANONYMOUS_LABELS)))
+    }
+
+    ///
+    /// Retrieves the anonymous labels that are visible from the specified bindings (those declared by the innermost
+    /// block that has any)
+    ///
+    fn visible_from(bindings: &SymbolBindings) -> AnonymousLabels {
+        let mut binding = Some(bindings);
+
+        while let Some(current_binding) = binding {
+            if let Some(labels) = current_binding.symbols.get(&*ANONYMOUS_LABELS) {
+                return AnonymousLabels::from_cell(Some(labels));
+            }
+
+            binding = current_binding.parent.as_ref().map(|parent| &**parent);
+        }
+
+        AnonymousLabels { previous: vec![], next: vec![] }
+    }
+
+    ///
+    /// Stores these labels as the anonymous labels for the block that the specified bindings are for
+    ///
+    fn store(&self, bindings: &mut SymbolBindings) {
+        let previous    = SafasCell::list_with_cells(self.previous.iter().cloned());
+        let next        = SafasCell::list_with_cells(self.next.iter().cloned());
+
+        bindings.symbols.insert(*ANONYMOUS_LABELS, SafasCell::list_with_cells(vec![previous, next]));
+        bindings.export(*ANONYMOUS_LABELS);
+    }
+}
+
+///
+/// Creates a new atom to use as the name of an anonymous label
+///
+/// The names can't be generated by the tokenizer, so they'll never clash with a label declared in the source
+///
+fn new_anonymous_label_name() -> CellRef {
+    let mut next_id = NEXT_ANONYMOUS_LABEL_ID.lock().unwrap();
+    let id          = *next_id;
+    (*next_id)      += 1;
+
+    SafasCell::atom(&format!(":{}", id))
+}
+
+///
+/// Binding monad for the `:` keyword
+///
+struct AnonymousLabel;
+
+impl BindingMonad for AnonymousLabel {
+    type Binding = SyntaxCompiler;
+
+    fn description(&self) -> String { "##anonymous_label##".to_string() }
+
+    fn pre_bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Self::Binding) {
+        // Add a new label to the end of the list for this block
+        let mut bindings    = bindings;
+        let mut labels      = AnonymousLabels::for_block(&bindings);
+        let label_name      = new_anonymous_label_name();
+
+        labels.next.push(label_name.clone());
+        labels.store(&mut bindings);
+
+        // Pre-bind as if this were `(label <name>)`
+        bindings.args       = Some(SafasCell::list_with_cells(vec![label_name]));
+        label_keyword().pre_bind(bindings)
+    }
+
+    fn bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Result<Self::Binding, BindError>) {
+        // ':' is used on its own
+        if !bindings.args.is_none() { return (bindings, Err(BindError::TooManyArguments)); }
+
+        // This label is the first one that has not been bound yet
+        let mut bindings    = bindings;
+        let mut labels      = AnonymousLabels::for_block(&bindings);
+        if labels.next.len() == 0 { return (bindings, Err(BindError::UnknownSymbol(":".to_string()))); }
+
+        let label_name      = labels.next.remove(0);
+        labels.previous.insert(0, label_name.clone());
+        labels.store(&mut bindings);
+
+        // Bind as if this were `(label <name>)`
+        bindings.args       = Some(SafasCell::list_with_cells(vec![label_name]));
+        label_keyword().bind(bindings)
+    }
+
+    fn reference_type(&self, _bound_value: CellRef) -> ReferenceType {
+        ReferenceType::Monad
+    }
+}
+
+///
+/// Binding monad for the `:+` and `:-` keywords
+///
+struct AnonymousLabelReference {
+    /// The name of this reference (eg, `:++`)
+    name: String,
+
+    /// The label that this refers to (1 for the next label, -1 for the previous label, etc)
+    offset: isize
+}
+
+impl BindingMonad for AnonymousLabelReference {
+    type Binding = SyntaxCompiler;
+
+    fn description(&self) -> String { self.name.clone() }
+
+    fn pre_bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Self::Binding) {
+        (bindings, SyntaxCompiler::default())
+    }
+
+    fn bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Result<Self::Binding, BindError>) {
+        // This is expected to be used as like a variable
+        if !bindings.args.is_none() { return (bindings, Err(BindError::ConstantsCannotBeCalled)); }
+
+        // Find the label that this refers to
+        let labels      = AnonymousLabels::visible_from(&bindings);
+        let label_name  = if self.offset > 0 {
+            labels.next.get((self.offset - 1) as usize)
+        } else {
+            labels.previous.get((-self.offset - 1) as usize)
+        };
+        let label_name  = match label_name { Some(label_name) => label_name.clone(), None => return (bindings, Err(BindError::UnknownSymbol(self.name.clone()))) };
+
+        // Bind to the label
+        match bind_statement(label_name, bindings) {
+            Ok((label, bindings))   => (bindings, Ok(SyntaxCompiler::with_compiler_and_reftype(|label| compile_statement(label), label, ReferenceType::Monad))),
+            Err((err, bindings))    => (bindings, Err(err))
+        }
+    }
+
+    fn reference_type(&self, _bound_value: CellRef) -> ReferenceType {
+        ReferenceType::Monad
+    }
+}
+
+///
+/// The `:` keyword declares an anonymous label at the current position
+///
+/// Anonymous labels work like the unnamed labels in ca65: `:+` refers to the next anonymous label in the same block of
+/// statements, and `:-` refers to the previous one. `:++` and `:--` refer to the label after the next one and the label
+/// before the previous one, and so on. For example, this loops until the X register reaches 0:
+///
+/// ```(ldx #10) : (dex) (bne :-)```
+///
+pub fn anonymous_label_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    AnonymousLabel
+}
+
+///
+/// Creates the syntax for an anonymous label reference, like `:+` (offset 1) or `:--` (offset -2)
+///
+pub fn anonymous_label_reference(offset: isize) -> impl BindingMonad<Binding=SyntaxCompiler> {
+    let direction   = if offset > 0 { "+" } else { "-" };
+    let name        = format!(":{}", direction.repeat(offset.abs() as usize));
+
+    AnonymousLabelReference { name: name, offset: offset }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+    use crate::test_support::{assemble_expr, cpu_session};
+
+    #[test]
+    fn previous_anonymous_label() {
+        // Without a label_value function, label values are bit positions
+        let bytes = assemble_expr("(d 1u8) : (d 2u8) (d (bits 8 :-))");
+        assert!(bytes == "01 02 08".to_string());
+    }
+
+    #[test]
+    fn next_anonymous_label() {
+        let bytes = assemble_expr("(d (bits 8 :+)) (d 2u8) : (d 3u8)");
+        assert!(bytes == "10 02 03".to_string());
+    }
+
+    #[test]
+    fn references_skip_labels() {
+        let bytes = assemble_expr(": (d 1u8) : (d 2u8) (d (bits 8 :--)) (d (bits 8 :-)) (d (bits 8 :++)) : (d 4u8) : (d 5u8)");
+        assert!(bytes == "01 02 00 08 30 04 05".to_string());
+    }
+
+    #[test]
+    fn missing_anonymous_label() {
+        let result = eval("(d 1u8) (d (bits 8 :+))");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn anonymous_labels_6502() {
+        let mut session = cpu_session("6502");
+        let assembly    = session.assemble("(assemble_6502 (set_ip $200) (ldx #3) : (dex) (bne :-) (beq :+) (nop) : (rts))").unwrap();
+
+        assert!(assembly.bytes() == vec![0xa2, 0x03, 0xca, 0xd0, 0xfd, 0xf0, 0x01, 0xea, 0x60]);
+    }
+}
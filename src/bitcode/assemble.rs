@@ -1,15 +1,22 @@
 use super::code::*;
 use super::label::*;
 use super::bitcode_monad::*;
+use super::listing::*;
+use super::label_values::*;
+use super::diagnostic::*;
+use super::section::*;
+use super::memory_region::*;
 
 use crate::meta::*;
 use crate::exec::*;
+use crate::parse::*;
 
 use std::mem;
+use std::sync::*;
 use std::collections::{HashMap, HashSet};
 
-/// The maximum number of assembly passes we should attempt before deciding that a bitcode monad cannot be evaluated
-const DEFAULT_MAX_PASSES: usize = 1000;
+/// The number of values to report for each label when labels fail to stabilise
+const REPORTED_LABEL_VALUES: usize = 8;
 
 ///
 /// Represents an assembler that is running
@@ -18,34 +25,94 @@ struct Assembler {
     /// Known values for the labels
     label_values: HashMap<Label, CellRef>,
 
+    /// The labels in the order their values were first set
+    label_order: Vec<Label>,
+
+    /// The length of the bitcode each time a label was set
+    label_positions: Vec<(usize, Label)>,
+
     /// Labels that have changed this pass
     changed_labels: HashSet<Label>,
 
     /// Bitcode that has been generated
     bitcode: Vec<BitCode>,
 
-    /// The current bitcode position
+    /// The current bitcode position (within the current section)
     bit_pos: u64,
 
     /// The current offset, added to bit_pos when generating label values
     bit_offset: i64,
 
+    /// The section that bitcode is currently being generated for
+    current_section: u64,
+
+    /// The bit position and offset of the sections other than the current section
+    section_positions: HashMap<u64, (u64, i64)>,
+
+    /// The changes to the offset that have been made, as the length of the bitcode when the change was made and the new offset
+    offset_changes: Vec<(usize, i64)>,
+
+    /// The locations of the source markers that have been started but not finished (used to report where errors occur)
+    open_source_markers: Vec<Arc<SourceLocation>>,
+
+    /// The locations of the source markers that were open the last time a label changed its value
+    label_change_markers: Vec<Arc<SourceLocation>>,
+
+    /// Errors that will be reported if they're still generated by the final pass
+    deferred_errors: Vec<RuntimeError>,
+
+    /// Messages that will be displayed if they're still generated by the final pass
+    diagnostics: Vec<Diagnostic>,
+
+    /// The memory regions that have been declared
+    regions: Vec<MemoryRegion>,
+
+    /// The values each label has been set to, in order (used to detect labels that oscillate between values)
+    label_history: HashMap<Label, Vec<CellRef>>,
+
+    /// True if grow_only values can only increase (set once a label has been found to oscillate)
+    grow_only: bool,
+
+    /// The largest value returned by each grow_only value, indexed by the order they're evaluated in during a pass
+    grow_only_values: HashMap<usize, CellRef>,
+
+    /// The index of the next grow_only value to be evaluated in the current pass
+    next_grow_only: usize,
+
     /// The maximum number of passes we should attempt
-    max_passes: usize
+    max_passes: usize,
+
+    /// The maximum size of the output, in bytes
+    max_output_size: u64
 }
 
 impl Assembler {
     ///
-    /// Creates a new assembler
+    /// Creates a new assembler that will stay within the specified limits
     ///
-    fn new() -> Assembler {
+    fn with_limits(limits: &Limits) -> Assembler {
         Assembler {
             label_values:   HashMap::new(),
+            label_order:    vec![],
+            label_positions: vec![],
             changed_labels: HashSet::new(),
             bitcode:        vec![],
             bit_pos:        0,
             bit_offset:     0,
-            max_passes:     DEFAULT_MAX_PASSES
+            current_section: *DEFAULT_SECTION,
+            section_positions: HashMap::new(),
+            offset_changes: vec![],
+            open_source_markers: vec![],
+            label_change_markers: vec![],
+            deferred_errors: vec![],
+            diagnostics:    vec![],
+            regions:        vec![],
+            label_history:  HashMap::new(),
+            grow_only:      false,
+            grow_only_values: HashMap::new(),
+            next_grow_only: 0,
+            max_passes:     limits.max_passes,
+            max_output_size: limits.max_output_size
         }
     }
 
@@ -93,22 +160,91 @@ impl Assembler {
         let label = self.get_label(label_cell)?;
 
         // If the label already has a value, check if it's the same as the existing value
-        if let Some(last_value) = self.label_values.get(&label) {
-            if (&**last_value) != (&*value) {
-                self.changed_labels.insert(label);
-            }
+        let changed = if let Some(last_value) = self.label_values.get(&label) {
+            (&**last_value) != (&*value)
         } else {
             // First time the label has been set, so mark it as changed
+            self.label_order.push(label);
+            true
+        };
+
+        if changed {
             self.changed_labels.insert(label);
+            self.label_change_markers = self.open_source_markers.clone();
+
+            // A label that returns to an earlier value is oscillating, which will happen forever unless instruction sizes stop shrinking
+            let history         = self.label_history.entry(label).or_insert_with(|| vec![]);
+            let oscillating     = history.iter().any(|old_value| (&**old_value) == (&*value));
+            history.push(value.clone());
+
+            if oscillating {
+                if self.grow_only {
+                    return Err(RuntimeError::UnstableLabels(self.label_values_history(&self.changed_labels)));
+                }
+
+                self.start_grow_only();
+            }
         }
 
         // Update the label value
         self.label_values.insert(label, value.clone());
+        self.label_positions.push((self.bitcode.len(), label));
 
         // Result is the value
         Ok(value)
     }
 
+    ///
+    /// Switches to grow-only mode, where grow_only values can't become smaller than the values they had in earlier passes
+    ///
+    fn start_grow_only(&mut self) {
+        self.grow_only = true;
+
+        // Labels are only considered to be oscillating if they return to a value they had after the switch
+        for history in self.label_history.values_mut() {
+            let last_value = history.pop();
+            history.clear();
+            history.extend(last_value);
+        }
+    }
+
+    ///
+    /// Evaluates a grow_only value
+    ///
+    fn grow_only_value(&mut self, value: CellRef) -> CellRef {
+        let index           = self.next_grow_only;
+        self.next_grow_only += 1;
+
+        // Values that can't be compared with the largest value so far replace it
+        let largest_value   = match self.grow_only_values.get(&index) {
+            Some(largest_value) if (&**largest_value) > (&*value)   => largest_value.clone(),
+            _                                                       => value.clone()
+        };
+        self.grow_only_values.insert(index, largest_value.clone());
+
+        if self.grow_only {
+            largest_value
+        } else {
+            value
+        }
+    }
+
+    ///
+    /// Returns the names of a set of labels along with the most recent values they were set to (in the order the labels were first set)
+    ///
+    fn label_values_history(&self, labels: &HashSet<Label>) -> Vec<(String, Vec<CellRef>)> {
+        self.label_order.iter()
+            .filter(|label| labels.contains(label))
+            .map(|label| {
+                let name    = label.name().unwrap_or_else(|| "(unnamed)".to_string());
+                let history = self.label_history.get(label).map(|history| &history[..]).unwrap_or(&[]);
+                let history = history[history.len().saturating_sub(REPORTED_LABEL_VALUES)..].to_vec();
+
+                (name, history)
+            })
+            .collect()
+    }
+
     ///
     /// Appends bitcode to this element 
     ///
@@ -118,10 +254,28 @@ impl Assembler {
             BitCodeContent::Value(bitcode)  => {
                 self.bit_pos = BitCode::position_after(self.bit_pos, bitcode.iter());
                 self.bitcode.extend(bitcode.iter().cloned());
+
+                // Track the statements that we're generating bitcode for
+                for code in bitcode.iter() {
+                    match code {
+                        BitCode::BeginSource(_, location)   => self.open_source_markers.push(location.clone()),
+                        BitCode::EndSource                  => { self.open_source_markers.pop(); },
+                        _                                   => { }
+                    }
+                }
             }
         }
     }
 
+    ///
+    /// Attaches the locations of the statements for a set of source markers to an error
+    ///
+    fn locate_error(error: RuntimeError, source_markers: &Vec<Arc<SourceLocation>>) -> RuntimeError {
+        source_markers.iter()
+            .rev()
+            .fold(error, |error, location| error.with_location(location.clone()))
+    }
+
     ///
     /// Assembles a single monad using this assembler, returning the monad's value
     ///
@@ -129,13 +283,13 @@ impl Assembler {
         // Append the initial bitcode
         self.append_bitcode(&monad.bitcode);
 
-        // Work out the value depending on the content of the monad
+        // Work out the value depending on the content of the monad (the following bitcode is not generated if there's an error)
         let result = match &monad.value {
             // Simple value
             BitCodeValue::Value(value)                      => Ok(value.clone()),
 
             // Allocates a new label
-            BitCodeValue::AllocLabel                        => Err(RuntimeError::CannotAllocateLabelsDuringAssembly),
+            BitCodeValue::AllocLabel(_, _)                  => Err(RuntimeError::CannotAllocateLabelsDuringAssembly),
 
             // Looks up a label value (or prepares a second pass if the label has no value yet)
             BitCodeValue::LabelValue(value)                 => self.get_label_value(value),
@@ -166,10 +320,52 @@ impl Assembler {
                 let offset      = (value as i64) - (self.bit_pos as i64);
 
                 self.bit_offset = offset;
+                self.offset_changes.push((self.bitcode.len(), offset));
+
+                Ok(NIL.clone())
+            },
+
+            BitCodeValue::SetSection(section)               => {
+                if *section != self.current_section {
+                    // Store the position of the current section and restore the position of the new one
+                    self.section_positions.insert(self.current_section, (self.bit_pos, self.bit_offset));
+
+                    let (bit_pos, bit_offset)   = self.section_positions.remove(section).unwrap_or((0, 0));
+                    self.bit_pos                = bit_pos;
+                    self.bit_offset             = bit_offset;
+                    self.current_section        = *section;
+
+                    // Mark where the section changes so the sections can be placed after assembly
+                    self.bitcode.push(BitCode::Section(*section));
+                }
+
+                Ok(NIL.clone())
+            },
+
+            BitCodeValue::DeclareRegion(region)             => {
+                self.regions.push(region.clone());
+
+                Ok(NIL.clone())
+            },
+
+            BitCodeValue::Diagnostic(level, message)        => {
+                // The message is reported at the innermost statement with a known location
+                let location = self.open_source_markers.last().cloned();
+
+                self.diagnostics.push(Diagnostic { level: *level, message: message.clone(), location: location });
 
                 Ok(NIL.clone())
             },
 
+            BitCodeValue::DeferredError(value, error)       => {
+                // Errors are only reported after the final pass (as label values might not be correct before then)
+                self.deferred_errors.push(Self::locate_error(error.clone(), &self.open_source_markers));
+
+                Ok(value.clone())
+            },
+
+            BitCodeValue::GrowOnly(value)                   => Ok(self.grow_only_value(value.clone())),
+
             // Value is the result of applying the mapping function to the specified monad, and then trying again with the current monad
             BitCodeValue::FlatMap(monad, mappings)          => {
                 // About to call assemble recursively: create a new set of changed labels
@@ -178,7 +374,17 @@ impl Assembler {
 
                 // Loop until the labels in the flat_mapped section acquire stable values
                 let initial_bit_pos     = self.bit_pos;
+                let initial_bit_offset  = self.bit_offset;
+                let initial_section     = self.current_section;
+                let initial_positions   = self.section_positions.clone();
                 let initial_code_len    = self.bitcode.len();
+                let initial_offsets_len = self.offset_changes.len();
+                let initial_markers     = self.open_source_markers.clone();
+                let initial_errors_len  = self.deferred_errors.len();
+                let initial_diagnostics = self.diagnostics.len();
+                let initial_regions     = self.regions.len();
+                let initial_label_pos   = self.label_positions.len();
+                let initial_grow_only   = self.next_grow_only;
                 let mut passes          = 0;
                 let mut value;
                 loop {
@@ -200,13 +406,25 @@ impl Assembler {
                     // Limit the number of passes we can perform
                     passes += 1;
                     if passes > self.max_passes {
-                        return Err(RuntimeError::TooManyPasses(self.max_passes));
+                        // Report the error at the last label that changed its value
+                        let labels = self.label_values_history(&self.changed_labels);
+                        return Err(Self::locate_error(RuntimeError::TooManyPasses(self.max_passes, labels), &self.label_change_markers));
                     }
 
                     // Reset for the next pass
                     self.changed_labels = HashSet::new();
                     self.bit_pos        = initial_bit_pos;
+                    self.bit_offset     = initial_bit_offset;
+                    self.current_section = initial_section;
+                    self.section_positions = initial_positions.clone();
                     self.bitcode.truncate(initial_code_len);
+                    self.offset_changes.truncate(initial_offsets_len);
+                    self.open_source_markers = initial_markers.clone();
+                    self.deferred_errors.truncate(initial_errors_len);
+                    self.diagnostics.truncate(initial_diagnostics);
+                    self.regions.truncate(initial_regions);
+                    self.label_positions.truncate(initial_label_pos);
+                    self.next_grow_only = initial_grow_only;
                 }
 
                 // Reset with the labels from this level of recursion
@@ -215,39 +433,168 @@ impl Assembler {
                 // Final value from the final monad
                 Ok(value)
             }
-        };
+        }?;
 
         // Append the following bitcode if there is any
         self.append_bitcode(&monad.following_bitcode);
 
         // Return the value we generated
-        result
+        Ok(result)
+    }
+}
+
+///
+/// Checks that some bitcode will not generate more output than the maximum output size
+///
+/// Memory regions are filled in after assembly, so the end of the last region is also included in the size
+///
+fn check_output_size(bitcode: &Vec<BitCode>, regions: &Vec<MemoryRegion>, max_output_size: u64) -> Result<(), RuntimeError> {
+    // Moves can go backwards, so the size is set by the furthest position that the bitcode reaches
+    let mut pos         = 0;
+    let mut max_pos     = 0;
+    for code in bitcode.iter() {
+        pos             = BitCode::position_after(pos, Some(code));
+        max_pos         = u64::max(max_pos, pos);
+    }
+
+    let size            = (max_pos + 7) / 8;
+    let size            = regions.iter().fold(size, |size, region| u64::max(size, region.start.saturating_add(region.size)));
+
+    if size > max_output_size {
+        Err(RuntimeError::OutputTooLarge(size, max_output_size))
+    } else {
+        Ok(())
     }
 }
 
 ///
-/// Assembles the bitcode generated by a bitcode monad, producing the final bitcode
+/// Assembles the bitcode generated by a bitcode monad, producing the final bitcode and the values of the labels
+///
+/// The default limits are used, and any messages generated by `print` or `warn` are discarded: use `assemble_with_listing`
+/// to choose the limits or to retrieve the messages
+///
+pub fn assemble(monad: &BitCodeMonad) -> Result<(CellRef, Vec<BitCode>, LabelValues), RuntimeError> {
+    let mut diagnostics                             = vec![];
+    let (value, bitcode, label_values, _listing)    = assemble_with_listing(monad, &Limits::default(), &mut diagnostics)?;
+
+    Ok((value, bitcode, label_values))
+}
+
+///
+/// Assembles the bitcode generated by a bitcode monad, producing the final bitcode, the values of the labels and a
+/// listing relating the source code to the addresses and bytes it generated
 ///
-pub fn assemble(monad: &BitCodeMonad) -> Result<(CellRef, Vec<BitCode>), RuntimeError> {
+/// The messages generated by the final assembly pass are added to the diagnostics (this happens even if the assembly
+/// finishes with an error generated by the `error` function), followed by a summary of the usage of any memory regions
+/// that were declared. The assembler stops with an error if it goes over the passes or output size set in the limits.
+///
+pub fn assemble_with_listing(monad: &BitCodeMonad, limits: &Limits, diagnostics: &mut Vec<Diagnostic>) -> Result<(CellRef, Vec<BitCode>, LabelValues, Listing), RuntimeError> {
     // Create an assembler, and assemble this monad
-    let mut assembler   = Assembler::new();
-    let value           = assembler.assemble(monad)?;
+    let mut assembler   = Assembler::with_limits(limits);
+    let value           = match assembler.assemble(monad) {
+        Ok(value)   => value,
+        Err(err)    => return Err(Assembler::locate_error(err, &assembler.open_source_markers))
+    };
+
+    // Messages from the final pass are reported to the caller
+    diagnostics.extend(assembler.diagnostics);
+
+    // Report any errors that were generated by the final pass
+    if let Some(error) = assembler.deferred_errors.into_iter().nth(0) {
+        return Err(error);
+    }
+
+    // Place the sections to generate the final bitcode (checking that it's not too large before generating any bytes)
+    let (bitcode, section_starts)   = layout_sections(&assembler.bitcode);
+    check_output_size(&bitcode, &assembler.regions, assembler.max_output_size)?;
+
+    // The source markers are used to generate the listing
+    let listing                     = Listing::from_bitcode(&assembler.bitcode, &assembler.offset_changes);
+
+    // Check the output against the memory regions, and report how much of each region was used
+    let region_usage    = check_memory_regions(&assembler.bitcode, &section_starts, &assembler.label_positions, &assembler.regions)?;
+    let bitcode         = fill_memory_regions(bitcode, &assembler.regions);
+
+    diagnostics.extend(region_usage.into_iter()
+        .map(|usage| Diagnostic { level: DiagnosticLevel::Message, message: usage.to_string(), location: None }));
+
+    let label_values    = LabelValues::new(assembler.label_order, assembler.label_values);
 
-    Ok((value, assembler.bitcode))
+    Ok((value, bitcode, label_values, listing))
 }
 
 #[cfg(test)]
 mod test {
+    use crate::meta::*;
+    use crate::exec::*;
     use crate::interactive::*;
     use crate::bitcode::*;
+    use crate::test_support::{cpu_session};
+
+    ///
+    /// Creates a monad that sets a label to a new value calculated from its previous value
+    ///
+    fn update_label<TFn: 'static+Fn(u128) -> u128+Send+Sync>(name: &str, update: TFn) -> BitCodeMonad {
+        let update = std::sync::Arc::new(update);
+
+        BitCodeMonad::alloc_label(Some(get_id_for_atom_with_name(name)), None).flat_map(move |label| {
+            let update = update.clone();
+
+            BitCodeMonad::read_label_value(label.clone()).flat_map(move |value| {
+                let value = value.number_value().map(|value| value.to_u128()).unwrap_or(0);
+                Ok(BitCodeMonad::set_label_value(label.clone(), SafasCell::Number(SafasNumber::Plain(update(value))).into()))
+            })
+        }).unwrap()
+    }
 
     #[test]
     fn return_value_from_assembler() {
         let result          = eval("((fun () (d 0u64) 1u64))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (val, _bitcode) = assemble(&monad).unwrap();
+        let (val, _bitcode, _labels) = assemble(&monad).unwrap();
 
         assert!(val.to_string() == "$1u64".to_string());
     }
+
+    #[test]
+    fn report_unstable_labels() {
+        // Label flips between 1 and 0 forever
+        let monad   = update_label("flip", |value| 1 - value);
+        let result  = assemble(&monad);
+
+        match result {
+            Err(RuntimeError::UnstableLabels(labels))   => {
+                assert!(labels.len() == 1);
+                assert!(labels[0].0 == "flip".to_string());
+                assert!(labels[0].1.iter().map(|value| value.to_string()).collect::<Vec<_>>() == vec!["1", "0", "1"]);
+            }
+            _                                           => assert!(false)
+        }
+    }
+
+    #[test]
+    fn report_labels_that_never_stabilise() {
+        // Label increases every pass
+        let monad   = update_label("count", |value| value + 1);
+        let result  = assemble(&monad);
+
+        match result {
+            Err(RuntimeError::TooManyPasses(_, labels)) => {
+                assert!(labels.len() == 1);
+                assert!(labels[0].0 == "count".to_string());
+                assert!(labels[0].1.len() == 8);
+            }
+            _                                           => assert!(false)
+        }
+    }
+
+    #[test]
+    fn oscillating_zero_page_settles_on_larger_size_6502() {
+        // The zero page version makes the address $100, and the absolute version makes it $ff, so this never stabilises unless the size can only grow
+        let mut session = cpu_session("6502");
+        let assembly    = session.assemble("(assemble_6502 (lda (- $102 end)) (label end))").unwrap();
+
+        assert!(assembly.bytes() == vec![0xad, 0xff, 0x00]);
+    }
 }
@@ -1,5 +1,7 @@
 use super::code::*;
 use super::bitcode_monad::*;
+use super::diagnostic::*;
+use super::memory_region::*;
 
 use crate::meta::*;
 use crate::exec::*;
@@ -35,6 +37,9 @@ impl FrameMonad for BitCodeFlatMap {
         // Fetch the mapping function to apply (this is a function of the form 'x -> <Monad>', where <Monad> is another bitcode monad)
         let map_fn = args.map_fn;
 
+        // The map function is called while assembling, but should be limited in the same way as the code that called flat_map
+        let limits = frame.limits.clone();
+
         // Applying the map function should return the updated monad
         let next                = bitcode_monad.flat_map(move |val| {
             // Fetch the map function
@@ -46,6 +51,7 @@ impl FrameMonad for BitCodeFlatMap {
             // Create a new frame to execute the map function on and execute it
             let mut frame           = Frame::new(1, None);
             frame.cells[0]          = SafasCell::List(val, NIL.clone()).into();
+            frame.limits            = limits.clone();
             let (_frame, next)      = map_fn.execute(frame);
 
             let next                = match next { Ok(next) => next, Err(err) => return Err(err) };
@@ -81,6 +87,39 @@ pub fn bitcode_flat_map_fn() -> impl FrameMonad<Binding=RuntimeResult> {
     BitCodeFlatMap
 }
 
+///
+/// Converts the values passed to the `d` function to the bitcode that writes them out
+///
+fn data_bitcode(values: Vec<CellRef>) -> Result<Vec<BitCode>, RuntimeError> {
+    use self::SafasNumber::*;
+    use self::BitCode::Bits;
+
+    let bitcode = values.into_iter().map(|value| {
+        match &*value {
+            SafasCell::Number(num) => {
+                match *num {
+                    Plain(val)                      => Ok(vec![Bits(32, val)]),
+                    BitNumber(bit_count, val)       => Ok(vec![Bits(bit_count, val)]),
+                    SignedBitNumber(bit_count, val) => Ok(vec![Bits(bit_count, val as u128)])
+                }
+            },
+
+            SafasCell::String(string) => {
+                Ok(iter::once(BitCode::Align(8, 0, 8))
+                    .chain(string.bytes()
+                        .map(|byte| Bits(8, byte as u128)))
+                    .collect())
+            },
+
+            SafasCell::Nil => { Ok(vec![Bits(32, 0)]) },
+
+            _ => Err(RuntimeError::NotANumber(value))
+        }
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(bitcode.into_iter().flatten().collect())
+}
+
 ///
 /// The 'd' data output function
 /// 
@@ -88,35 +127,33 @@ pub fn bitcode_flat_map_fn() -> impl FrameMonad<Binding=RuntimeResult> {
 /// 
 pub fn d_fn() -> impl FrameMonad<Binding=RuntimeResult> {
     ReturnsMonad(FnMonad::from(|values: Vec<CellRef>| {
-        use self::SafasNumber::*;
-        use self::BitCode::Bits;
-
         // Generate the bitcode
-        let bitcode = values.into_iter().map(|value| {
-            match &*value {
-                SafasCell::Number(num) => {
-                    match *num {
-                        Plain(val)                      => Ok(vec![Bits(32, val)]),
-                        BitNumber(bit_count, val)       => Ok(vec![Bits(bit_count, val)]),
-                        SignedBitNumber(bit_count, val) => Ok(vec![Bits(bit_count, val as u128)])
-                    }
-                },
-
-                SafasCell::String(string) => {
-                    Ok(iter::once(BitCode::Align(8, 0, 8))
-                        .chain(string.bytes()
-                            .map(|byte| Bits(8, byte as u128)))
-                        .collect())
-                },
-
-                SafasCell::Nil => { Ok(vec![Bits(32, 0)]) },
-
-                _ => Err(RuntimeError::NotANumber(value))
-            }
-        }).collect::<Result<Vec<_>, _>>()?;
+        let bitcode = data_bitcode(values)?;
 
         // Create a bitcode monad cell
-        let bitcode_monad   = BitCodeMonad::write_bitcode(bitcode.into_iter().flatten());
+        let bitcode_monad   = BitCodeMonad::write_bitcode(bitcode);
+        Ok(bitcode_monad.to_cell())
+    }))
+}
+
+///
+/// The 'd_msb' data output function, which writes values most significant bit first
+/// 
+/// `(d_msb 1u3 2u5)` fills bytes from the highest bit down, so this writes the byte $22. Values of more than 8 bits are written
+/// with their most significant byte first (ie, big-endian)
+/// 
+pub fn d_msb_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    ReturnsMonad(FnMonad::from(|values: Vec<CellRef>| {
+        // Generate the bitcode, and convert it to be written most significant bit first
+        let bitcode = data_bitcode(values)?;
+        let bitcode = bitcode.into_iter()
+            .map(|code| match code {
+                BitCode::Bits(bit_count, val)   => BitCode::MsbBits(bit_count, val),
+                other                           => other
+            });
+
+        // Create a bitcode monad cell
+        let bitcode_monad   = BitCodeMonad::write_bitcode(bitcode);
         Ok(bitcode_monad.to_cell())
     }))
 }
@@ -201,9 +238,116 @@ pub fn bit_pos_fn() -> impl FrameMonad<Binding=RuntimeResult>  {
     }))
 }
 
+///
+/// The 'grow_only' function
+///
+/// `(grow_only 16)` returns 16. If the assembler finds labels that keep returning to earlier values, it switches to a
+/// grow-only mode where this returns the largest value that it has returned in any earlier pass, so a choice of
+/// instruction size can't flip back to a smaller size.
+///
+pub fn grow_only_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    ReturnsMonad(FnMonad::from(|(value, ): (CellRef, )| {
+        // Create a bitcode monad cell
+        let bitcode_monad   = BitCodeMonad::grow_only(value);
+        bitcode_monad.to_cell()
+    }))
+}
+
+///
+/// The 'region' function
+///
+/// `(region "rom" $0 $4000 $ff =f)` declares that the output from byte $0 to byte $3fff is a region called 'rom'. Code
+/// that starts in the region must not continue past its end, and any bytes in the region that are not written are set to
+/// $ff. The last value indicates if code in the region can overwrite code that was generated earlier. The fill byte (0 by
+/// default) and the overlap flag (=f by default) are optional. The name can be a string or an atom.
+///
+pub fn region_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    ReturnsMonad(FnMonad::from(|args: Vec<CellRef>| {
+        if args.len() < 3 { return Err(RuntimeError::NotEnoughArguments(SafasCell::list_with_cells(args))); }
+        if args.len() > 5 { return Err(RuntimeError::TooManyArguments(SafasCell::list_with_cells(args))); }
+
+        let name            = match &*args[0] {
+            SafasCell::String(name) => name.clone(),
+            SafasCell::Atom(atom)   => name_for_atom_with_id(*atom),
+            _                       => return Err(RuntimeError::NotAString(args[0].clone()))
+        };
+        let start           = args[1].number_value().ok_or(RuntimeError::NotANumber(args[1].clone()))?;
+        let size            = args[2].number_value().ok_or(RuntimeError::NotANumber(args[2].clone()))?;
+        let fill            = args.get(3).map(|fill| fill.number_value().ok_or(RuntimeError::NotANumber(fill.clone()))).unwrap_or(Ok(SafasNumber::Plain(0)))?;
+        let allow_overlap   = args.get(4).map(|overlap| overlap.bool_value().ok_or(RuntimeError::TypeMismatch(overlap.clone()))).unwrap_or(Ok(false))?;
+
+        let region          = MemoryRegion {
+            name:           name,
+            start:          start.to_usize() as u64,
+            size:           size.to_usize() as u64,
+            fill:           fill.to_usize() as u8,
+            allow_overlap:  allow_overlap
+        };
+
+        // Create a bitcode monad cell
+        let bitcode_monad   = BitCodeMonad::declare_region(region);
+        Ok(bitcode_monad.to_cell())
+    }))
+}
+
+///
+/// Generates the text of a message from the arguments passed to `print`, `warn` or `error`
+///
+/// Strings are written out as they are, other values are converted to their textual representation, and the values are
+/// separated by spaces
+///
+fn message_text(values: Vec<CellRef>) -> String {
+    values.into_iter()
+        .map(|value| {
+            match &*value {
+                SafasCell::String(string)   => string.clone(),
+                _                           => value.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+///
+/// The 'print' function
+///
+/// `(print "Hello")` displays a message to the user when the code is assembled
+///
+pub fn print_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    ReturnsMonad(FnMonad::from(|values: Vec<CellRef>| {
+        let bitcode_monad   = BitCodeMonad::diagnostic(DiagnosticLevel::Message, message_text(values));
+        bitcode_monad.to_cell()
+    }))
+}
+
+///
+/// The 'warn' function
+///
+/// `(warn "Oops")` displays a warning along with the location of the statement that generated it when the code is assembled
+///
+pub fn warn_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    ReturnsMonad(FnMonad::from(|values: Vec<CellRef>| {
+        let bitcode_monad   = BitCodeMonad::diagnostic(DiagnosticLevel::Warning, message_text(values));
+        bitcode_monad.to_cell()
+    }))
+}
+
+///
+/// The 'error' function
+///
+/// `(error "Doh")` causes assembly to fail with a UserError if it's still generated by the final assembly pass
+///
+pub fn error_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    ReturnsMonad(FnMonad::from(|values: Vec<CellRef>| {
+        let bitcode_monad   = BitCodeMonad::with_deferred_error(NIL.clone(), RuntimeError::UserError(message_text(values)));
+        bitcode_monad.to_cell()
+    }))
+}
+
 #[cfg(test)]
 mod test {
     use crate::meta::*;
+    use crate::exec::*;
     use crate::bitcode::*;
     use crate::interactive::*;
 
@@ -212,7 +356,7 @@ mod test {
         let result          = eval("((fun () (d $9fu8)))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode == &vec![BitCode::Bits(8, 0x9f)])
     }
@@ -222,17 +366,26 @@ mod test {
         let result          = eval("((fun () (d $9fu8)))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (value, _)      = assemble(&monad).unwrap();
+        let (value, _, _)   = assemble(&monad).unwrap();
 
         assert!(value.to_string() == "()".to_string());
     }
 
+    #[test]
+    fn write_data_msb_first() {
+        let result          = eval("((fun () (d_msb 1u3 2u5 $1234u16)))").unwrap();
+        let monad           = BitCodeMonad::from_cell(&result).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
+
+        assert!(bitcode_to_bytes(bitcode) == vec![0x22, 0x12, 0x34]);
+    }
+
     #[test]
     fn write_data_byte_from_monad() {
         let result          = eval("((fun () (d (wrap $9fu8))))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode == &vec![BitCode::Bits(8, 0x9f)])
     }
@@ -242,7 +395,7 @@ mod test {
         let result          = eval("(def x (wrap $9fu8)) ((fun () (d x)))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode == &vec![BitCode::Bits(8, 0x9f)])
     }
@@ -252,7 +405,7 @@ mod test {
         let result          = eval("((fun () (d (wrap $9fu8))))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (value, _)      = assemble(&monad).unwrap();
+        let (value, _, _)   = assemble(&monad).unwrap();
 
         assert!(value.to_string() == "()".to_string());
     }
@@ -262,7 +415,7 @@ mod test {
         let result          = eval("((fun () (d $9fu8) (d $1c42u16)))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode == &vec![BitCode::Bits(8, 0x9f), BitCode::Bits(16, 0x1c42)])
     }
@@ -272,7 +425,7 @@ mod test {
         let result          = eval("((fun () (d (wrap $9fu8)) (d (wrap $1c42u16))))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode == &vec![BitCode::Bits(8, 0x9f), BitCode::Bits(16, 0x1c42)])
     }
@@ -282,7 +435,7 @@ mod test {
         let result          = eval("((fun () (d (wrap $9fu8)) (d (wrap $1c42u16))))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (value, _)      = assemble(&monad).unwrap();
+        let (value, _, _)   = assemble(&monad).unwrap();
 
         assert!(value.to_string() == "()".to_string());
     }
@@ -292,7 +445,7 @@ mod test {
         let result          = eval("((fun () (label foo) (d foo)))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode == &vec![BitCode::Bits(64, 0)])
     }
@@ -302,7 +455,7 @@ mod test {
         let result          = eval("(set_bit_pos $8000) (label foo) (d foo)").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode == &vec![BitCode::Bits(64, 0x8000)])
     }
@@ -312,7 +465,7 @@ mod test {
         let result          = eval("(set_bit_pos $8000) (label foo) (d foo) (bit_pos)").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (value, _)      = assemble(&monad).unwrap();
+        let (value, _, _)   = assemble(&monad).unwrap();
 
         assert!(value.number_value() == Some(SafasNumber::BitNumber(64, 0x8040)));
     }
@@ -332,7 +485,7 @@ mod test {
             )").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (value, _)      = assemble(&monad).unwrap();
+        let (value, _, _)   = assemble(&monad).unwrap();
 
         assert!(value.number_value() == Some(SafasNumber::BitNumber(64, 0x8040)));
     }
@@ -352,7 +505,7 @@ mod test {
             )").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (value, _)      = assemble(&monad).unwrap();
+        let (value, _, _)   = assemble(&monad).unwrap();
 
         assert!(value.number_value() == Some(SafasNumber::BitNumber(64, 0x8040)));
     }
@@ -362,7 +515,7 @@ mod test {
         let result          = eval("((fun () (d $9fu8 $1c42u16)))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode == &vec![BitCode::Bits(8, 0x9f), BitCode::Bits(16, 0x1c42)])
     }
@@ -372,7 +525,7 @@ mod test {
         let result          = eval("((fun () (m $c001)))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Move(0xc001)])
     }
@@ -382,7 +535,7 @@ mod test {
         let result          = eval("((fun () (a $beeff00du32 64)))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Align(32, 0xbeeff00d, 64)])
     }
@@ -392,7 +545,7 @@ mod test {
         let result          = eval("(d $42u32) (label foo) (d (bits 32 foo))").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Bits(32, 0x42), BitCode::Bits(32, 0x20)])
     }
@@ -402,7 +555,7 @@ mod test {
         let result          = eval("(d $42u32) (d (bits 32 foo)) (label foo)").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Bits(32, 0x42), BitCode::Bits(32, 0x40)])
     }
@@ -412,7 +565,7 @@ mod test {
         let result          = eval("(d $42u32) (label bar) (d (bits 32 foo)) (label foo)").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Bits(32, 0x42), BitCode::Bits(32, 0x40)])
     }
@@ -422,7 +575,7 @@ mod test {
         let result          = eval("(d $42u32) (label bar) (d (bits 32 bar)) (d (bits 32 foo)) (label foo)").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Bits(32, 0x42), BitCode::Bits(32, 0x20), BitCode::Bits(32, 0x60)])
     }
@@ -441,7 +594,7 @@ mod test {
             )").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Bits(32, 0x42), BitCode::Bits(32, 0x40)])
     }
@@ -460,7 +613,7 @@ mod test {
             )").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Bits(32, 0x42), BitCode::Bits(32, 0x40)])
     }
@@ -479,7 +632,7 @@ mod test {
             )").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Bits(32, 0x42), BitCode::Bits(32, 0x20)])
     }
@@ -500,7 +653,7 @@ mod test {
             )").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Bits(32, 0x42), BitCode::Bits(32, 0x20), BitCode::Bits(32, 0x00)])
     }
@@ -521,7 +674,7 @@ mod test {
             )").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Bits(32, 0x42), BitCode::Bits(32, 0x40), BitCode::Bits(32, 0x60)])
     }
@@ -542,7 +695,7 @@ mod test {
             )").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Align(32, 0, 8), BitCode::Bits(32, 0x42), BitCode::Align(32, 0, 8), BitCode::Bits(32, 0x60), BitCode::Align(32, 0, 8), BitCode::Bits(32, 0x20)])
     }
@@ -554,8 +707,43 @@ mod test {
         ").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (_, bitcode)    = assemble(&monad).unwrap();
+        let (_, bitcode, _) = assemble(&monad).unwrap();
 
         assert!(&bitcode ==  &vec![BitCode::Align(8, 0, 8), BitCode::Bits(8, 0x48), BitCode::Bits(8, 0x65), BitCode::Bits(8, 0x6c), BitCode::Bits(8, 0x6c), BitCode::Bits(8, 0x6f)])
     }
+
+    #[test]
+    fn print_message() {
+        let result          = eval("(d $1u8) (print \"Value is\" $42u8)").unwrap();
+        let monad           = BitCodeMonad::from_cell(&result).unwrap();
+        let mut messages    = vec![];
+
+        assemble_with_listing(&monad, &Limits::default(), &mut messages).unwrap();
+
+        assert!(messages.len() == 1);
+        assert!(messages[0].report() == "Value is $42u8\n".to_string());
+    }
+
+    #[test]
+    fn warn_only_on_final_pass() {
+        let result          = eval("(label start) (if (= end start) ((warn \"Empty\")) ((warn \"Not empty\"))) (d $1u8) (label end)").unwrap();
+        let monad           = BitCodeMonad::from_cell(&result).unwrap();
+        let mut messages    = vec![];
+
+        assemble_with_listing(&monad, &Limits::default(), &mut messages).unwrap();
+
+        assert!(messages.len() == 1);
+        assert!(messages[0].level == DiagnosticLevel::Warning);
+        assert!(messages[0].message == "Not empty".to_string());
+        assert!(messages[0].location.as_ref().unwrap().text == "(warn \"Not empty\")".to_string());
+    }
+
+    #[test]
+    fn error_stops_assembly() {
+        let result          = eval("(d $1u8)\n(error \"Doh\")").unwrap();
+        let monad           = BitCodeMonad::from_cell(&result).unwrap();
+        let error           = assemble(&monad).unwrap_err();
+
+        assert!(error_report(&error) == "error: UserError(\"Doh\")\n --> <expr>:2:1\n  |\n2 | (error \"Doh\")\n  | ^\n".to_string());
+    }
 }
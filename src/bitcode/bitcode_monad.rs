@@ -1,5 +1,7 @@
 use super::code::*;
 use super::label::*;
+use super::diagnostic::*;
+use super::memory_region::*;
 use super::bitcode_functions::*;
 
 use crate::meta::*;
@@ -19,8 +21,8 @@ pub enum BitCodeValue {
     /// An absolute value
     Value(CellRef),
 
-    /// Allocates a label with the specified ID (replacing any previous label with this ID)
-    AllocLabel,
+    /// Allocates a label with the specified ID (replacing any previous label with this ID), with the atom ID of its name if it has one
+    AllocLabel(Option<u64>, Option<u64>),
 
     /// Reads the label (cell returned by AllocLabel)
     LabelValue(CellRef),
@@ -34,6 +36,21 @@ pub enum BitCodeValue {
     /// Sets the instruction pointer to the specified value (by updating the offset)
     SetBitPos(CellRef),
 
+    /// Switches the section that bitcode is generated in (the section name is an atom ID)
+    SetSection(u64),
+
+    /// Declares a region of memory that the output is checked against after assembly
+    DeclareRegion(MemoryRegion),
+
+    /// Generates a message to display to the user after the final assembly pass
+    Diagnostic(DiagnosticLevel, String),
+
+    /// A value along with an error that should be reported if it's still present after the final assembly pass
+    DeferredError(CellRef, RuntimeError),
+
+    /// A value (usually an instruction size) that can only increase once the assembler has found labels that oscillate between values
+    GrowOnly(CellRef),
+
     /// Value is the result of a chain of flat_map operations on a bitcode monad
     FlatMap(Arc<BitCodeMonad>, Vec<Arc<dyn Fn(CellRef) -> Result<BitCodeMonad, RuntimeError>+Send+Sync>>)
 }
@@ -44,11 +61,16 @@ impl Debug for BitCodeValue {
 
         match self {
             Value(value)                => write!(fmt, "Value({})", value.to_string()),
-            AllocLabel                  => write!(fmt, "AllocLabel"),
+            AllocLabel(_, _)            => write!(fmt, "AllocLabel"),
             LabelValue(value)           => write!(fmt, "LabelValue({})", value.to_string()),
             SetLabelValue(label, value) => write!(fmt, "SetLabelValue({}, {})", label.to_string(), value.to_string()),
             BitPos                      => write!(fmt, "BitPos"),
             SetBitPos(value)            => write!(fmt, "SetBitPos({})", value.to_string()),
+            SetSection(section)         => write!(fmt, "SetSection({})", name_for_atom_with_id(*section)),
+            DeclareRegion(region)       => write!(fmt, "DeclareRegion({:?})", region),
+            Diagnostic(level, message)  => write!(fmt, "Diagnostic({:?}, {:?})", level, message),
+            DeferredError(value, error) => write!(fmt, "DeferredError({}, {:?})", value.to_string(), error),
+            GrowOnly(value)             => write!(fmt, "GrowOnly({})", value.to_string()),
             FlatMap(monad, flat_map)    => write!(fmt, "FlatMap({:?}, [{}])", monad, flat_map.len())
         }
     }
@@ -192,9 +214,12 @@ impl BitCodeMonad {
     ///
     /// Creates a new bitcode monad that means 'allocate a new label'
     ///
-    pub fn alloc_label() -> BitCodeMonad {
+    /// The name is the atom ID that the label was declared with and the scope is the atom ID of the name of the scope it
+    /// was declared in: these are used when generating symbol tables
+    ///
+    pub fn alloc_label(name: Option<u64>, scope: Option<u64>) -> BitCodeMonad {
         BitCodeMonad {
-            value:              BitCodeValue::AllocLabel,
+            value:              BitCodeValue::AllocLabel(name, scope),
             bitcode:            BitCodeContent::Empty,
             following_bitcode:  BitCodeContent::Empty
         }
@@ -233,6 +258,66 @@ impl BitCodeMonad {
         }
     }
 
+    ///
+    /// Creates a bitcode monad that switches the section that bitcode is generated in
+    ///
+    pub fn set_section(section: u64) -> BitCodeMonad {
+        BitCodeMonad {
+            value:              BitCodeValue::SetSection(section),
+            bitcode:            BitCodeContent::Empty,
+            following_bitcode:  BitCodeContent::Empty
+        }
+    }
+
+    ///
+    /// Creates a bitcode monad that declares a memory region
+    ///
+    pub fn declare_region(region: MemoryRegion) -> BitCodeMonad {
+        BitCodeMonad {
+            value:              BitCodeValue::DeclareRegion(region),
+            bitcode:            BitCodeContent::Empty,
+            following_bitcode:  BitCodeContent::Empty
+        }
+    }
+
+    ///
+    /// Creates a bitcode monad that displays a message to the user (if it's generated by the final assembly pass)
+    ///
+    pub fn diagnostic(level: DiagnosticLevel, message: String) -> BitCodeMonad {
+        BitCodeMonad {
+            value:              BitCodeValue::Diagnostic(level, message),
+            bitcode:            BitCodeContent::Empty,
+            following_bitcode:  BitCodeContent::Empty
+        }
+    }
+
+    ///
+    /// Creates a bitcode monad that wraps a value, and reports an error if it's still generated after the final assembly pass
+    ///
+    /// This is used for errors that depend on label values: these might be incorrect until the labels have stabilised
+    ///
+    pub fn with_deferred_error(value: CellRef, error: RuntimeError) -> BitCodeMonad {
+        BitCodeMonad {
+            value:              BitCodeValue::DeferredError(value, error),
+            bitcode:            BitCodeContent::Empty,
+            following_bitcode:  BitCodeContent::Empty
+        }
+    }
+
+    ///
+    /// Creates a bitcode monad that wraps a value that is only allowed to grow if the assembler has to fall back to its grow-only mode
+    ///
+    /// Choices that affect the size of the generated code (such as whether or not to use a zero page instruction) should be made
+    /// using this, so that labels that would otherwise flip between two values can settle on the larger size
+    ///
+    pub fn grow_only(value: CellRef) -> BitCodeMonad {
+        BitCodeMonad {
+            value:              BitCodeValue::GrowOnly(value),
+            bitcode:            BitCodeContent::Empty,
+            following_bitcode:  BitCodeContent::Empty
+        }
+    }
+
     ///
     /// Creates a new bitcode monad that means 'set the value of the specified label to the value of the argument'
     ///
@@ -263,15 +348,46 @@ impl BitCodeMonad {
         }
     }
 
+    ///
+    /// Appends bitcode to the bitcode that is generated after this monad's value
+    ///
+    pub fn append_following_bitcode(&mut self, bitcode: BitCodeContent) {
+        match bitcode {
+            BitCodeContent::Empty           => { }
+            BitCodeContent::Value(bitcode)  => {
+                match self.following_bitcode.take() {
+                    BitCodeContent::Empty                   => { self.following_bitcode = BitCodeContent::Value(bitcode); }
+                    BitCodeContent::Value(mut our_bitcode)  => {
+                        our_bitcode.extend(bitcode);
+                        self.following_bitcode = BitCodeContent::Value(our_bitcode);
+                    }
+                }
+            }
+        }
+    }
+
+    ///
+    /// True if this monad has bitcode that should be generated after its value
+    ///
+    fn has_following_bitcode(&self) -> bool {
+        match self.following_bitcode {
+            BitCodeContent::Empty       => false,
+            BitCodeContent::Value(_)    => true
+        }
+    }
+
     ///
     /// Maps this monad by applying a function to the value it contains
     ///
     pub fn flat_map<TFn: 'static+Fn(CellRef) -> Result<BitCodeMonad, RuntimeError>+Send+Sync>(mut self, fun: TFn) -> Result<BitCodeMonad, RuntimeError> {
+        let has_following_bitcode = self.has_following_bitcode();
+
         match self.value {
-            BitCodeValue::AllocLabel => {
+            BitCodeValue::AllocLabel(name, scope) => {
                 // Labels are given a fixed value as soon as flat_map is called
-                let label       = SafasCell::Any(Box::new(Label::new()));
+                let label       = SafasCell::Any(Box::new(Label::with_name(name, scope)));
                 let mut next    = fun(label.into())?;
+                next.prepend_bitcode(self.following_bitcode);
                 next.prepend_bitcode(self.bitcode);
 
                 Ok(next)
@@ -280,19 +396,20 @@ impl BitCodeMonad {
             BitCodeValue::Value(const_value) => {
                 // If this just has a constant value, we can map the bitcode immediately and combine the bitcode to get the result
                 let mut next = fun(const_value)?;
+                next.prepend_bitcode(self.following_bitcode);
                 next.prepend_bitcode(self.bitcode);
 
                 Ok(next)
             },
 
-            BitCodeValue::FlatMap(initial, mut mappings) => {
+            BitCodeValue::FlatMap(initial, mut mappings) if !has_following_bitcode => {
                 mappings.push(Arc::new(fun));
                 self.value = BitCodeValue::FlatMap(initial, mappings);
                 Ok(self)
             },
 
             _ => {
-                // Return a flatmapped bitcode monad
+                // Return a flatmapped bitcode monad (this keeps any following bitcode ahead of the bitcode generated by the mapping function)
                 Ok(BitCodeMonad {
                     value:              BitCodeValue::FlatMap(Arc::new(self), vec![Arc::new(fun)]),
                     bitcode:            BitCodeContent::Empty,
@@ -1,18 +1,33 @@
+use crate::parse::*;
+
 use radix_fmt::*;
+use std::sync::*;
 
 ///
 /// The output of the assembler is a vector of bitcode, which forms a series of instructions for generating the final result
 /// 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum BitCode {
     /// Write out the specified set of bits and move the file offset forward that much
     Bits(u8, u128),
 
+    /// Write out the specified set of bits starting at the most significant bit, filling each byte from its highest bit down
+    MsbBits(u8, u128),
+
     /// Align to the specified number of bits
     Align(u8, u128, u32),
 
     /// Sets the file offset (in bits) for future instructions
-    Move(u64)
+    Move(u64),
+
+    /// Marks the start of the bitcode generated by a source statement (the ID is a source marker ID, followed by the location of the statement)
+    BeginSource(u64, Arc<SourceLocation>),
+
+    /// Marks the end of the bitcode generated by the most recent `BeginSource`
+    EndSource,
+
+    /// The bitcode that follows is part of the section with the specified name (an atom ID), until the next section marker
+    Section(u64)
 }
 
 impl BitCode {
@@ -25,8 +40,10 @@ impl BitCode {
 
         for code_point in bitcode {
             match code_point {
-                Bits(num_bits, _value)                  => pos += *num_bits as u64,
+                Bits(num_bits, _value)                  |
+                MsbBits(num_bits, _value)               => pos += *num_bits as u64,
                 Move(new_pos)                           => pos = *new_pos,
+                BeginSource(_, _) | EndSource | Section(_) => { },
                 Align(_bit_count, _pattern, alignment)  => {
                     let alignment   = *alignment as u64;
                     let offset      = pos % alignment;
@@ -38,14 +55,28 @@ impl BitCode {
         pos
     }
 
+    ///
+    /// True if this is a source marker (which generates no output and is only used to relate bitcode to the source)
+    ///
+    pub fn is_source_marker(&self) -> bool {
+        match self {
+            BitCode::BeginSource(_, _) | BitCode::EndSource => true,
+            _                                               => false
+        }
+    }
+
     ///
     /// Generates a string representation of this bitcode operation
     ///
     pub fn to_string(&self) -> String {
         match self {
             BitCode::Bits(num_bits, bits)                   => format!("d{}b{}", radix(*bits, 16), num_bits),
+            BitCode::MsbBits(num_bits, bits)                => format!("dm{}b{}", radix(*bits, 16), num_bits),
             BitCode::Align(num_bits, pattern, align_pos)    => format!("a{}({}b{})", align_pos, radix(*pattern, 16), num_bits),
-            BitCode::Move(pos)                              => format!("m{}", radix(*pos, 16))
+            BitCode::Move(pos)                              => format!("m{}", radix(*pos, 16)),
+            BitCode::BeginSource(marker_id, _location)      => format!("src{}", marker_id),
+            BitCode::EndSource                              => format!("endsrc"),
+            BitCode::Section(section)                       => format!("sec{}", section)
         }
     }
 }
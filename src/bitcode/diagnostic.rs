@@ -0,0 +1,43 @@
+use crate::exec::*;
+use crate::parse::*;
+
+use std::sync::*;
+
+///
+/// The kinds of message that can be generated during assembly
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiagnosticLevel {
+    /// A message generated by the `print` function
+    Message,
+
+    /// A warning generated by the `warn` function
+    Warning
+}
+
+///
+/// A message generated during the final pass of assembly
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// The kind of message this is
+    pub level: DiagnosticLevel,
+
+    /// The text of the message
+    pub message: String,
+
+    /// The statement that generated the message, if it's known
+    pub location: Option<Arc<SourceLocation>>
+}
+
+impl Diagnostic {
+    ///
+    /// Generates the text to display to the user for this diagnostic
+    ///
+    pub fn report(&self) -> String {
+        match self.level {
+            DiagnosticLevel::Message    => format!("{}\n", self.message),
+            DiagnosticLevel::Warning    => warning_report(&self.message, self.location.as_ref().map(|location| &**location))
+        }
+    }
+}
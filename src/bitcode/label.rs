@@ -1,3 +1,5 @@
+use crate::meta::*;
+
 use std::sync::*;
 
 lazy_static! {
@@ -10,14 +12,20 @@ lazy_static! {
 ///
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Label {
-    id: u64
+    id: u64,
+
+    /// The atom ID of the name this label was declared with in the source code, if it has one
+    name: Option<u64>,
+
+    /// The atom ID of the name of the scope this label was declared in (eg, `outer.inner`), if it was declared in one
+    scope: Option<u64>
 }
 
 impl Label {
     ///
-    /// Creates a new label with a unique ID
+    /// Creates a new label with a unique ID, the name (as an atom ID) it was declared with and the scope it was declared in
     ///
-    pub fn new() -> Label {
+    pub fn with_name(name: Option<u64>, scope: Option<u64>) -> Label {
         // Assign an ID to this label
         let mut next_id = NEXT_ID.lock().unwrap();
         let id          = *next_id;
@@ -25,7 +33,23 @@ impl Label {
 
         // Label is just the ID; we assign it while assembling the bitcode
         Label {
-            id: id
+            id:     id,
+            name:   name,
+            scope:  scope
         }
     }
+
+    ///
+    /// Retrieves the name this label was declared with, if it has one
+    ///
+    pub fn name(&self) -> Option<String> {
+        self.name.map(|name| name_for_atom_with_id(name))
+    }
+
+    ///
+    /// Retrieves the name of the scope this label was declared in, if it was declared in one
+    ///
+    pub fn scope(&self) -> Option<String> {
+        self.scope.map(|scope| name_for_atom_with_id(scope))
+    }
 }
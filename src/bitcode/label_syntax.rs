@@ -4,15 +4,13 @@ use super::bitcode_functions::*;
 use crate::bind::*;
 use crate::meta::*;
 use crate::exec::*;
+use crate::syntax::*;
 
 use smallvec::*;
 use std::convert::*;
 use std::iter;
 
 lazy_static! {
-    /// The alloc_label bitcode monad
-    static ref ALLOC_LABEL: CellRef = alloc_label();
-
     /// The wrap_value flat_map function (reads a value from a monad and stores it)
     static ref WRAP_VALUE: CellRef = wrap_value();
 
@@ -33,11 +31,11 @@ lazy_static! {
 }
 
 ///
-/// Creates the 'alloc_label' bitcode monad as a cell
+/// Creates the 'alloc_label' bitcode monad as a cell, for a label with the specified name, declared in the specified scope
 ///
-fn alloc_label() -> CellRef {
+fn alloc_label(name: u64, scope: Option<u64>) -> CellRef {
     // Basic alloc_label monad
-    let alloc_label = BitCodeMonad::alloc_label();
+    let alloc_label = BitCodeMonad::alloc_label(Some(name), scope);
 
     // Stuff into a cell with the any mapping
     let alloc_label = SafasCell::Any(Box::new(alloc_label)).into();
@@ -314,7 +312,14 @@ pub fn label_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
 
                 // TODO: check that we've got the reference we allocated in the pre-binding (if it's been rebound the label is invalid)
 
-                let result = SafasCell::list_with_cells(vec![reference.into(), label_value_fn, label_value_expr]);
+                // The label is stored as the frame reference for its cell (so it's substituted along with the other cells when this is used in a macro)
+                let reference = match &*reference { SafasCell::Syntax(_, cell_reference) => cell_reference.clone(), _ => return (bindings, Err(BindError::UnknownSymbol(name_for_atom_with_id(atom_id)))) };
+
+                // The label also records the scope it was declared in, for the symbol table
+                let scope  = current_scope_name(&bindings).map(|scope| SafasCell::Atom(scope).into()).unwrap_or_else(|| NIL.clone());
+
+                let label  = SafasCell::list_with_cells(vec![reference, SafasCell::Atom(atom_id).into(), scope]);
+                let result = SafasCell::list_with_cells(vec![label, label_value_fn, label_value_expr]);
                 (bindings, Ok(result))
             },
 
@@ -340,10 +345,8 @@ pub fn label_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
             // Compiling function: labels bind themselves to a monad that allocates/retrieves the label value at the start of the code block and just bind to the label value later on 
             let compiler = |value: CellRef| -> Result<_, BindError> {
                 // Results of the bindings is the cell reference
-                let ListTuple((label_action, default_label_value_fn, label_value_expr)): ListTuple<(CellRef, CellRef, CellRef)> = value.clone().try_into()?;
-
-                // The label should be bound to a syntax item, with the frame cell as the parameter
-                let cell_reference = match &*label_action { SafasCell::Syntax(_, cell_reference) => Ok(cell_reference.clone()), _ => Err(BindError::MissingArgument) }?;
+                let ListTuple((label, default_label_value_fn, label_value_expr)): ListTuple<(CellRef, CellRef, CellRef)> = value.clone().try_into()?;
+                let ListTuple((cell_reference, AtomId(label_name), scope)): ListTuple<(CellRef, AtomId, CellRef)> = label.try_into()?;
 
                 // Fetch out the frame reference
                 let (cell_id, frame_num, _) = cell_reference.frame_reference().ok_or(BindError::MissingArgument)?;
@@ -354,7 +357,7 @@ pub fn label_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
 
                 // Frame setup allocates the label. We use the cell ID as the label ID for updating it later
                 actions.frame_setup.extend(vec![
-                    Action::Value(ALLOC_LABEL.clone()),
+                    Action::Value(alloc_label(label_name, scope.to_atom_id())),
                     Action::Push,
                     Action::Value(WRAP_VALUE.clone()),
                     Action::FlatMap,
@@ -429,6 +432,7 @@ mod test {
     use crate::syntax::*;
     use crate::parse::*;
     use crate::functions::*;
+    use crate::test_support::{cpu_session};
 
     use std::sync::*;
 
@@ -494,7 +498,7 @@ mod test {
         let result          = eval("(label foo) foo").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (val, _bitcode) = assemble(&monad).unwrap();
+        let (val, _bitcode, _labels) = assemble(&monad).unwrap();
 
         assert!(val.to_string() == "$0u64".to_string());
     }
@@ -504,7 +508,7 @@ mod test {
         let result          = eval("(label foo) (list foo)").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (val, _bitcode) = assemble(&monad).unwrap();
+        let (val, _bitcode, _labels) = assemble(&monad).unwrap();
 
         assert!(val.to_string() == "($0u64)".to_string());
     }
@@ -514,7 +518,7 @@ mod test {
         let result          = eval("(d 5u8) (label foo) foo").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (val, _bitcode) = assemble(&monad).unwrap();
+        let (val, _bitcode, _labels) = assemble(&monad).unwrap();
 
         assert!(val.to_string() == "$8u64".to_string());
     }
@@ -532,7 +536,7 @@ mod test {
         ).unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (val, _bitcode) = assemble(&monad).unwrap();
+        let (val, _bitcode, _labels) = assemble(&monad).unwrap();
         println!("{:?}", val.to_string());
 
         assert!(val.to_string() == "$40u64".to_string());
@@ -548,7 +552,7 @@ mod test {
         ).unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (val, _bitcode) = assemble(&monad).unwrap();
+        let (val, _bitcode, _labels) = assemble(&monad).unwrap();
         println!("{:?}", val.to_string());
 
         assert!(val.to_string() == "$40u64".to_string());
@@ -567,7 +571,7 @@ mod test {
         ).unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (val, _bitcode) = assemble(&monad).unwrap();
+        let (val, _bitcode, _labels) = assemble(&monad).unwrap();
         println!("{:?}", val.to_string());
 
         assert!(val.to_string() == "$40u64".to_string());
@@ -578,7 +582,7 @@ mod test {
         let result          = eval("(d foo) (label foo) foo").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (val, _bitcode) = assemble(&monad).unwrap();
+        let (val, _bitcode, _labels) = assemble(&monad).unwrap();
 
         // Labels are 64-bits so we should end up with a label position of 64 here
         assert!(val.to_string() == "$40u64".to_string());
@@ -589,9 +593,18 @@ mod test {
         let result          = eval("(d (bits 32 foo)) (label foo) foo").unwrap();
         let monad           = BitCodeMonad::from_cell(&result).unwrap();
 
-        let (val, _bitcode) = assemble(&monad).unwrap();
+        let (val, _bitcode, _labels) = assemble(&monad).unwrap();
 
         // Cut down to 32 bits, so we end up with a label position of 32
         assert!(val.to_string() == "$20u64".to_string());
     }
+
+    #[test]
+    fn labels_in_macros_6502() {
+        let mut session = cpu_session("6502");
+        session.eval("(def_syntax loops ((wait_x) ((assemble_6502 (label loop) (dex) (bne loop)))))").unwrap();
+        let assembly    = session.assemble("(assemble_6502 (set_ip $200) (loops (wait_x) (wait_x)))").unwrap();
+
+        assert!(assembly.bytes() == vec![0xca, 0xd0, 0xfd, 0xca, 0xd0, 0xfd]);
+    }
 }
@@ -0,0 +1,148 @@
+use super::label::*;
+
+use crate::meta::*;
+
+use std::collections::{HashMap};
+
+///
+/// A named label found in the label values, with the scope it was found in
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelSymbol {
+    /// The name the label was declared with
+    pub name: String,
+
+    /// The name of the scope the label was declared in (eg, `outer.inner`), if it was declared in one
+    pub scope: Option<String>,
+
+    /// The value of the label
+    pub value: u64
+}
+
+///
+/// The final values assigned to the labels in an assembled program
+///
+#[derive(Clone, Debug)]
+pub struct LabelValues {
+    /// The labels in the order that their values were first set
+    labels: Vec<Label>,
+
+    /// The value of each label
+    values: HashMap<Label, CellRef>
+}
+
+impl LabelValues {
+    ///
+    /// Creates a new set of label values (the labels are in the order they were first set during assembly)
+    ///
+    pub fn new(labels: Vec<Label>, values: HashMap<Label, CellRef>) -> LabelValues {
+        LabelValues {
+            labels: labels,
+            values: values
+        }
+    }
+
+    ///
+    /// Retrieves the labels and their values, in the order that they were set
+    ///
+    pub fn iter<'a>(&'a self) -> impl 'a+Iterator<Item=(Label, CellRef)> {
+        self.labels.iter()
+            .filter_map(move |label| self.values.get(label).map(|value| (*label, value.clone())))
+    }
+
+    ///
+    /// Returns the symbols for the labels that have a name and a numeric value
+    ///
+    /// Labels declared inside a `scope` keep the name of the scope they were declared in. A name that's used by more than
+    /// one label in the same scope (for example, a label declared in a function that is called several times) is given a
+    /// numeric suffix so that every symbol has a unique qualified name.
+    ///
+    pub fn symbols(&self) -> Vec<LabelSymbol> {
+        let mut symbols         = vec![];
+        let mut qualified_count = HashMap::new();
+
+        for (label, value) in self.iter() {
+            // Only named labels with a number value are symbols
+            let (name, value)   = match (label.name(), value.number_value()) {
+                (Some(name), Some(value))   => (name, value.to_usize() as u64),
+                _                           => continue
+            };
+
+            // Add a suffix if the qualified name is already in use
+            let symbol      = LabelSymbol { name: name, scope: label.scope(), value: value };
+            let count       = qualified_count.entry(symbol.qualified_name()).or_insert(0);
+            *count          += 1;
+
+            let symbol      = if *count > 1 {
+                LabelSymbol { name: format!("{}_{}", symbol.name, count), ..symbol }
+            } else {
+                symbol
+            };
+
+            symbols.push(symbol);
+        }
+
+        symbols
+    }
+}
+
+impl LabelSymbol {
+    ///
+    /// Returns the name of this symbol, qualified by its scope
+    ///
+    pub fn qualified_name(&self) -> String {
+        match &self.scope {
+            Some(scope) => format!("{}.{}", scope, self.name),
+            None        => self.name.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+    use crate::bitcode::*;
+
+    fn symbols_for(expr: &str) -> Vec<String> {
+        let result              = eval(expr).unwrap();
+        let monad               = BitCodeMonad::from_cell(&result).unwrap();
+        let (_, _, labels)      = assemble(&monad).unwrap();
+
+        labels.symbols().into_iter()
+            .map(|symbol| format!("{}={}", symbol.qualified_name(), symbol.value))
+            .collect()
+    }
+
+    #[test]
+    fn label_symbols_have_names() {
+        let symbols = symbols_for("(d $42u8) (label first) (d $42u16) (label second)");
+
+        assert!(symbols == vec!["first=8".to_string(), "second=24".to_string()]);
+    }
+
+    #[test]
+    fn labels_keep_their_scope() {
+        let symbols = symbols_for("
+            (label start)
+            (scope first (label loop) (d $1u8))
+            (scope second (label loop) (d $2u8) (scope inner (label loop) (d $3u8)))");
+
+        assert!(symbols == vec![
+            "start=0".to_string(), "first.loop=0".to_string(),
+            "second.loop=8".to_string(), "second.inner.loop=16".to_string()
+        ]);
+    }
+
+    #[test]
+    fn repeated_labels_get_a_suffix() {
+        let symbols = symbols_for("
+            (def with_loop (fun (x) (label loop) (d x)))
+            (label first) (with_loop $1u8)
+            (label second) (with_loop $2u8) (scope third (with_loop $3u8) (with_loop $4u8))");
+
+        assert!(symbols == vec![
+            "first=0".to_string(), "loop=0".to_string(),
+            "second=8".to_string(), "loop_2=8".to_string(), "loop_3=16".to_string(), "loop_4=24".to_string()
+        ]);
+    }
+}
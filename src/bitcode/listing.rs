@@ -0,0 +1,309 @@
+use super::code::*;
+use super::to_bytes::*;
+use super::section::*;
+
+use crate::parse::*;
+
+use std::iter;
+use std::sync::*;
+use std::collections::{HashMap};
+
+/// The number of bytes to show on each line of a listing
+const BYTES_PER_LINE: usize = 8;
+
+///
+/// A statement found while reading the source markers from some bitcode
+///
+struct ListingEntry {
+    /// The ID of the source marker for this entry
+    marker_id: u64,
+
+    /// The index of the entry that this entry is the display target for (the entry whose line will show the bytes generated for this entry)
+    display_entry: usize,
+
+    /// True if this entry has been folded into its parent
+    folded: bool,
+
+    /// Where this entry is in the source code
+    location: Option<Arc<SourceLocation>>,
+
+    /// The address where this entry started
+    start_address: u64,
+
+    /// The addresses and positions within the output (in bytes) of the bytes that are displayed against this entry
+    bytes: Vec<(u64, usize)>
+}
+
+///
+/// A line in an assembly listing
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListingLine {
+    /// The statement that generated the line
+    pub location: Arc<SourceLocation>,
+
+    /// The address of the first byte generated by the statement
+    pub address: u64,
+
+    /// The addresses and values of the bytes generated by the statement
+    pub bytes: Vec<(u64, u8)>
+}
+
+///
+/// An assembly listing, relating source statements to the addresses and bytes that they generated
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Listing {
+    lines: Vec<ListingLine>
+}
+
+impl Listing {
+    ///
+    /// Creates a listing from some bitcode containing source markers
+    ///
+    /// The offset changes are the bitcode index and new offset for each time the bit position was changed by
+    /// `SetBitPos` (these are used to calculate the address of each statement).
+    ///
+    /// Statements that were bound before the statement that contains them come from the body of a macro or a
+    /// function: these are folded into the statement that contains them, so the listing shows where the macro or
+    /// function was used rather than where it was defined. Statements on the same line as the statement that
+    /// contains them are folded in the same way.
+    ///
+    /// The bit position and offset are tracked separately for each section, in the same way as they are during
+    /// assembly.
+    ///
+    pub fn from_bitcode(bitcode: &Vec<BitCode>, offset_changes: &Vec<(usize, i64)>) -> Listing {
+        let (output, section_starts) = layout_sections(bitcode);
+
+        let mut entries             = Vec::<ListingEntry>::new();
+        let mut open_entries        = vec![];
+        let mut offset_changes      = offset_changes.iter().peekable();
+        let mut bit_pos             = 0u64;
+        let mut bit_offset          = 0i64;
+        let mut current_section     = *DEFAULT_SECTION;
+        let mut section_positions   = HashMap::new();
+
+        let address_for_bit_pos = |bit_pos: u64, bit_offset: i64| ((bit_pos as i64) + bit_offset).max(0) as u64 / 8;
+
+        for (code_idx, code) in bitcode.iter().enumerate() {
+            // Apply any changes to the offset that happened before this code
+            while let Some((change_idx, new_offset)) = offset_changes.peek() {
+                if *change_idx > code_idx { break; }
+
+                bit_offset = *new_offset;
+                offset_changes.next();
+            }
+
+            match code {
+                BitCode::BeginSource(marker_id, location) => {
+                    // Work out where the bytes for this entry should be displayed
+                    let entry_idx   = entries.len();
+                    let location    = Some(location.clone());
+                    let parent      = open_entries.last().map(|parent_idx: &usize| &entries[*parent_idx]);
+
+                    let (display_entry, folded) = match parent {
+                        Some(parent) => {
+                            let same_line = match (&location, &entries[parent.display_entry].location) {
+                                (Some(location), Some(parent_location)) => same_source_line(location, parent_location),
+                                _                                       => false
+                            };
+
+                            if location.is_none() || parent.folded || same_line || *marker_id < parent.marker_id {
+                                (parent.display_entry, true)
+                            } else {
+                                (entry_idx, false)
+                            }
+                        },
+
+                        None => (entry_idx, false)
+                    };
+
+                    entries.push(ListingEntry {
+                        marker_id:      *marker_id,
+                        display_entry:  display_entry,
+                        folded:         folded,
+                        location:       location,
+                        start_address:  address_for_bit_pos(bit_pos, bit_offset),
+                        bytes:          vec![]
+                    });
+                    open_entries.push(entry_idx);
+                },
+
+                BitCode::EndSource => {
+                    open_entries.pop();
+                },
+
+                BitCode::Section(section) => {
+                    // Each section has its own position
+                    section_positions.insert(current_section, (bit_pos, bit_offset));
+
+                    let (new_bit_pos, new_bit_offset) = section_positions.remove(section).unwrap_or((0, 0));
+                    bit_pos         = new_bit_pos;
+                    bit_offset      = new_bit_offset;
+                    current_section = *section;
+                },
+
+                code => {
+                    let next_bit_pos = BitCode::position_after(bit_pos, iter::once(code));
+
+                    // Moves just change the position: any other code that advances the position writes to the file
+                    if !matches!(code, BitCode::Move(_)) && next_bit_pos > bit_pos {
+                        if let Some(entry_idx) = open_entries.last() {
+                            let display_idx     = entries[*entry_idx].display_entry;
+                            let display_entry   = &mut entries[display_idx];
+                            let section_start   = (section_starts[&current_section]/8) as usize;
+                            let first_byte      = (bit_pos/8) as usize;
+                            let last_byte       = ((next_bit_pos-1)/8) as usize;
+
+                            for byte_pos in first_byte..=last_byte {
+                                // Codes that share a byte should only show it once
+                                let output_pos = section_start + byte_pos;
+                                if display_entry.bytes.last().map(|(_, last_pos)| *last_pos) == Some(output_pos) { continue; }
+
+                                let address = address_for_bit_pos((byte_pos*8) as u64, bit_offset);
+                                display_entry.bytes.push((address, output_pos));
+                            }
+                        }
+                    }
+
+                    bit_pos = next_bit_pos;
+                }
+            }
+        }
+
+        // Read the bytes that were generated
+        let file_bytes  = bitcode_to_bytes(output);
+
+        // Generate the lines from the entries that are not folded into another entry
+        let lines       = entries.into_iter()
+            .filter(|entry| !entry.folded)
+            .filter_map(|entry| {
+                let location    = entry.location?;
+                let address     = entry.bytes.first().map(|(address, _)| *address).unwrap_or(entry.start_address);
+                let bytes       = entry.bytes.into_iter()
+                    .map(|(address, byte_pos)| (address, file_bytes.get(byte_pos).cloned().unwrap_or(0)))
+                    .collect();
+
+                Some(ListingLine { location, address, bytes })
+            })
+            .collect::<Vec<_>>();
+
+        // Lines that generate no bytes are left out if they're next to a line with the same source line
+        let lines               = lines.iter().enumerate()
+            .filter(|(idx, line)| {
+                if !line.bytes.is_empty() { return true; }
+
+                let same_as_last = *idx > 0 && same_source_line(&line.location, &lines[idx-1].location);
+                let same_as_next = idx+1 < lines.len() && same_source_line(&line.location, &lines[idx+1].location);
+
+                !same_as_last && !same_as_next
+            })
+            .map(|(_, line)| line.clone())
+            .collect();
+
+        Listing { lines }
+    }
+
+    ///
+    /// Formats this listing as text
+    ///
+    pub fn to_string(&self) -> String {
+        let mut result          = String::new();
+        let mut last_filename   = None;
+
+        for line in self.lines.iter() {
+            // Write a heading whenever the file changes
+            let filename = &line.location.location.filename;
+            if last_filename != Some(filename) {
+                result.push_str(&format!("; {}\n", filename));
+                last_filename = Some(filename);
+            }
+
+            // Split the bytes into runs of consecutive addresses
+            let mut runs: Vec<Vec<(u64, u8)>> = vec![];
+            for (address, byte) in line.bytes.iter() {
+                match runs.last_mut() {
+                    Some(run) if run.len() < BYTES_PER_LINE && run.last().map(|(last_address, _)| last_address+1) == Some(*address) => run.push((*address, *byte)),
+                    _ => runs.push(vec![(*address, *byte)])
+                }
+            }
+
+            // The first run goes alongside the source text, the rest go on their own lines
+            let mut runs = runs.into_iter();
+            let first_run = runs.next().unwrap_or_else(|| vec![]);
+
+            result.push_str(&format!("{:>6}  {:04x}  {:<width$}  {}\n", line.location.location.line, line.address, format_bytes(&first_run), line.location.text, width=BYTES_PER_LINE*3-1));
+
+            for run in runs {
+                result.push_str(&format!("{:>6}  {:04x}  {}\n", "", run[0].0, format_bytes(&run)));
+            }
+        }
+
+        result
+    }
+}
+
+///
+/// True if two source locations are on the same line of the same file
+///
+fn same_source_line(a: &SourceLocation, b: &SourceLocation) -> bool {
+    a.location.filename == b.location.filename && a.location.line == b.location.line
+}
+
+///
+/// Formats a set of bytes as hex
+///
+fn format_bytes(bytes: &Vec<(u64, u8)>) -> String {
+    bytes.iter()
+        .map(|(_, byte)| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_support::*;
+
+    #[test]
+    fn list_simple_statements() {
+        let listing = assembly_for("(d $42u8)\n(d $1234u16)").unwrap().listing.to_string();
+
+        assert!(listing == "; <expr>\n     1  0000  42                       (d $42u8)\n     2  0001  34 12                    (d $1234u16)\n");
+    }
+
+    #[test]
+    fn list_addresses_after_set_bit_pos() {
+        let listing = assembly_for("(set_bit_pos $8000u64)\n(d $42u8)").unwrap().listing.to_string();
+
+        assert!(listing.contains("     2  1000  42 "));
+    }
+
+    #[test]
+    fn list_sections() {
+        let listing = assembly_for("(d $01u8)\n(section data)\n(d $02u8)\n(section code)\n(d $03u8)").unwrap().listing.to_string();
+
+        assert!(listing.contains("     3  0000  02 "));
+        assert!(listing.contains("     5  0001  03 "));
+    }
+
+    #[test]
+    fn macro_bytes_shown_at_call_site() {
+        let listing = assembly_for("(def_syntax two_bytes ((byte <a>) ((d a) (d a))))\n(d $01u8)\n(two_bytes (byte $02u8))").unwrap().listing.to_string();
+
+        assert!(listing == "; <expr>\n     2  0000  01                       (d $01u8)\n     3  0001  02 02                    (two_bytes (byte $02u8))\n");
+    }
+
+    #[test]
+    fn long_statements_continue_on_next_line() {
+        let listing = assembly_for("(d $0102030405060708u64 $090au16)").unwrap().listing.to_string();
+
+        assert!(listing == "; <expr>\n     1  0000  08 07 06 05 04 03 02 01  (d $0102030405060708u64 $090au16)\n        0008  0a 09\n");
+    }
+
+    #[test]
+    fn markers_removed_from_bitcode() {
+        let bitcode = assembly_for("(d $42u8)").unwrap().bitcode;
+
+        assert!(!bitcode.iter().any(|code| code.is_source_marker()));
+    }
+}
@@ -0,0 +1,283 @@
+use super::code::*;
+use super::label::*;
+use super::section::*;
+
+use crate::exec::*;
+
+use std::iter;
+use std::collections::{HashMap};
+
+///
+/// A region of the output declared by the `region` function
+///
+/// Positions are in bytes from the start of the output. Code that starts in a region must not continue past its end,
+/// and the parts of the region that are not written to are filled in with the fill byte.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryRegion {
+    /// The name of the region
+    pub name: String,
+
+    /// The position of the first byte of the region in the output
+    pub start: u64,
+
+    /// The size of the region in bytes
+    pub size: u64,
+
+    /// The byte used for the parts of the region that are not written to
+    pub fill: u8,
+
+    /// True if code in this region is allowed to write over code that was generated earlier
+    pub allow_overlap: bool
+}
+
+///
+/// How much of a memory region was used after assembly
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegionUsage {
+    /// The name of the region
+    pub name: String,
+
+    /// The number of bytes that were written to in the region
+    pub used: u64,
+
+    /// The size of the region in bytes
+    pub size: u64
+}
+
+impl MemoryRegion {
+    ///
+    /// True if the specified byte position is within this region
+    ///
+    fn contains(&self, byte_pos: u64) -> bool {
+        byte_pos >= self.start && byte_pos < self.start + self.size
+    }
+}
+
+impl RegionUsage {
+    ///
+    /// Describes the usage of the region
+    ///
+    pub fn to_string(&self) -> String {
+        format!("{}: {} of {} bytes used ({} free)", self.name, self.used, self.size, self.size.saturating_sub(self.used))
+    }
+}
+
+///
+/// Retrieves the name of a label, if there is one
+///
+fn label_name(label: Option<&Label>) -> Option<String> {
+    label.and_then(|label| label.name())
+}
+
+///
+/// Returns the mask of the bits between start_bit and end_bit that are in the specified byte
+///
+fn bits_in_byte(byte_pos: u64, start_bit: u64, end_bit: u64) -> u8 {
+    let first_bit   = u64::max(start_bit, byte_pos*8) - byte_pos*8;
+    let last_bit    = u64::min(end_bit, byte_pos*8+8) - byte_pos*8;
+
+    (((1u16<<last_bit) - (1u16<<first_bit)) & 0xff) as u8
+}
+
+///
+/// Checks the bitcode generated by the assembler against a set of memory regions
+///
+/// The bitcode should still contain the section markers, and the section starts are the bit positions of each section in the
+/// output. The label positions are the index in the bitcode where each label was set: the label that was set most recently
+/// in the same section is named in any error. It's an error for code that starts in a region to continue past its end, or for
+/// code in a region to write over code that was generated earlier (unless the region allows overlaps). Code outside of any
+/// region can be freely written over. The usage of each region is returned if there are no errors.
+///
+pub fn check_memory_regions(bitcode: &Vec<BitCode>, section_starts: &HashMap<u64, u64>, label_positions: &Vec<(usize, Label)>, regions: &Vec<MemoryRegion>) -> Result<Vec<RegionUsage>, RuntimeError> {
+    // Nothing to check if there are no regions
+    if regions.len() == 0 { return Ok(vec![]); }
+
+    let mut written_bits        = regions.iter().map(|region| vec![0u8; region.size as usize]).collect::<Vec<_>>();
+    let mut writers             = regions.iter().map(|region| vec![None; region.size as usize]).collect::<Vec<Vec<Option<Label>>>>();
+    let mut labels              = label_positions.iter().peekable();
+    let mut last_label          = HashMap::new();
+    let mut section_positions   = HashMap::new();
+    let mut current_section     = *DEFAULT_SECTION;
+    let mut bit_pos             = 0u64;
+    let mut run_region          = None;
+
+    for (code_idx, code) in bitcode.iter().enumerate() {
+        // Update the labels that were set before this code
+        while let Some((label_idx, label)) = labels.peek() {
+            if *label_idx > code_idx { break; }
+
+            last_label.insert(current_section, *label);
+            labels.next();
+        }
+
+        match code {
+            BitCode::BeginSource(_, _) | BitCode::EndSource => { },
+
+            BitCode::Section(section) => {
+                section_positions.insert(current_section, bit_pos);
+                bit_pos         = section_positions.remove(section).unwrap_or(0);
+                current_section = *section;
+                run_region      = None;
+            },
+
+            BitCode::Move(pos) => {
+                bit_pos         = *pos;
+                run_region      = None;
+            },
+
+            code => {
+                let next_bit_pos = BitCode::position_after(bit_pos, iter::once(code));
+
+                if next_bit_pos > bit_pos {
+                    let section_start   = section_starts.get(&current_section).cloned().unwrap_or(0);
+                    let start_bit       = section_start + bit_pos;
+                    let end_bit         = section_start + next_bit_pos;
+                    let label           = last_label.get(&current_section).cloned();
+
+                    // A run of code belongs to the region that it starts in, and must not continue past the end of that region
+                    let region = *run_region.get_or_insert_with(|| regions.iter().position(|region| region.contains(start_bit/8)));
+                    if let Some(region) = region {
+                        let region = &regions[region];
+                        if (end_bit+7)/8 > region.start + region.size {
+                            return Err(RuntimeError::RegionOverflow(region.name.clone(), label_name(label.as_ref())));
+                        }
+                    }
+
+                    // Every bit in a region should be written only once
+                    let start_byte  = start_bit/8;
+                    let end_byte    = (end_bit+7)/8;
+
+                    for (region_idx, region) in regions.iter().enumerate() {
+                        let first_byte  = u64::max(start_byte, region.start);
+                        let last_byte   = u64::min(end_byte, region.start + region.size);
+
+                        for byte_pos in first_byte..last_byte {
+                            let offset  = (byte_pos - region.start) as usize;
+                            let mask    = bits_in_byte(byte_pos, start_bit, end_bit);
+
+                            if (written_bits[region_idx][offset] & mask) != 0 {
+                                let allow_overlap = regions.iter().any(|region| region.allow_overlap && region.contains(byte_pos));
+
+                                if !allow_overlap {
+                                    return Err(RuntimeError::OverlappingCode(byte_pos, label_name(writers[region_idx][offset].as_ref()), label_name(label.as_ref())));
+                                }
+                            }
+
+                            written_bits[region_idx][offset]    |= mask;
+                            writers[region_idx][offset]         = label;
+                        }
+                    }
+                }
+
+                bit_pos = next_bit_pos;
+            }
+        }
+    }
+
+    // Work out how much of each region was used
+    let usage = regions.iter().zip(written_bits.iter())
+        .map(|(region, written_bits)| {
+            let used = written_bits.iter().filter(|bits| **bits != 0).count();
+
+            RegionUsage { name: region.name.clone(), used: used as u64, size: region.size }
+        })
+        .collect();
+
+    Ok(usage)
+}
+
+///
+/// Adds bitcode to fill in the memory regions ahead of the bitcode generated by the assembler (after the sections have been placed)
+///
+pub fn fill_memory_regions(bitcode: Vec<BitCode>, regions: &Vec<MemoryRegion>) -> Vec<BitCode> {
+    if regions.len() == 0 { return bitcode; }
+
+    let mut result = vec![];
+
+    for region in regions.iter() {
+        // Fill 8 bytes at a time where possible
+        let fill        = region.fill as u128;
+        let fill_64     = (0..8).fold(0u128, |pattern, _| (pattern<<8) | fill);
+
+        result.push(BitCode::Move(region.start * 8));
+        result.extend(iter::repeat(BitCode::Bits(64, fill_64)).take((region.size/8) as usize));
+        result.extend(iter::repeat(BitCode::Bits(8, fill)).take((region.size%8) as usize));
+    }
+
+    // The bitcode generated by the assembler is written over the top of the fill bytes
+    result.push(BitCode::Move(0));
+    result.extend(bitcode);
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_support::*;
+
+    #[test]
+    fn region_is_filled() {
+        let bytes = assembly_for("(region \"rom\" 0 4 $ff) (d $1u8 $2u8)").unwrap().bytes();
+
+        assert!(bytes == vec![1, 2, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn region_usage_is_reported() {
+        let (_, diagnostics) = assembly_with_diagnostics("(region \"rom\" 0 16) (d $1u8 $2u8)").unwrap();
+
+        assert!(diagnostics.len() == 1);
+        assert!(diagnostics[0].report() == "rom: 2 of 16 bytes used (14 free)\n".to_string());
+    }
+
+    #[test]
+    fn code_overflows_region() {
+        let error = assembly_for("(region \"bank\" 0 2) (label start) (d $1u8) (label table) (d $2u8 $3u8)").unwrap_err();
+
+        assert!(format!("{:?}", error) == "RegionOverflow(\"bank\", Some(\"table\"))".to_string());
+    }
+
+    #[test]
+    fn code_overlaps() {
+        let error = assembly_for("(region \"rom\" 0 4) (label first) (d $1u8 $2u8) (m 8) (label second) (d $3u8)").unwrap_err();
+
+        assert!(format!("{:?}", error) == "OverlappingCode(1, Some(\"first\"), Some(\"second\"))".to_string());
+    }
+
+    #[test]
+    fn overlap_outside_region() {
+        let bytes = assembly_for("(d $1u8 $2u8) (m 0) (d $3u8)").unwrap().bytes();
+
+        assert!(bytes == vec![3, 2]);
+    }
+
+    #[test]
+    fn overlap_after_region() {
+        let bytes = assembly_for("(region \"rom\" 0 2) (m 16) (d $1u8 $2u8) (m 16) (d $3u8)").unwrap().bytes();
+
+        assert!(bytes == vec![0, 0, 3, 2]);
+    }
+
+    #[test]
+    fn region_allows_overlap() {
+        let bytes = assembly_for("(region \"patch\" 0 2 0 =t) (d $1u8 $2u8) (m 8) (d $3u8)").unwrap().bytes();
+
+        assert!(bytes == vec![1, 3]);
+    }
+
+    #[test]
+    fn bits_in_same_byte_do_not_overlap() {
+        let bytes = assembly_for("(region \"rom\" 0 1) (d 1u4 2u4)").unwrap().bytes();
+
+        assert!(bytes == vec![0x21]);
+    }
+
+    #[test]
+    fn bits_in_same_byte_overlap() {
+        let error = assembly_for("(region \"rom\" 0 1) (d 1u4) (m 2) (d 1u4)").unwrap_err();
+
+        assert!(format!("{:?}", error) == "OverlappingCode(0, None, None)".to_string());
+    }
+}
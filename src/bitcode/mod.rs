@@ -3,13 +3,27 @@ mod label;
 mod bitcode_monad;
 mod bitcode_functions;
 mod label_syntax;
+mod anonymous_label_syntax;
 mod assemble;
 mod to_bytes;
+mod source_marker;
+mod listing;
+mod label_values;
+mod diagnostic;
+mod section;
+mod memory_region;
 
 pub use self::code::*;
 pub use self::label::*;
 pub use self::bitcode_monad::*;
 pub use self::bitcode_functions::*;
 pub use self::label_syntax::*;
+pub use self::anonymous_label_syntax::*;
 pub use self::assemble::*;
 pub use self::to_bytes::*;
+pub use self::source_marker::*;
+pub use self::listing::*;
+pub use self::label_values::*;
+pub use self::diagnostic::*;
+pub use self::section::*;
+pub use self::memory_region::*;
@@ -0,0 +1,157 @@
+use super::code::*;
+use super::bitcode_monad::*;
+
+use crate::bind::*;
+use crate::meta::*;
+use crate::exec::*;
+
+use smallvec::*;
+use std::collections::{HashMap};
+
+lazy_static! {
+    /// The atom ID of the section that bitcode is generated in when no other section has been chosen
+    pub static ref DEFAULT_SECTION: u64 = get_id_for_atom_with_name("code");
+}
+
+///
+/// The `section` keyword switches the section that bitcode is generated in
+///
+/// `(section data)` - the bitcode generated after this statement is written to the `data` section. Bitcode is written
+/// to the `code` section until another section is chosen.
+///
+/// Each section has its own bit position, which starts at 0 and can be changed with `set_bit_pos` (so each section
+/// can have its own origin). Switching back to a section carries on from where the section was left. Once assembly
+/// has finished, the sections are placed one after the other in the output, in the order they were first used.
+///
+pub fn section_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    get_expression_arguments().and_then(|ListTuple((AtomId(section), )): ListTuple<(AtomId, )>| {
+        // The section is a monad that's known when the statement is bound
+        let set_section = BitCodeMonad::set_section(section).to_cell();
+        wrap_binding(set_section)
+    }).map(|set_section| {
+        let set_section = set_section.clone();
+
+        // The compiler just loads the monad
+        let compiler = move |_| {
+            Ok(smallvec![Action::Value(set_section.clone())].into())
+        };
+
+        SyntaxCompiler::with_compiler_and_reftype(compiler, NIL.clone(), ReferenceType::Monad)
+    })
+}
+
+///
+/// Places the sections found in some bitcode in the output
+///
+/// The result is the bitcode with the sections placed one after the other (in the order they're first used), along
+/// with the bit position in the output where each section starts. `Move` instructions are relative to the start of
+/// the section they're in. Section markers and source markers are not included in the result.
+///
+pub fn layout_sections(bitcode: &Vec<BitCode>) -> (Vec<BitCode>, HashMap<u64, u64>) {
+    // Split the bitcode into sections
+    let mut section_order   = vec![*DEFAULT_SECTION];
+    let mut section_code    = HashMap::new();
+    let mut current_section = *DEFAULT_SECTION;
+
+    section_code.insert(current_section, vec![]);
+
+    for code in bitcode.iter() {
+        match code {
+            BitCode::Section(section)   => {
+                if !section_code.contains_key(section) {
+                    section_order.push(*section);
+                    section_code.insert(*section, vec![]);
+                }
+
+                current_section = *section;
+            },
+
+            BitCode::BeginSource(_, _)  |
+            BitCode::EndSource          => { },
+
+            code                        => { section_code.get_mut(&current_section).unwrap().push(code.clone()); }
+        }
+    }
+
+    // Each section starts on the byte after the furthest position reached by the section before it
+    let mut result          = vec![];
+    let mut section_starts  = HashMap::new();
+    let mut start_pos       = 0u64;
+
+    for section in section_order {
+        let code = &section_code[&section];
+
+        let mut pos     = 0;
+        let mut length  = 0;
+        for code_point in code.iter() {
+            pos     = BitCode::position_after(pos, Some(code_point));
+            length  = u64::max(length, pos);
+        }
+
+        section_starts.insert(section, start_pos);
+
+        // Empty sections do not affect the output
+        if code.is_empty() { continue; }
+
+        if start_pos != 0 { result.push(BitCode::Move(start_pos)); }
+        result.extend(code.iter().map(|code_point| {
+            match code_point {
+                BitCode::Move(pos)  => BitCode::Move(start_pos + pos),
+                other               => other.clone()
+            }
+        }));
+
+        start_pos = ((start_pos + length + 7) / 8) * 8;
+    }
+
+    (result, section_starts)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test_support::*;
+
+    #[test]
+    fn sections_are_placed_in_order() {
+        let bytes = assembly_for("(d $1u8) (section data) (d $2u8) (section code) (d $3u8) (section data) (d $4u8)").unwrap().bytes();
+
+        assert!(bytes == vec![1, 3, 2, 4]);
+    }
+
+    #[test]
+    fn sections_have_their_own_origin() {
+        let bytes = assembly_for("
+            (set_bit_pos $8000u64)
+            (d (bits 8 (/ message 8)))
+            (section data)
+            (set_bit_pos $10000u64)
+            (label message)
+            (d $42u8)
+            (section code)
+            (label end)
+            (d (bits 8 (/ end 8)))").unwrap().bytes();
+
+        assert!(bytes == vec![0x00, 0x01, 0x42]);
+    }
+
+    #[test]
+    fn sections_without_bitcode_reserve_space() {
+        let bytes = assembly_for("
+            (section zeropage)
+            (set_bit_pos $80u64)
+            (label counter)
+            (set_bit_pos (+ (bit_pos) 8))
+            (label pointer)
+            (section code)
+            (d (bits 8 (/ counter 8)) (bits 8 (/ pointer 8)))").unwrap().bytes();
+
+        assert!(bytes == vec![0x10, 0x11]);
+    }
+
+    #[test]
+    fn move_is_relative_to_section() {
+        let bytes = assembly_for("(d $1u8 $2u8) (section data) (m 8) (d $3u8)").unwrap().bytes();
+
+        assert!(bytes == vec![1, 2, 0, 3]);
+    }
+}
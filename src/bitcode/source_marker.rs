@@ -0,0 +1,64 @@
+use super::code::*;
+use super::bitcode_monad::*;
+
+use crate::meta::*;
+use crate::exec::*;
+use crate::parse::*;
+
+use smallvec::*;
+use std::sync::*;
+
+lazy_static! {
+    /// The ID to assign to the next source marker
+    static ref NEXT_MARKER_ID: Mutex<u64> = Mutex::new(0);
+}
+
+///
+/// Reserves an ID for a source marker
+/// 
+/// Marker IDs are assigned in increasing order, so the IDs can be used to tell if one statement was bound before
+/// another (statements bound before the statement that contains them come from elsewhere, such as the body of a
+/// macro or a function)
+///
+pub fn reserve_source_marker() -> u64 {
+    let mut next_id = NEXT_MARKER_ID.lock().unwrap();
+    let id          = *next_id;
+    (*next_id)      += 1;
+
+    id
+}
+
+///
+/// Creates a function that takes a monad and surrounds any bitcode that it generates with source markers
+/// 
+/// The markers record the location of the source statement, so it stays available for as long as the bitcode does. Monads
+/// other than the bitcode monad are returned unchanged.
+///
+pub fn source_marker_fn(marker_id: u64, location: Arc<SourceLocation>) -> CellRef {
+    let mark_source = FnMonad::from(move |(monad, ): (CellRef, )| {
+        match &*monad {
+            SafasCell::Monad(bitcode_monad, _monad_type) => {
+                match &**bitcode_monad {
+                    SafasCell::Any(bitcode_monad)   => {
+                        if let Some(bitcode_monad) = bitcode_monad.downcast_ref::<BitCodeMonad>() {
+                            // Add markers around the bitcode generated by this monad
+                            let mut bitcode_monad = bitcode_monad.clone();
+                            bitcode_monad.prepend_bitcode(BitCodeContent::Value(smallvec![BitCode::BeginSource(marker_id, location.clone())]));
+                            bitcode_monad.append_following_bitcode(BitCodeContent::Value(smallvec![BitCode::EndSource]));
+
+                            bitcode_monad.to_cell()
+                        } else {
+                            monad
+                        }
+                    },
+
+                    _ => monad
+                }
+            },
+
+            _ => monad
+        }
+    });
+
+    CellRef::new(SafasCell::FrameMonad(Box::new(mark_source)))
+}
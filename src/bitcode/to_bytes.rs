@@ -58,6 +58,28 @@ pub fn bitcode_to_bytes<BitCodeIterator: IntoIterator<Item=BitCode>>(bitcode: Bi
                 }
             },
 
+            MsbBits(len, pattern)           => {
+                // Resize so that the pattern will fit in to the result
+                let new_bit_pos = cur_bit_pos + len as usize;
+                while (new_bit_pos/8) >= result.len() {
+                    result.resize(result.len() * 2, 0u8);
+                }
+
+                // Write out the pattern one bit at a time, from the most significant bit
+                for bit in (0..len).rev() {
+                    let pos             = cur_bit_pos/8;
+                    let mask            = 0x80u8 >> (cur_bit_pos%8);
+
+                    if (pattern >> bit) & 1 != 0 {
+                        result[pos]     |= mask;
+                    } else {
+                        result[pos]     &= !mask;
+                    }
+
+                    cur_bit_pos         += 1;
+                }
+            },
+
             Align(pattern_len, pattern, alignment)  => {
                 // Work out where we're going to align to 
                 let alignment           = alignment as usize;
@@ -114,6 +136,9 @@ pub fn bitcode_to_bytes<BitCodeIterator: IntoIterator<Item=BitCode>>(bitcode: Bi
                 }
             }
 
+            BeginSource(_, _) | EndSource   |
+            Section(_)                      => { }
+
             Move(new_bit_pos)               => {
                 // Move and resize
                 cur_bit_pos = new_bit_pos as usize;
@@ -127,10 +152,10 @@ pub fn bitcode_to_bytes<BitCodeIterator: IntoIterator<Item=BitCode>>(bitcode: Bi
         if cur_bit_pos > max_bit_pos { max_bit_pos = cur_bit_pos; }
     }
 
-    let len = if (cur_bit_pos & 0x7) == 0 {
-        cur_bit_pos / 8
+    let len = if (max_bit_pos & 0x7) == 0 {
+        max_bit_pos / 8
     } else {
-        cur_bit_pos / 8 + 1
+        max_bit_pos / 8 + 1
     };
     result.resize(len, 0);
     result
@@ -184,6 +209,15 @@ mod test {
         assert!(bytes.len() == 2);
     }
 
+    #[test]
+    fn assemble_msb_first_bits() {
+        let bytes = bitcode_to_bytes(vec![BitCode::MsbBits(3, 0x1), BitCode::MsbBits(5, 0x2), BitCode::MsbBits(16, 0x1234)]);
+        assert!(bytes[0] == 0x22);
+        assert!(bytes[1] == 0x12);
+        assert!(bytes[2] == 0x34);
+        assert!(bytes.len() == 3);
+    }
+
     #[test]
     fn assemble_5000() {
         let bytes = bitcode_to_bytes((0..5000u128).into_iter().map(|num| BitCode::Bits(8, num)));
@@ -216,6 +250,14 @@ mod test {
         assert!(byte.len() == 1);
     }
 
+    #[test]
+    fn move_backwards_keeps_length() {
+        let bytes = bitcode_to_bytes(vec![BitCode::Move(16), BitCode::Bits(8, 0x42), BitCode::Move(0), BitCode::Bits(8, 0x41)]);
+        assert!(bytes.len() == 3);
+        assert!(bytes[0] == 0x41);
+        assert!(bytes[2] == 0x42);
+    }
+
     #[test]
     fn align_word_after_nybble() {
         let byte = bitcode_to_bytes(vec![BitCode::Bits(4, 0x9), BitCode::Align(8, 0, 32)]);
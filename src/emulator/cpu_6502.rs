@@ -0,0 +1,922 @@
+use crate::exec::*;
+
+/// Carry flag
+pub const FLAG_C: u8 = 0x01;
+
+/// Zero flag
+pub const FLAG_Z: u8 = 0x02;
+
+/// Interrupt disable flag
+pub const FLAG_I: u8 = 0x04;
+
+/// Decimal mode flag
+pub const FLAG_D: u8 = 0x08;
+
+/// Break flag (only exists on the copy of the status register pushed to the stack)
+pub const FLAG_B: u8 = 0x10;
+
+/// Unused flag (always reads as 1)
+pub const FLAG_U: u8 = 0x20;
+
+/// Overflow flag
+pub const FLAG_V: u8 = 0x40;
+
+/// Negative flag
+pub const FLAG_N: u8 = 0x80;
+
+///
+/// The variants of the 6502 that can be emulated
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cpu6502Variant {
+    /// The original NMOS 6502 (undocumented opcodes are not supported)
+    Nmos6502,
+
+    /// The WDC 65c02, with its extra instructions and addressing modes
+    Wdc65c02
+}
+
+///
+/// The reasons the emulator can stop running code
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopReason {
+    /// A `brk` instruction was executed
+    Break,
+
+    /// An `rts` instruction returned from the routine that the emulator started in
+    Return,
+
+    /// A `stp` instruction was executed (65c02 only)
+    Stop,
+
+    /// A `wai` instruction was executed (65c02 only: there are no interrupts to wait for)
+    Wait
+}
+
+///
+/// The instructions understood by the emulator
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operation {
+    Adc, And, Asl, Bit, Brk, Cmp, Cpx, Cpy, Dec, Dex, Dey, Eor, Inc, Inx, Iny, Jmp, Jsr, Lda, Ldx, Ldy, Lsr, Nop, Ora,
+    Pha, Php, Pla, Plp, Rol, Ror, Rti, Rts, Sbc, Sta, Stx, Sty, Tax, Tay, Tsx, Txa, Txs, Tya,
+
+    /// Branch if the flag has the specified value
+    Branch(u8, bool),
+
+    /// Sets the flag to the specified value
+    SetFlag(u8, bool),
+
+    // 65c02 instructions
+    Bra, Phx, Phy, Plx, Ply, Stz, Trb, Tsb, Stp, Wai,
+
+    /// Reset memory bit
+    Rmb(u8),
+
+    /// Set memory bit
+    Smb(u8),
+
+    /// Branch if memory bit is reset
+    Bbr(u8),
+
+    /// Branch if memory bit is set
+    Bbs(u8)
+}
+
+///
+/// The addressing modes understood by the emulator
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AddressMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    AbsoluteIndexedIndirect,
+    IndexedIndirect,
+    IndirectIndexed,
+    ZeroPageIndirect,
+    Relative,
+    ZeroPageRelative
+}
+
+///
+/// The location of the operand of an instruction, once its addressing mode has been decoded
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operand {
+    None,
+    Accumulator,
+    Value(u8),
+    Address(u16)
+}
+
+///
+/// Emulates a 6502 or 65c02 CPU attached to 64k of RAM
+///
+#[derive(Clone)]
+pub struct Cpu6502 {
+    /// The CPU being emulated
+    pub variant: Cpu6502Variant,
+
+    /// The accumulator
+    pub a: u8,
+
+    /// The X index register
+    pub x: u8,
+
+    /// The Y index register
+    pub y: u8,
+
+    /// The stack pointer
+    pub sp: u8,
+
+    /// The program counter
+    pub pc: u16,
+
+    /// The status register
+    pub status: u8,
+
+    /// The number of cycles that have been executed
+    pub cycles: u64,
+
+    /// The memory attached to the CPU
+    pub memory: Vec<u8>
+}
+
+impl Cpu6502 {
+    ///
+    /// Creates a new CPU with empty memory
+    ///
+    pub fn new(variant: Cpu6502Variant) -> Cpu6502 {
+        Cpu6502 {
+            variant:    variant,
+            a:          0,
+            x:          0,
+            y:          0,
+            sp:         0xfd,
+            pc:         0,
+            status:     FLAG_U | FLAG_I,
+            cycles:     0,
+            memory:     vec![0; 65536]
+        }
+    }
+
+    ///
+    /// Copies some bytes into memory (wrapping around at the end of the address space)
+    ///
+    pub fn load(&mut self, address: u16, bytes: &[u8]) {
+        for (offset, byte) in bytes.iter().enumerate() {
+            self.memory[address.wrapping_add(offset as u16) as usize] = *byte;
+        }
+    }
+
+    ///
+    /// True if the specified flag is set
+    ///
+    pub fn flag(&self, flag: u8) -> bool {
+        (self.status & flag) != 0
+    }
+
+    ///
+    /// Runs code until it stops or the number of cycles executed reaches the limit
+    ///
+    /// The code stops when it executes a `brk` instruction, or when an `rts` instruction returns from the routine that was
+    /// running when this was called (ie, when the stack pointer is back where it started). `stp` and `wai` also stop the
+    /// 65c02.
+    ///
+    pub fn run(&mut self, max_cycles: u64) -> Result<StopReason, RuntimeError> {
+        let initial_sp = self.sp;
+
+        loop {
+            if self.cycles >= max_cycles {
+                return Err(RuntimeError::CycleLimitReached(max_cycles));
+            }
+
+            if let Some(stop_reason) = self.step(initial_sp)? {
+                return Ok(stop_reason);
+            }
+        }
+    }
+
+    ///
+    /// Reads a byte from memory
+    ///
+    #[inline]
+    fn read(&self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    ///
+    /// Writes a byte to memory
+    ///
+    #[inline]
+    fn write(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+    }
+
+    ///
+    /// Reads a little-endian word from memory
+    ///
+    fn read_word(&self, address: u16) -> u16 {
+        (self.read(address) as u16) | ((self.read(address.wrapping_add(1)) as u16) << 8)
+    }
+
+    ///
+    /// Reads a little-endian word from the zero page (the high byte wraps around within the zero page)
+    ///
+    fn read_zero_page_word(&self, address: u8) -> u16 {
+        (self.read(address as u16) as u16) | ((self.read(address.wrapping_add(1) as u16) as u16) << 8)
+    }
+
+    ///
+    /// Reads the byte at the program counter and moves it on
+    ///
+    fn fetch(&mut self) -> u8 {
+        let value   = self.read(self.pc);
+        self.pc     = self.pc.wrapping_add(1);
+        value
+    }
+
+    ///
+    /// Reads the word at the program counter and moves it on
+    ///
+    fn fetch_word(&mut self) -> u16 {
+        let low     = self.fetch() as u16;
+        let high    = self.fetch() as u16;
+        low | (high << 8)
+    }
+
+    fn push(&mut self, value: u8) {
+        self.write(0x100 | (self.sp as u16), value);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+
+    fn pull(&mut self) -> u8 {
+        self.sp = self.sp.wrapping_add(1);
+        self.read(0x100 | (self.sp as u16))
+    }
+
+    fn push_word(&mut self, value: u16) {
+        self.push((value >> 8) as u8);
+        self.push(value as u8);
+    }
+
+    fn pull_word(&mut self) -> u16 {
+        let low     = self.pull() as u16;
+        let high    = self.pull() as u16;
+        low | (high << 8)
+    }
+
+    fn set_flag(&mut self, flag: u8, value: bool) {
+        if value {
+            self.status |= flag;
+        } else {
+            self.status &= !flag;
+        }
+    }
+
+    ///
+    /// Sets the zero and negative flags for a value
+    ///
+    fn set_nz(&mut self, value: u8) -> u8 {
+        self.set_flag(FLAG_Z, value == 0);
+        self.set_flag(FLAG_N, (value & 0x80) != 0);
+        value
+    }
+
+    ///
+    /// Decodes an opcode into an operation and an addressing mode
+    ///
+    fn decode(&self, opcode: u8) -> Option<(Operation, AddressMode)> {
+        use self::Operation::*;
+        use self::AddressMode::*;
+
+        let nmos = match opcode {
+            0x69 => (Adc, Immediate),   0x65 => (Adc, ZeroPage),    0x75 => (Adc, ZeroPageX),   0x6d => (Adc, Absolute),
+            0x7d => (Adc, AbsoluteX),   0x79 => (Adc, AbsoluteY),   0x61 => (Adc, IndexedIndirect), 0x71 => (Adc, IndirectIndexed),
+
+            0x29 => (And, Immediate),   0x25 => (And, ZeroPage),    0x35 => (And, ZeroPageX),   0x2d => (And, Absolute),
+            0x3d => (And, AbsoluteX),   0x39 => (And, AbsoluteY),   0x21 => (And, IndexedIndirect), 0x31 => (And, IndirectIndexed),
+
+            0x0a => (Asl, Accumulator), 0x06 => (Asl, ZeroPage),    0x16 => (Asl, ZeroPageX),   0x0e => (Asl, Absolute),
+            0x1e => (Asl, AbsoluteX),
+
+            0x24 => (Bit, ZeroPage),    0x2c => (Bit, Absolute),
+
+            0x10 => (Branch(FLAG_N, false), Relative),  0x30 => (Branch(FLAG_N, true), Relative),
+            0x50 => (Branch(FLAG_V, false), Relative),  0x70 => (Branch(FLAG_V, true), Relative),
+            0x90 => (Branch(FLAG_C, false), Relative),  0xb0 => (Branch(FLAG_C, true), Relative),
+            0xd0 => (Branch(FLAG_Z, false), Relative),  0xf0 => (Branch(FLAG_Z, true), Relative),
+
+            0x00 => (Brk, Implied),
+
+            0x18 => (SetFlag(FLAG_C, false), Implied),  0x38 => (SetFlag(FLAG_C, true), Implied),
+            0x58 => (SetFlag(FLAG_I, false), Implied),  0x78 => (SetFlag(FLAG_I, true), Implied),
+            0xb8 => (SetFlag(FLAG_V, false), Implied),
+            0xd8 => (SetFlag(FLAG_D, false), Implied),  0xf8 => (SetFlag(FLAG_D, true), Implied),
+
+            0xc9 => (Cmp, Immediate),   0xc5 => (Cmp, ZeroPage),    0xd5 => (Cmp, ZeroPageX),   0xcd => (Cmp, Absolute),
+            0xdd => (Cmp, AbsoluteX),   0xd9 => (Cmp, AbsoluteY),   0xc1 => (Cmp, IndexedIndirect), 0xd1 => (Cmp, IndirectIndexed),
+
+            0xe0 => (Cpx, Immediate),   0xe4 => (Cpx, ZeroPage),    0xec => (Cpx, Absolute),
+            0xc0 => (Cpy, Immediate),   0xc4 => (Cpy, ZeroPage),    0xcc => (Cpy, Absolute),
+
+            0xc6 => (Dec, ZeroPage),    0xd6 => (Dec, ZeroPageX),   0xce => (Dec, Absolute),    0xde => (Dec, AbsoluteX),
+            0xca => (Dex, Implied),     0x88 => (Dey, Implied),
+
+            0x49 => (Eor, Immediate),   0x45 => (Eor, ZeroPage),    0x55 => (Eor, ZeroPageX),   0x4d => (Eor, Absolute),
+            0x5d => (Eor, AbsoluteX),   0x59 => (Eor, AbsoluteY),   0x41 => (Eor, IndexedIndirect), 0x51 => (Eor, IndirectIndexed),
+
+            0xe6 => (Inc, ZeroPage),    0xf6 => (Inc, ZeroPageX),   0xee => (Inc, Absolute),    0xfe => (Inc, AbsoluteX),
+            0xe8 => (Inx, Implied),     0xc8 => (Iny, Implied),
+
+            0x4c => (Jmp, Absolute),    0x6c => (Jmp, Indirect),
+            0x20 => (Jsr, Absolute),
+
+            0xa9 => (Lda, Immediate),   0xa5 => (Lda, ZeroPage),    0xb5 => (Lda, ZeroPageX),   0xad => (Lda, Absolute),
+            0xbd => (Lda, AbsoluteX),   0xb9 => (Lda, AbsoluteY),   0xa1 => (Lda, IndexedIndirect), 0xb1 => (Lda, IndirectIndexed),
+
+            0xa2 => (Ldx, Immediate),   0xa6 => (Ldx, ZeroPage),    0xb6 => (Ldx, ZeroPageY),   0xae => (Ldx, Absolute),
+            0xbe => (Ldx, AbsoluteY),
+
+            0xa0 => (Ldy, Immediate),   0xa4 => (Ldy, ZeroPage),    0xb4 => (Ldy, ZeroPageX),   0xac => (Ldy, Absolute),
+            0xbc => (Ldy, AbsoluteX),
+
+            0x4a => (Lsr, Accumulator), 0x46 => (Lsr, ZeroPage),    0x56 => (Lsr, ZeroPageX),   0x4e => (Lsr, Absolute),
+            0x5e => (Lsr, AbsoluteX),
+
+            0xea => (Nop, Implied),
+
+            0x09 => (Ora, Immediate),   0x05 => (Ora, ZeroPage),    0x15 => (Ora, ZeroPageX),   0x0d => (Ora, Absolute),
+            0x1d => (Ora, AbsoluteX),   0x19 => (Ora, AbsoluteY),   0x01 => (Ora, IndexedIndirect), 0x11 => (Ora, IndirectIndexed),
+
+            0x48 => (Pha, Implied),     0x08 => (Php, Implied),     0x68 => (Pla, Implied),     0x28 => (Plp, Implied),
+
+            0x2a => (Rol, Accumulator), 0x26 => (Rol, ZeroPage),    0x36 => (Rol, ZeroPageX),   0x2e => (Rol, Absolute),
+            0x3e => (Rol, AbsoluteX),
+
+            0x6a => (Ror, Accumulator), 0x66 => (Ror, ZeroPage),    0x76 => (Ror, ZeroPageX),   0x6e => (Ror, Absolute),
+            0x7e => (Ror, AbsoluteX),
+
+            0x40 => (Rti, Implied),     0x60 => (Rts, Implied),
+
+            0xe9 => (Sbc, Immediate),   0xe5 => (Sbc, ZeroPage),    0xf5 => (Sbc, ZeroPageX),   0xed => (Sbc, Absolute),
+            0xfd => (Sbc, AbsoluteX),   0xf9 => (Sbc, AbsoluteY),   0xe1 => (Sbc, IndexedIndirect), 0xf1 => (Sbc, IndirectIndexed),
+
+            0x85 => (Sta, ZeroPage),    0x95 => (Sta, ZeroPageX),   0x8d => (Sta, Absolute),    0x9d => (Sta, AbsoluteX),
+            0x99 => (Sta, AbsoluteY),   0x81 => (Sta, IndexedIndirect), 0x91 => (Sta, IndirectIndexed),
+
+            0x86 => (Stx, ZeroPage),    0x96 => (Stx, ZeroPageY),   0x8e => (Stx, Absolute),
+            0x84 => (Sty, ZeroPage),    0x94 => (Sty, ZeroPageX),   0x8c => (Sty, Absolute),
+
+            0xaa => (Tax, Implied),     0xa8 => (Tay, Implied),     0xba => (Tsx, Implied),
+            0x8a => (Txa, Implied),     0x9a => (Txs, Implied),     0x98 => (Tya, Implied),
+
+            _ => {
+                if self.variant == Cpu6502Variant::Wdc65c02 {
+                    return self.decode_65c02(opcode);
+                } else {
+                    return None;
+                }
+            }
+        };
+
+        Some(nmos)
+    }
+
+    ///
+    /// Decodes the opcodes that were added in the 65c02
+    ///
+    fn decode_65c02(&self, opcode: u8) -> Option<(Operation, AddressMode)> {
+        use self::Operation::*;
+        use self::AddressMode::*;
+
+        let bit_number = (opcode >> 4) & 0x7;
+
+        match opcode {
+            0x12 => Some((Ora, ZeroPageIndirect)),  0x32 => Some((And, ZeroPageIndirect)),
+            0x52 => Some((Eor, ZeroPageIndirect)),  0x72 => Some((Adc, ZeroPageIndirect)),
+            0x92 => Some((Sta, ZeroPageIndirect)),  0xb2 => Some((Lda, ZeroPageIndirect)),
+            0xd2 => Some((Cmp, ZeroPageIndirect)),  0xf2 => Some((Sbc, ZeroPageIndirect)),
+
+            0x1a => Some((Inc, Accumulator)),       0x3a => Some((Dec, Accumulator)),
+            0x89 => Some((Bit, Immediate)),         0x34 => Some((Bit, ZeroPageX)),         0x3c => Some((Bit, AbsoluteX)),
+            0x7c => Some((Jmp, AbsoluteIndexedIndirect)),
+            0x80 => Some((Bra, Relative)),
+
+            0xda => Some((Phx, Implied)),           0x5a => Some((Phy, Implied)),
+            0xfa => Some((Plx, Implied)),           0x7a => Some((Ply, Implied)),
+
+            0x64 => Some((Stz, ZeroPage)),          0x74 => Some((Stz, ZeroPageX)),
+            0x9c => Some((Stz, Absolute)),          0x9e => Some((Stz, AbsoluteX)),
+
+            0x04 => Some((Tsb, ZeroPage)),          0x0c => Some((Tsb, Absolute)),
+            0x14 => Some((Trb, ZeroPage)),          0x1c => Some((Trb, Absolute)),
+
+            0xcb => Some((Wai, Implied)),           0xdb => Some((Stp, Implied)),
+
+            _ if (opcode & 0x8f) == 0x07 => Some((Rmb(bit_number), ZeroPage)),
+            _ if (opcode & 0x8f) == 0x87 => Some((Smb(bit_number), ZeroPage)),
+            _ if (opcode & 0x8f) == 0x0f => Some((Bbr(bit_number), ZeroPageRelative)),
+            _ if (opcode & 0x8f) == 0x8f => Some((Bbs(bit_number), ZeroPageRelative)),
+
+            _ => None
+        }
+    }
+
+    ///
+    /// The number of cycles an instruction takes (not including any penalty for crossing a page or taking a branch)
+    ///
+    fn base_cycles(operation: Operation, mode: AddressMode) -> u64 {
+        use self::Operation::*;
+        use self::AddressMode::*;
+
+        match (operation, mode) {
+            (Brk, _)                                    => 7,
+            (Jsr, _) | (Rts, _) | (Rti, _)              => 6,
+            (Jmp, Absolute)                             => 3,
+            (Jmp, Indirect)                             => 5,
+            (Jmp, _)                                    => 6,
+            (Pha, _) | (Php, _) | (Phx, _) | (Phy, _)   => 3,
+            (Pla, _) | (Plp, _) | (Plx, _) | (Ply, _)   => 4,
+            (Wai, _) | (Stp, _)                         => 3,
+            (Rmb(_), _) | (Smb(_), _)                   => 5,
+            (Bbr(_), _) | (Bbs(_), _)                   => 5,
+            (Bra, _)                                    => 3,
+
+            // Read-modify-write instructions
+            (Asl, Accumulator) | (Lsr, Accumulator) | (Rol, Accumulator) | (Ror, Accumulator) |
+            (Inc, Accumulator) | (Dec, Accumulator)     => 2,
+            (Asl, mode) | (Lsr, mode) | (Rol, mode) | (Ror, mode) | (Inc, mode) | (Dec, mode) | (Tsb, mode) | (Trb, mode) => {
+                match mode {
+                    ZeroPage    => 5,
+                    ZeroPageX   => 6,
+                    Absolute    => 6,
+                    _           => 7
+                }
+            },
+
+            // Stores always take the time needed for a page crossing
+            (Sta, AbsoluteX) | (Sta, AbsoluteY) | (Stz, AbsoluteX) => 5,
+            (Sta, IndirectIndexed)                      => 6,
+
+            // Everything else depends only on the addressing mode
+            (_, Implied) | (_, Accumulator) | (_, Immediate) | (_, Relative) => 2,
+            (_, ZeroPage)                               => 3,
+            (_, ZeroPageX) | (_, ZeroPageY)             => 4,
+            (_, Absolute) | (_, AbsoluteX) | (_, AbsoluteY) => 4,
+            (_, IndexedIndirect)                        => 6,
+            (_, IndirectIndexed) | (_, ZeroPageIndirect) => 5,
+            (_, Indirect) | (_, AbsoluteIndexedIndirect) | (_, ZeroPageRelative) => 6
+        }
+    }
+
+    ///
+    /// Decodes the operand for an instruction, returning the operand and whether or not a page boundary was crossed
+    ///
+    fn fetch_operand(&mut self, mode: AddressMode) -> (Operand, bool) {
+        use self::AddressMode::*;
+
+        match mode {
+            Implied                 => (Operand::None, false),
+            Accumulator             => (Operand::Accumulator, false),
+            Immediate               => (Operand::Value(self.fetch()), false),
+            Relative                => (Operand::Value(self.fetch()), false),
+            ZeroPageRelative        => (Operand::None, false),
+            ZeroPage                => (Operand::Address(self.fetch() as u16), false),
+            ZeroPageX               => (Operand::Address(self.fetch().wrapping_add(self.x) as u16), false),
+            ZeroPageY               => (Operand::Address(self.fetch().wrapping_add(self.y) as u16), false),
+            Absolute                => (Operand::Address(self.fetch_word()), false),
+
+            AbsoluteX               => {
+                let base = self.fetch_word();
+                let addr = base.wrapping_add(self.x as u16);
+                (Operand::Address(addr), (base & 0xff00) != (addr & 0xff00))
+            },
+
+            AbsoluteY               => {
+                let base = self.fetch_word();
+                let addr = base.wrapping_add(self.y as u16);
+                (Operand::Address(addr), (base & 0xff00) != (addr & 0xff00))
+            },
+
+            Indirect                => {
+                let pointer = self.fetch_word();
+                let addr    = if self.variant == Cpu6502Variant::Nmos6502 && (pointer & 0xff) == 0xff {
+                    // The NMOS 6502 doesn't carry into the high byte of the pointer
+                    (self.read(pointer) as u16) | ((self.read(pointer & 0xff00) as u16) << 8)
+                } else {
+                    self.read_word(pointer)
+                };
+                (Operand::Address(addr), false)
+            },
+
+            AbsoluteIndexedIndirect => {
+                let pointer = self.fetch_word().wrapping_add(self.x as u16);
+                (Operand::Address(self.read_word(pointer)), false)
+            },
+
+            IndexedIndirect         => {
+                let pointer = self.fetch().wrapping_add(self.x);
+                (Operand::Address(self.read_zero_page_word(pointer)), false)
+            },
+
+            IndirectIndexed         => {
+                let pointer = self.fetch();
+                let base    = self.read_zero_page_word(pointer);
+                let addr    = base.wrapping_add(self.y as u16);
+                (Operand::Address(addr), (base & 0xff00) != (addr & 0xff00))
+            },
+
+            ZeroPageIndirect        => {
+                let pointer = self.fetch();
+                (Operand::Address(self.read_zero_page_word(pointer)), false)
+            }
+        }
+    }
+
+    fn read_operand(&self, operand: Operand) -> u8 {
+        match operand {
+            Operand::None           => 0,
+            Operand::Accumulator    => self.a,
+            Operand::Value(value)   => value,
+            Operand::Address(addr)  => self.read(addr)
+        }
+    }
+
+    fn write_operand(&mut self, operand: Operand, value: u8) {
+        match operand {
+            Operand::None           |
+            Operand::Value(_)       => { },
+            Operand::Accumulator    => { self.a = value; },
+            Operand::Address(addr)  => { self.write(addr, value); }
+        }
+    }
+
+    fn operand_address(operand: Operand) -> u16 {
+        match operand {
+            Operand::Address(addr)  => addr,
+            _                       => 0
+        }
+    }
+
+    ///
+    /// Moves the program counter by a relative branch offset, returning the number of extra cycles this takes
+    ///
+    fn branch(&mut self, offset: u8) -> u64 {
+        let target  = self.pc.wrapping_add(offset as i8 as u16);
+        let cycles  = if (target & 0xff00) != (self.pc & 0xff00) { 2 } else { 1 };
+
+        self.pc = target;
+        cycles
+    }
+
+    ///
+    /// Adds a value to the accumulator with carry
+    ///
+    fn add_with_carry(&mut self, value: u8) {
+        let carry   = if self.flag(FLAG_C) { 1 } else { 0 };
+        let sum     = (self.a as u16) + (value as u16) + carry;
+        let result  = sum as u8;
+
+        if self.flag(FLAG_D) {
+            // Binary coded decimal: the low digit is adjusted first, and the overflow flag is set from the result before the high digit is adjusted
+            let mut low     = ((self.a & 0x0f) as u16) + ((value & 0x0f) as u16) + carry;
+            if low >= 0x0a { low = ((low + 0x06) & 0x0f) + 0x10; }
+
+            let mut decimal = ((self.a & 0xf0) as u16) + ((value & 0xf0) as u16) + low;
+            let unadjusted  = decimal as u8;
+            self.set_flag(FLAG_V, ((self.a ^ unadjusted) & (value ^ unadjusted) & 0x80) != 0);
+
+            if decimal >= 0xa0 { decimal += 0x60; }
+            self.set_flag(FLAG_C, decimal > 0xff);
+
+            if self.variant == Cpu6502Variant::Wdc65c02 {
+                // The 65c02 sets the N and Z flags from the decimal result
+                self.set_nz(decimal as u8);
+            } else {
+                // The NMOS 6502 sets Z from the binary result and N from the result before the high digit is adjusted
+                self.set_flag(FLAG_Z, result == 0);
+                self.set_flag(FLAG_N, (unadjusted & 0x80) != 0);
+            }
+
+            self.a = decimal as u8;
+        } else {
+            self.add_binary(value);
+        }
+    }
+
+    ///
+    /// Subtracts a value from the accumulator with borrow
+    ///
+    fn subtract_with_carry(&mut self, value: u8) {
+        if self.flag(FLAG_D) {
+            // The flags are set as for a binary subtraction (except that the 65c02 sets N and Z from the decimal result)
+            let carry       = if self.flag(FLAG_C) { 1 } else { 0 };
+            let original    = self.a;
+            self.add_binary(!value);
+
+            let mut low     = ((original & 0x0f) as i16) - ((value & 0x0f) as i16) + carry - 1;
+            if low < 0 { low = ((low - 0x06) & 0x0f) - 0x10; }
+
+            let mut decimal = ((original & 0xf0) as i16) - ((value & 0xf0) as i16) + low;
+            if decimal < 0 { decimal -= 0x60; }
+
+            if self.variant == Cpu6502Variant::Wdc65c02 {
+                self.set_nz(decimal as u8);
+            }
+
+            self.a = decimal as u8;
+        } else {
+            self.add_binary(!value);
+        }
+    }
+
+    ///
+    /// Adds a value to the accumulator with carry, ignoring the decimal flag
+    ///
+    fn add_binary(&mut self, value: u8) {
+        let carry   = if self.flag(FLAG_C) { 1 } else { 0 };
+        let sum     = (self.a as u16) + (value as u16) + carry;
+        let result  = sum as u8;
+
+        self.set_flag(FLAG_C, sum > 0xff);
+        self.set_flag(FLAG_V, ((self.a ^ result) & (value ^ result) & 0x80) != 0);
+        self.a = self.set_nz(result);
+    }
+
+    ///
+    /// Compares a register against a value
+    ///
+    fn compare(&mut self, register: u8, value: u8) {
+        self.set_flag(FLAG_C, register >= value);
+        self.set_nz(register.wrapping_sub(value));
+    }
+
+    ///
+    /// Executes a single instruction, returning the reason that the emulator should stop if it should
+    ///
+    fn step(&mut self, initial_sp: u8) -> Result<Option<StopReason>, RuntimeError> {
+        use self::Operation::*;
+
+        let address                 = self.pc;
+        let opcode                  = self.fetch();
+        let (operation, mode)       = self.decode(opcode).ok_or(RuntimeError::IllegalOpcode(opcode, address))?;
+        let (operand, page_crossed) = self.fetch_operand(mode);
+
+        let mut cycles              = Self::base_cycles(operation, mode);
+        let mut stop                = None;
+
+        match operation {
+            // Loads and stores
+            Lda                     => { let value = self.read_operand(operand); self.a = self.set_nz(value); },
+            Ldx                     => { let value = self.read_operand(operand); self.x = self.set_nz(value); },
+            Ldy                     => { let value = self.read_operand(operand); self.y = self.set_nz(value); },
+            Sta                     => { self.write_operand(operand, self.a); },
+            Stx                     => { self.write_operand(operand, self.x); },
+            Sty                     => { self.write_operand(operand, self.y); },
+            Stz                     => { self.write_operand(operand, 0); },
+
+            // Transfers
+            Tax                     => { self.x = self.set_nz(self.a); },
+            Tay                     => { self.y = self.set_nz(self.a); },
+            Txa                     => { self.a = self.set_nz(self.x); },
+            Tya                     => { self.a = self.set_nz(self.y); },
+            Tsx                     => { self.x = self.set_nz(self.sp); },
+            Txs                     => { self.sp = self.x; },
+
+            // Stack
+            Pha                     => { self.push(self.a); },
+            Phx                     => { self.push(self.x); },
+            Phy                     => { self.push(self.y); },
+            Php                     => { self.push(self.status | FLAG_B | FLAG_U); },
+            Pla                     => { let value = self.pull(); self.a = self.set_nz(value); },
+            Plx                     => { let value = self.pull(); self.x = self.set_nz(value); },
+            Ply                     => { let value = self.pull(); self.y = self.set_nz(value); },
+            Plp                     => { self.status = (self.pull() & !FLAG_B) | FLAG_U; },
+
+            // Arithmetic and logic
+            Adc                     => { let value = self.read_operand(operand); self.add_with_carry(value); },
+            Sbc                     => { let value = self.read_operand(operand); self.subtract_with_carry(value); },
+            And                     => { let value = self.read_operand(operand); self.a = self.set_nz(self.a & value); },
+            Ora                     => { let value = self.read_operand(operand); self.a = self.set_nz(self.a | value); },
+            Eor                     => { let value = self.read_operand(operand); self.a = self.set_nz(self.a ^ value); },
+            Cmp                     => { let value = self.read_operand(operand); self.compare(self.a, value); },
+            Cpx                     => { let value = self.read_operand(operand); self.compare(self.x, value); },
+            Cpy                     => { let value = self.read_operand(operand); self.compare(self.y, value); },
+
+            Bit                     => {
+                let value = self.read_operand(operand);
+                self.set_flag(FLAG_Z, (self.a & value) == 0);
+
+                // The immediate mode only affects the zero flag
+                if mode != AddressMode::Immediate {
+                    self.set_flag(FLAG_N, (value & 0x80) != 0);
+                    self.set_flag(FLAG_V, (value & 0x40) != 0);
+                }
+            },
+
+            // Increment and decrement
+            Inc                     => { let value = self.read_operand(operand).wrapping_add(1); self.set_nz(value); self.write_operand(operand, value); },
+            Dec                     => { let value = self.read_operand(operand).wrapping_sub(1); self.set_nz(value); self.write_operand(operand, value); },
+            Inx                     => { self.x = self.set_nz(self.x.wrapping_add(1)); },
+            Iny                     => { self.y = self.set_nz(self.y.wrapping_add(1)); },
+            Dex                     => { self.x = self.set_nz(self.x.wrapping_sub(1)); },
+            Dey                     => { self.y = self.set_nz(self.y.wrapping_sub(1)); },
+
+            // Shifts and rotates
+            Asl                     => {
+                let value = self.read_operand(operand);
+                self.set_flag(FLAG_C, (value & 0x80) != 0);
+                let value = self.set_nz(value << 1);
+                self.write_operand(operand, value);
+            },
+
+            Lsr                     => {
+                let value = self.read_operand(operand);
+                self.set_flag(FLAG_C, (value & 0x01) != 0);
+                let value = self.set_nz(value >> 1);
+                self.write_operand(operand, value);
+            },
+
+            Rol                     => {
+                let value = self.read_operand(operand);
+                let carry = if self.flag(FLAG_C) { 0x01 } else { 0 };
+                self.set_flag(FLAG_C, (value & 0x80) != 0);
+                let value = self.set_nz((value << 1) | carry);
+                self.write_operand(operand, value);
+            },
+
+            Ror                     => {
+                let value = self.read_operand(operand);
+                let carry = if self.flag(FLAG_C) { 0x80 } else { 0 };
+                self.set_flag(FLAG_C, (value & 0x01) != 0);
+                let value = self.set_nz((value >> 1) | carry);
+                self.write_operand(operand, value);
+            },
+
+            // Test and set/reset bits
+            Tsb                     => {
+                let value = self.read_operand(operand);
+                self.set_flag(FLAG_Z, (self.a & value) == 0);
+                self.write_operand(operand, value | self.a);
+            },
+
+            Trb                     => {
+                let value = self.read_operand(operand);
+                self.set_flag(FLAG_Z, (self.a & value) == 0);
+                self.write_operand(operand, value & !self.a);
+            },
+
+            Rmb(bit)                => { let value = self.read_operand(operand); self.write_operand(operand, value & !(1<<bit)); },
+            Smb(bit)                => { let value = self.read_operand(operand); self.write_operand(operand, value | (1<<bit)); },
+
+            // Flags
+            SetFlag(flag, value)    => { self.set_flag(flag, value); },
+
+            // Branches
+            Branch(flag, value)     => {
+                if self.flag(flag) == value {
+                    cycles += self.branch(self.read_operand(operand));
+                }
+            },
+
+            Bra                     => { cycles += self.branch(self.read_operand(operand)) - 1; },
+
+            Bbr(bit) | Bbs(bit)     => {
+                let location    = self.fetch();
+                let offset      = self.fetch();
+                let value       = self.read(location as u16);
+                let is_set      = (value & (1<<bit)) != 0;
+
+                if is_set == (operation == Bbs(bit)) {
+                    cycles += self.branch(offset);
+                }
+            },
+
+            // Jumps and subroutines
+            Jmp                     => { self.pc = Self::operand_address(operand); },
+
+            Jsr                     => {
+                self.push_word(self.pc.wrapping_sub(1));
+                self.pc = Self::operand_address(operand);
+            },
+
+            Rts                     => {
+                if self.sp == initial_sp {
+                    // Returning from the routine the emulator started in
+                    stop = Some(StopReason::Return);
+                } else {
+                    self.pc = self.pull_word().wrapping_add(1);
+                }
+            },
+
+            Rti                     => {
+                self.status = (self.pull() & !FLAG_B) | FLAG_U;
+                self.pc     = self.pull_word();
+            },
+
+            // Stopping the processor
+            Brk                     => { stop = Some(StopReason::Break); },
+            Stp                     => { stop = Some(StopReason::Stop); },
+            Wai                     => { stop = Some(StopReason::Wait); },
+
+            Nop                     => { }
+        }
+
+        // Reading across a page boundary takes an extra cycle
+        if page_crossed {
+            match operation {
+                Lda | Ldx | Ldy | Adc | Sbc | And | Ora | Eor | Cmp | Bit => { cycles += 1; },
+                _ => { }
+            }
+        }
+
+        self.cycles += cycles;
+        Ok(stop)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(variant: Cpu6502Variant, code: &[u8]) -> Cpu6502 {
+        let mut cpu = Cpu6502::new(variant);
+        cpu.load(0x200, code);
+        cpu.pc = 0x200;
+        cpu.run(10000).unwrap();
+        cpu
+    }
+
+    #[test]
+    fn load_and_add() {
+        // lda #$12 ; clc ; adc #$34 ; brk
+        let cpu = run(Cpu6502Variant::Nmos6502, &[0xa9, 0x12, 0x18, 0x69, 0x34, 0x00]);
+
+        assert!(cpu.a == 0x46);
+        assert!(!cpu.flag(FLAG_C));
+    }
+
+    #[test]
+    fn decimal_add() {
+        // sed ; clc ; lda #$19 ; adc #$28 ; brk
+        let cpu = run(Cpu6502Variant::Nmos6502, &[0xf8, 0x18, 0xa9, 0x19, 0x69, 0x28, 0x00]);
+
+        assert!(cpu.a == 0x47);
+    }
+
+    #[test]
+    fn loop_with_branch() {
+        // ldx #$05 ; lda #$00 ; loop: clc ; adc #$03 ; dex ; bne loop ; brk
+        let cpu = run(Cpu6502Variant::Nmos6502, &[0xa2, 0x05, 0xa9, 0x00, 0x18, 0x69, 0x03, 0xca, 0xd0, 0xfa, 0x00]);
+
+        assert!(cpu.a == 15);
+        assert!(cpu.x == 0);
+        assert!(cpu.flag(FLAG_Z));
+    }
+
+    #[test]
+    fn subroutine_call() {
+        // jsr $0206 ; sta $10 ; rts ; lda #$42 ; rts
+        let cpu = run(Cpu6502Variant::Nmos6502, &[0x20, 0x06, 0x02, 0x85, 0x10, 0x60, 0xa9, 0x42, 0x60]);
+
+        assert!(cpu.memory[0x10] == 0x42);
+        assert!(cpu.sp == 0xfd);
+    }
+
+    #[test]
+    fn cmos_opcodes_are_illegal_on_nmos() {
+        // stz $10
+        let mut cpu = Cpu6502::new(Cpu6502Variant::Nmos6502);
+        cpu.load(0, &[0x64, 0x10]);
+
+        assert!(matches!(cpu.run(100), Err(RuntimeError::IllegalOpcode(0x64, 0))));
+    }
+
+    #[test]
+    fn branch_on_bit_set() {
+        // smb3 $10 ; bbs3 $10, +1 ; brk ; lda #$01 ; stp
+        let cpu = run(Cpu6502Variant::Wdc65c02, &[0xb7, 0x10, 0xbf, 0x10, 0x01, 0x00, 0xa9, 0x01, 0xdb]);
+
+        assert!(cpu.memory[0x10] == 0x08);
+        assert!(cpu.a == 0x01);
+    }
+
+    #[test]
+    fn cycle_limit() {
+        // loop: jmp loop
+        let mut cpu = Cpu6502::new(Cpu6502Variant::Nmos6502);
+        cpu.load(0, &[0x4c, 0x00, 0x00]);
+
+        assert!(matches!(cpu.run(100), Err(RuntimeError::CycleLimitReached(100))));
+    }
+}
@@ -0,0 +1,5 @@
+mod cpu_6502;
+mod run_6502;
+
+pub use self::cpu_6502::*;
+pub use self::run_6502::*;
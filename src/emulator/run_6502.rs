@@ -0,0 +1,200 @@
+use super::cpu_6502::*;
+
+use crate::meta::*;
+use crate::exec::*;
+use crate::bitcode::*;
+
+/// The number of cycles the emulator runs for if no limit is specified
+const DEFAULT_MAX_CYCLES: u64 = 1_000_000;
+
+///
+/// Retrieves the bytes to load into the emulator from a program (either a bitcode cell, or the list returned by `assemble`)
+///
+fn program_bytes(program: &CellRef) -> Result<Vec<u8>, RuntimeError> {
+    match &**program {
+        SafasCell::BitCode(bitcode)     => Ok(bitcode_to_bytes(bitcode.iter().cloned())),
+        SafasCell::List(car, _cdr)      => {
+            match &**car {
+                SafasCell::BitCode(bitcode) => Ok(bitcode_to_bytes(bitcode.iter().cloned())),
+                _                           => Err(RuntimeError::NotBitCode(program.clone()))
+            }
+        },
+        _                               => Err(RuntimeError::NotBitCode(program.clone()))
+    }
+}
+
+///
+/// Reads a numeric option
+///
+fn number_option(value: &CellRef) -> Result<usize, RuntimeError> {
+    value.number_value().map(|number| number.to_usize()).ok_or_else(|| RuntimeError::NotANumber(value.clone()))
+}
+
+///
+/// Generates the result of running the emulator
+///
+fn emulator_result(cpu: &Cpu6502, stop_reason: StopReason, memory_addresses: &[u16]) -> Result<CellRef, RuntimeError> {
+    let byte        = |value: u8| SafasCell::Number(SafasNumber::BitNumber(8, value as u128)).into();
+    let flag        = |flag: u8| SafasCell::Boolean(cpu.flag(flag)).into();
+    let stop_reason = match stop_reason {
+        StopReason::Break   => "brk",
+        StopReason::Return  => "rts",
+        StopReason::Stop    => "stp",
+        StopReason::Wait    => "wai"
+    };
+    let memory      = SafasCell::list_with_cells(memory_addresses.iter().map(|address| byte(cpu.memory[*address as usize])).collect::<Vec<_>>());
+
+    let values: Vec<(&str, CellRef)> = vec![
+        ("a",       byte(cpu.a)),
+        ("x",       byte(cpu.x)),
+        ("y",       byte(cpu.y)),
+        ("sp",      byte(cpu.sp)),
+        ("p",       byte(cpu.status)),
+        ("pc",      SafasCell::Number(SafasNumber::BitNumber(16, cpu.pc as u128)).into()),
+        ("c",       flag(FLAG_C)),
+        ("z",       flag(FLAG_Z)),
+        ("i",       flag(FLAG_I)),
+        ("d",       flag(FLAG_D)),
+        ("v",       flag(FLAG_V)),
+        ("n",       flag(FLAG_N)),
+        ("cycles",  SafasCell::Number(SafasNumber::Plain(cpu.cycles as u128)).into()),
+        ("stop",    SafasCell::atom(stop_reason)),
+        ("memory",  memory)
+    ];
+
+    let mut result = btree_new();
+    for (key, value) in values {
+        result = btree_insert(result, (SafasCell::atom(key), value))?;
+    }
+
+    Ok(result)
+}
+
+///
+/// Loads a program into an emulated CPU and runs it
+///
+fn run_program(variant: Cpu6502Variant, args: Vec<CellRef>) -> Result<CellRef, RuntimeError> {
+    if args.is_empty() { return Err(RuntimeError::NotEnoughArguments(SafasCell::list_with_cells(args))); }
+
+    let program                 = program_bytes(&args[0])?;
+    let mut cpu                 = Cpu6502::new(variant);
+    let mut load_address        = 0u16;
+    let mut start_address       = None;
+    let mut max_cycles          = DEFAULT_MAX_CYCLES;
+    let mut memory_addresses    = vec![];
+
+    // The options are pairs of values following the program
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().ok_or_else(|| RuntimeError::NotEnoughArguments(option.clone()))?;
+
+        match option.to_atom_id().map(name_for_atom_with_id).as_deref() {
+            Some(":load")       => { load_address = number_option(value)? as u16; },
+            Some(":start")      => { start_address = Some(number_option(value)? as u16); },
+            Some(":max_cycles") => { max_cycles = number_option(value)? as u64; },
+            Some(":a")          => { cpu.a = number_option(value)? as u8; },
+            Some(":x")          => { cpu.x = number_option(value)? as u8; },
+            Some(":y")          => { cpu.y = number_option(value)? as u8; },
+
+            Some(":memory")     => {
+                // Either a single address or a list of addresses
+                memory_addresses = match &**value {
+                    SafasCell::List(_, _)   => value.to_vec().unwrap_or(vec![]).iter().map(|address| number_option(address).map(|address| address as u16)).collect::<Result<_, _>>()?,
+                    _                       => vec![number_option(value)? as u16]
+                };
+            },
+
+            _                   => { return Err(RuntimeError::UnknownOption(option.clone())); }
+        }
+    }
+
+    // Load and run the program
+    cpu.load(load_address, &program);
+    cpu.pc = start_address.unwrap_or(load_address);
+
+    let stop_reason = cpu.run(max_cycles)?;
+
+    emulator_result(&cpu, stop_reason, &memory_addresses)
+}
+
+///
+/// `(run_6502 program [:load address] [:start address] [:max_cycles n] [:a value] [:x value] [:y value] [:memory addresses])`
+///
+/// Runs a program on an emulated 6502 with 64k of RAM. The program is the bitcode returned by `assemble` (or the list that
+/// `assemble` returns), and is loaded at the `:load` address (0 by default). Execution starts at the `:start` address (the
+/// load address by default) and continues until a `brk` instruction, or an `rts` that returns from the starting routine.
+/// It's an error if this doesn't happen within `:max_cycles` cycles.
+///
+/// The result is a btree containing the registers (`a`, `x`, `y`, `sp`, `p` as 8-bit values, and `pc` as a 16-bit value),
+/// the flags (`c`, `z`, `i`, `d`, `v`, `n`), the number of `cycles` executed, the instruction that stopped the program
+/// (`stop`), and a list of the bytes at the addresses passed in as `:memory`.
+///
+pub fn run_6502_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|args: Vec<CellRef>| {
+        run_program(Cpu6502Variant::Nmos6502, args)
+    })
+}
+
+///
+/// `(run_65c02 program [options])` - as for `run_6502`, except the emulated CPU is a 65c02 (which also stops on the `stp` and
+/// `wai` instructions)
+///
+pub fn run_65c02_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|args: Vec<CellRef>| {
+        run_program(Cpu6502Variant::Wdc65c02, args)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+
+    #[test]
+    fn run_bitcode() {
+        let val = eval("
+            (def result (run_6502 (car (assemble (d $a9u8 $42u8 $00u8)))))
+            (btree_lookup result (quote a))
+        ").unwrap().to_string();
+
+        assert!(val == "$42u8".to_string());
+    }
+
+    #[test]
+    fn read_memory_and_flags() {
+        let val = eval("
+            (def result (run_6502 (assemble (d $a9u8 $ffu8 $85u8 $10u8 $0au8 $00u8)) :memory (list $10 $11)))
+            (list (btree_lookup result (quote memory)) (btree_lookup result (quote c)) (btree_lookup result (quote stop)))
+        ").unwrap().to_string();
+
+        assert!(val == "(($ffu8 $0u8) =t brk)".to_string());
+    }
+
+    #[test]
+    fn set_registers_and_start_address() {
+        let val = eval("
+            (def result (run_6502 (assemble (d $00u8 $8au8 $60u8)) :load $200 :start $201 :x 7))
+            (list (btree_lookup result (quote a)) (btree_lookup result (quote stop)))
+        ").unwrap().to_string();
+
+        assert!(val == "($7u8 rts)".to_string());
+    }
+
+    #[test]
+    fn unknown_option() {
+        let val = eval("
+            (run_6502 (assemble (d $00u8)) :speed 2)
+        ");
+
+        assert!(format!("{:?}", val).contains("UnknownOption"));
+    }
+
+    #[test]
+    fn run_65c02_instructions() {
+        let val = eval("
+            (def result (run_65c02 (assemble (d $a9u8 $01u8 $1au8 $dbu8))))
+            (list (btree_lookup result (quote a)) (btree_lookup result (quote stop)))
+        ").unwrap().to_string();
+
+        assert!(val == "($2u8 stp)".to_string());
+    }
+}
@@ -5,7 +5,7 @@ use super::runtime_error::*;
 use crate::meta::*;
 
 use smallvec::*;
-use std::collections::{HashMap};
+use std::collections::{HashMap, HashSet};
 use std::sync::*;
 use std::iter::{FromIterator};
 
@@ -69,6 +69,18 @@ impl Action {
     /// Performs simple peephole optimisation on a series of actions, combining operations that are easy to combine
     ///
     pub fn peephole_optimise<ActionIter: IntoIterator<Item=Action>, Target: FromIterator<Action>>(actions: ActionIter) -> Target {
+        let actions     = actions.into_iter().collect::<Vec<_>>();
+
+        // Actions that are the target of a jump can't be combined with the actions before them
+        let jump_targets = actions.iter().enumerate()
+            .flat_map(|(pos, action)| match action {
+                Action::Jump(offset)        |
+                Action::JumpIfFalse(offset) => Some(((pos as isize) + offset) as usize),
+                _                           => None
+            })
+            .collect::<HashSet<_>>();
+        let is_target   = |pos: &usize| jump_targets.contains(pos);
+
         let mut actions = actions.into_iter().enumerate().fuse();
 
         // The window represents the instructions we're inspecting
@@ -88,25 +100,25 @@ impl Action {
             }
 
             match window {
-                (action1, action2, Some((pos1, Action::Value(val))), Some((_pos2, Action::Push))) => {
+                (action1, action2, Some((pos1, Action::Value(val))), Some((pos2, Action::Push))) if !is_target(&pos2) => {
                     // Value, Push => PushValue
                     window = (None, action1, action2, Some((pos1, Action::PushValue(val))));
                 }
 
-                (action1, action2, Some((pos1, Action::CellValue(cell_id))), Some((_pos2, Action::Push))) => {
+                (action1, action2, Some((pos1, Action::CellValue(cell_id))), Some((pos2, Action::Push))) if !is_target(&pos2) => {
                     // CellValue, Push => PushCell
                     window = (None, action1, action2, Some((pos1, Action::PushCell(cell_id))));
                 }
 
-                (Some((pos1, Action::PopList(arg_count))), Some((_pos2, Action::StoreCell(0))), Some((_pos3, Action::Pop)), Some((_pos4, Action::Call))) => {
+                (Some((pos1, Action::PopList(arg_count))), Some((pos2, Action::StoreCell(0))), Some((pos3, Action::Pop)), Some((pos4, Action::Call))) if !is_target(&pos2) && !is_target(&pos3) && !is_target(&pos4) => {
                     window = (None, None, None, Some((pos1, Action::PopCall(arg_count))));
                 }
 
-                (action1, action2, Some((_pos1, Action::Push)), Some((_pos2, Action::Pop))) => {
+                (action1, action2, Some((pos1, Action::Push)), Some((pos2, Action::Pop))) if !is_target(&pos1) && !is_target(&pos2) => {
                     window = (None, None, action1, action2);
                 },
 
-                (action1, action2, Some((_pos1, Action::Pop)), Some((_pos2, Action::Push))) => {
+                (action1, action2, Some((pos1, Action::Pop)), Some((pos2, Action::Push))) if !is_target(&pos1) && !is_target(&pos2) => {
                     // Slight behaviour difference: the result is not the popped value after this
                     window = (None, None, action1, action2);
                 },
@@ -0,0 +1,141 @@
+use super::runtime_error::*;
+
+use crate::bind::*;
+use crate::parse::*;
+
+use std::sync::*;
+
+///
+/// Finds the locations attached to an error (outermost first) and returns them along with a description of the error itself
+///
+fn error_locations(error: &RuntimeError, locations: &mut Vec<Arc<SourceLocation>>) -> String {
+    match error {
+        RuntimeError::AtLocation(location, error)   => { locations.push(location.clone()); error_locations(error, locations) },
+        RuntimeError::BindingError(error)           => bind_error_locations(error, locations),
+        other                                       => format!("{:?}", other)
+    }
+}
+
+///
+/// Finds the locations attached to a bind error (outermost first) and returns them along with a description of the error itself
+///
+fn bind_error_locations(error: &BindError, locations: &mut Vec<Arc<SourceLocation>>) -> String {
+    match error {
+        BindError::AtLocation(location, error)  => { locations.push(location.clone()); bind_error_locations(error, locations) },
+        other                                   => format!("{:?}", other)
+    }
+}
+
+///
+/// Writes out a source location, with the text of the statement and a marker showing where it starts
+///
+fn write_location(report: &mut String, location: &SourceLocation, gutter_width: usize) {
+    let column = location.location.chr_idx.max(1) as usize;
+    let indent = " ".repeat(column-1);
+
+    report.push_str(&format!("{:width$}--> {}:{}:{}\n", "", location.location.filename, location.location.line, column, width=gutter_width));
+    report.push_str(&format!("{:width$} |\n", "", width=gutter_width));
+    report.push_str(&format!("{:>width$} | {}{}\n", location.location.line, indent, location.text, width=gutter_width));
+    report.push_str(&format!("{:width$} | {}^\n", "", indent, width=gutter_width));
+}
+
+///
+/// Generates a report describing an error and where it occurred
+///
+/// The report starts with the location where the error occurred and is followed by the locations of any macros,
+/// functions or imported files that were being expanded at the time.
+///
+pub fn error_report(error: &RuntimeError) -> String {
+    let mut locations   = vec![];
+    let description     = error_locations(error, &mut locations);
+
+    // Line numbers are aligned in the gutter
+    let gutter_width    = locations.iter()
+        .map(|location| location.location.line.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut report      = format!("error: {}\n", description);
+
+    // The innermost location is where the error occurred, the others are the context
+    for (idx, location) in locations.iter().rev().enumerate() {
+        if idx > 0 { report.push_str("note: in this expansion\n"); }
+        write_location(&mut report, location, gutter_width);
+    }
+
+    report
+}
+
+///
+/// Generates a report for a warning, showing where it occurred if the location is known
+///
+pub fn warning_report(message: &str, location: Option<&SourceLocation>) -> String {
+    let mut report = format!("warning: {}\n", message);
+
+    if let Some(location) = location {
+        let gutter_width = location.location.line.to_string().len();
+        write_location(&mut report, location, gutter_width);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interactive::*;
+    use crate::bitcode::*;
+
+    #[test]
+    fn report_unknown_symbol() {
+        let error   = eval("(d $1u8)\n  (d not_a_symbol)").unwrap_err();
+        let report  = error_report(&error);
+
+        assert!(report == "error: UnknownSymbol(\"not_a_symbol\")\n --> <expr>:2:3\n  |\n2 |   (d not_a_symbol)\n  |   ^\n");
+    }
+
+    #[test]
+    fn report_error_in_macro() {
+        let error   = eval("(def_syntax bad ((bad) ((d not_a_symbol))))\n(bad)").unwrap_err();
+        let report  = error_report(&error);
+
+        assert!(report == "error: UnknownSymbol(\"not_a_symbol\")\n --> <expr>:1:25\n  |\n1 |                         (d not_a_symbol)\n  |                         ^\n");
+    }
+
+    #[test]
+    fn report_error_during_assembly() {
+        let monad   = eval("(def f (fun (x) (d (bits 8 (+ x \"a\")))))\n(label l)\n(f l)").unwrap();
+        let monad   = BitCodeMonad::from_cell(&monad).unwrap();
+        let error   = assemble(&monad).unwrap_err();
+        let report  = error_report(&error);
+
+        assert!(report == "error: TypeMismatch(\"a\")\n --> <expr>:3:1\n  |\n3 | (f l)\n  | ^\n");
+    }
+
+    #[test]
+    fn report_context() {
+        let inner   = SourceLocation::new(FileLocation { line: 12, chr_idx: 3, file_idx: 100, ..FileLocation::new("lib.sf") }, "(bad)");
+        let outer   = SourceLocation::new(FileLocation::new("main.sf"), "(import \"lib\")");
+        let error   = RuntimeError::NotImplemented.with_location(Arc::new(inner)).with_location(Arc::new(outer));
+        let report  = error_report(&error);
+
+        assert!(report == "error: NotImplemented\n  --> lib.sf:12:3\n   |\n12 |   (bad)\n   |   ^\nnote: in this expansion\n  --> main.sf:1:1\n   |\n 1 | (import \"lib\")\n   | ^\n");
+    }
+
+    #[test]
+    fn contained_location_is_not_context() {
+        let inner   = SourceLocation::new(FileLocation { chr_idx: 4, file_idx: 3, ..FileLocation::new("main.sf") }, "(bad)");
+        let outer   = SourceLocation::new(FileLocation::new("main.sf"), "(d (bad))");
+        let error   = RuntimeError::NotImplemented.with_location(Arc::new(inner.clone())).with_location(Arc::new(outer));
+
+        assert!(error.location() == Some(Arc::new(inner)));
+    }
+
+    #[test]
+    fn report_warning() {
+        let location    = SourceLocation::new(FileLocation { line: 3, ..FileLocation::new("main.sf") }, "(warn \"Oops\")");
+        let report      = warning_report("Oops", Some(&location));
+
+        assert!(report == "warning: Oops\n --> main.sf:3:1\n  |\n3 | (warn \"Oops\")\n  | ^\n");
+    }
+}
@@ -1,6 +1,8 @@
 use super::frame::*;
 use super::frame_monad::*;
 
+use super::runtime_error::*;
+
 use crate::meta::*;
 use crate::bind::*;
 
@@ -26,6 +28,16 @@ fn wrap_value(value: CellRef) -> CellRef {
     SafasCell::Monad(NIL.clone(), monad_type).into()
 }
 
+///
+/// Attaches the location of a statement (if it's known) to an error that occurred while evaluating it
+///
+fn locate_error(error: RuntimeError, statement: &CellRef, bindings: &SymbolBindings) -> RuntimeError {
+    match bindings.source_locations.location(statement) {
+        Some(location)  => error.with_location(location),
+        None            => error
+    }
+}
+
 ///
 /// Evaluates a set of parsed statements
 /// 
@@ -75,9 +87,9 @@ pub fn eval_statements(statement_list: CellRef, monad: CellRef, bindings: Symbol
 
                 // Compile the bound statement
                 let actions         = compile_statement(bound);
-                let actions         = match actions { Ok(actions) => actions, Err(err) => return (SafasCell::Error(err.into()).into(), bindings, frame) };
+                let actions         = match actions { Ok(actions) => actions, Err(err) => return (SafasCell::Error(locate_error(err.into(), statement, &bindings)).into(), bindings, frame) };
 
-                compiled_actions.push(actions);
+                compiled_actions.push((statement.clone(), actions));
             }
         }
 
@@ -91,21 +103,21 @@ pub fn eval_statements(statement_list: CellRef, monad: CellRef, bindings: Symbol
     let (nil_monad_value, nil_monad_type) = (NIL.clone(), wrap_nil());
 
     // Run the setup actions
-    for actions in compiled_actions.iter() {
+    for (statement, actions) in compiled_actions.iter() {
         let (new_frame, setup_result) = actions.frame_setup.execute(frame);
         frame = new_frame;
-        if let Err(err) = setup_result { return (SafasCell::Error(err.into()).into(), bindings, frame); }
+        if let Err(err) = setup_result { return (SafasCell::Error(locate_error(err, statement, &bindings)).into(), bindings, frame); }
     }
 
     // Evaluate the actions
-    for actions in compiled_actions {
+    for (statement, actions) in compiled_actions {
         // Collect the actions into a list we can run
         let actions = actions.actions;
 
         let expr_result = actions.execute(frame);
         let expr_result = match expr_result {
             (new_frame, Ok(expr_result))    => { frame = new_frame; expr_result }
-            (new_frame, Err(err))           => { return (SafasCell::Error(err.into()).into(), bindings, new_frame); }
+            (new_frame, Err(err))           => { return (SafasCell::Error(locate_error(err, &statement, &bindings)).into(), bindings, new_frame); }
         };
 
         // Combine the expression result into the final result
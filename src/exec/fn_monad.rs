@@ -31,7 +31,7 @@ pub trait FnResult : Sized {
 /// by your function and produce errors if the type is incorrect. Several implemenations are provided already, 
 /// allowing for creating monads out of a wide variety of types. For example:Arc
 /// 
-/// ```
+/// ```ignore
 ///     let car = FnMonad::from(|(SafasList(car, _cdr), )| { Arc::clone(&car) })
 /// ```
 /// 
@@ -1,11 +1,14 @@
 use crate::meta::*;
 use crate::bind::*;
 
+use super::limits::*;
+
 use smallvec::*;
 
 ///
 /// A SAFAS execution frame
 ///
+#[derive(Clone)]
 pub struct Frame {
     /// The frame above this one on the 'stack'
     pub previous_frame: Option<Box<Frame>>,
@@ -14,7 +17,13 @@ pub struct Frame {
     pub cells: SmallVec<[CellRef; 8]>,
 
     /// The stack for this frame
-    pub stack: SmallVec<[CellRef; 8]>
+    pub stack: SmallVec<[CellRef; 8]>,
+
+    /// The number of frames below this one
+    pub depth: usize,
+
+    /// The limits that apply to the code running in this frame (shared with the previous frame)
+    pub limits: SharedLimits
 }
 
 impl Frame {
@@ -22,10 +31,15 @@ impl Frame {
     /// Creates a new frame (with a previous frame, if appropriate)
     ///
     pub fn new(size: usize, previous_frame: Option<Frame>) -> Frame {
+        let depth   = previous_frame.as_ref().map(|frame| frame.depth + 1).unwrap_or(0);
+        let limits  = previous_frame.as_ref().map(|frame| frame.limits.clone()).unwrap_or_default();
+
         Frame {
             previous_frame: previous_frame.map(|frame| Box::new(frame)),
             cells:          smallvec![NIL.clone(); size],
-            stack:          smallvec![]
+            stack:          smallvec![],
+            depth:          depth,
+            limits:         limits
         }
     }
 
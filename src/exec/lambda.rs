@@ -1,5 +1,7 @@
 use super::frame::*;
 use super::frame_monad::*;
+use super::limits::*;
+use super::runtime_error::*;
 
 use crate::meta::*;
 
@@ -35,7 +37,7 @@ impl<Action: FrameMonad> Lambda<Action> {
     }
 }
 
-impl<Action: FrameMonad> FrameMonad for Lambda<Action> {
+impl<Action: FrameMonad<Binding=RuntimeResult>> FrameMonad for Lambda<Action> {
     type Binding = Action::Binding;
 
     fn description(&self) -> String {
@@ -45,6 +47,12 @@ impl<Action: FrameMonad> FrameMonad for Lambda<Action> {
     }
 
     fn execute(&self, frame: Frame) -> (Frame, Action::Binding) {
+        // Runaway recursion generates an error rather than overflowing the stack
+        let max_depth       = frame.limits.get().max_recursion_depth;
+        if frame.depth >= max_depth {
+            return (frame, Err(RuntimeError::RecursionLimitReached(max_depth)));
+        }
+
         // Args in cell 0 from the calling frame
         let mut args        = Arc::clone(&frame.cells[0]);
 
@@ -75,8 +83,8 @@ impl<Action: FrameMonad> FrameMonad for Lambda<Action> {
             arg_pos                 += 1;
         }
 
-        // Resolve the action (actually calling the function)
-        let (frame, result) = self.action.execute(frame);
+        // Resolve the action (actually calling the function), on a new stack segment if the current one is running out
+        let (frame, result) = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, move || self.action.execute(frame));
 
         // Pop the frame we pushed for the action
         let frame = frame.pop().expect("Calling frame");
@@ -0,0 +1,84 @@
+use std::sync::*;
+
+/// The maximum number of assembly passes we should attempt before deciding that a bitcode monad cannot be evaluated
+pub const DEFAULT_MAX_PASSES: usize = 1000;
+
+/// The maximum size of the assembled output, in bytes (16MB)
+pub const DEFAULT_MAX_OUTPUT_SIZE: u64 = 16*1024*1024;
+
+/// The maximum number of function calls that can be in progress at once
+pub const DEFAULT_MAX_RECURSION_DEPTH: usize = 500;
+
+/// When less than this much stack is left, function calls and macro expansions move on to a new stack segment (each call can use
+/// more than 10kB of stack in a debug build)
+pub const STACK_RED_ZONE: usize = 256*1024;
+
+/// The size of the stack segments that are allocated when the stack is running out
+pub const STACK_SEGMENT_SIZE: usize = 4*1024*1024;
+
+/// The maximum number of macro expansions that can be nested inside each other
+pub const DEFAULT_MAX_MACRO_DEPTH: usize = 128;
+
+///
+/// The limits on the resources that evaluating and assembling SAFAS code can use
+///
+/// Going over one of these limits generates an error instead of running out of memory or stack space. Deep recursion moves on
+/// to stack segments allocated on the heap, so this doesn't depend on the stack size of the thread doing the evaluation. Each
+/// session has its own limits (see `SharedLimits`), which can be changed from SAFAS code with the `set_limits` function.
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Limits {
+    /// The maximum number of passes the assembler will make while waiting for the labels to stabilise
+    pub max_passes: usize,
+
+    /// The maximum size of the assembled output, in bytes
+    pub max_output_size: u64,
+
+    /// The maximum number of function calls that can be in progress at once
+    pub max_recursion_depth: usize,
+
+    /// The maximum number of macro expansions that can be nested inside each other
+    pub max_macro_depth: usize
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_passes:             DEFAULT_MAX_PASSES,
+            max_output_size:        DEFAULT_MAX_OUTPUT_SIZE,
+            max_recursion_depth:    DEFAULT_MAX_RECURSION_DEPTH,
+            max_macro_depth:        DEFAULT_MAX_MACRO_DEPTH
+        }
+    }
+}
+
+///
+/// The limits that apply to a session, shared between its frames and symbol bindings
+///
+/// Cloning this shares the same limits: changing the limits via one copy changes them for every copy.
+///
+#[derive(Clone, Debug, Default)]
+pub struct SharedLimits(Arc<Mutex<Limits>>);
+
+impl SharedLimits {
+    ///
+    /// Creates a new set of shared limits
+    ///
+    pub fn new(limits: Limits) -> SharedLimits {
+        SharedLimits(Arc::new(Mutex::new(limits)))
+    }
+
+    ///
+    /// Retrieves the current limits
+    ///
+    pub fn get(&self) -> Limits {
+        *self.0.lock().unwrap()
+    }
+
+    ///
+    /// Changes the limits for everything sharing them
+    ///
+    pub fn set(&self, limits: Limits) {
+        *self.0.lock().unwrap() = limits;
+    }
+}
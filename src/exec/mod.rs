@@ -11,8 +11,10 @@ mod compiled_actions;
 mod lambda;
 mod closure;
 mod runtime_error;
+mod error_report;
 mod fn_monad;
 mod returns_monad;
+mod limits;
 
 pub use self::eval::*;
 pub use self::frame::*;
@@ -22,5 +24,7 @@ pub use self::compiled_actions::*;
 pub use self::lambda::*;
 pub use self::closure::*;
 pub use self::runtime_error::*;
+pub use self::error_report::*;
 pub use self::fn_monad::*;
 pub use self::returns_monad::*;
+pub use self::limits::*;
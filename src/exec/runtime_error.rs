@@ -2,6 +2,7 @@ use crate::meta::*;
 use crate::bind::*;
 use crate::parse::*;
 
+use std::sync::*;
 use std::num::{TryFromIntError};
 use std::result::{Result};
 use std::convert::{Infallible};
@@ -38,6 +39,9 @@ pub enum RuntimeError {
     /// Value cannot be treated as a string
     NotAString(CellRef),
 
+    /// Value cannot be treated as a character
+    NotAChar(CellRef),
+
     /// Value cannot be treated as a number
     NotANumber(CellRef),
 
@@ -65,6 +69,9 @@ pub enum RuntimeError {
     /// The number is too large to fit into the correct format
     NumberTooLarge,
 
+    /// A number was divided by zero
+    DivisionByZero,
+
     /// A file could not be found
     FileNotFound(String),
 
@@ -74,16 +81,85 @@ pub enum RuntimeError {
     /// An IO error occurred (we would have known the io::Error at the time but it's not compatible with RuntimeError as it can't be compared or cloned)
     IOError,
 
-    /// Assembly did not stabilise in the specified number of passes
-    TooManyPasses(usize),
+    /// Assembly did not stabilise in the specified number of passes (with the names of the labels that changed in the last pass, and the values they were given)
+    TooManyPasses(usize, Vec<(String, Vec<CellRef>)>),
+
+    /// Labels kept returning to earlier values even when instruction sizes were only allowed to grow (with the names of the labels and the values they cycled through)
+    UnstableLabels(Vec<(String, Vec<CellRef>)>),
+
+    /// The assembled output was larger than the maximum output size (the size of the output and the maximum size, in bytes)
+    OutputTooLarge(u64, u64),
+
+    /// Function calls were nested more deeply than the maximum recursion depth
+    RecursionLimitReached(usize),
 
     /// Two cells cannot be compared
-    CannotCompare(CellRef, CellRef)
+    CannotCompare(CellRef, CellRef),
+
+    /// An address is too large to be written in the requested output format
+    AddressTooLarge(u64),
+
+    /// A value does not fit into the requested number of bits
+    ValueOutOfRange(SafasNumber, u8),
+
+    /// Code that started in a memory region continued past the end of it (the region name, and the name of the last label before the code)
+    RegionOverflow(String, Option<String>),
+
+    /// Code was written over code that was generated earlier (the position in bytes, and the names of the last labels before the two pieces of code)
+    OverlappingCode(u64, Option<String>, Option<String>),
+
+    /// An attempt was made to read beyond the end of a file (the name of the file, and the position that was read up to)
+    ReadPastEndOfFile(String, usize),
+
+    /// An error generated by the `error` function, with its message (the location of the statement that raised it is attached with AtLocation)
+    UserError(String),
+
+    /// A named argument (eg `:start`) is not understood by the function it was passed to
+    UnknownOption(CellRef),
+
+    /// The emulator reached an opcode that it can't execute (the opcode and its address)
+    IllegalOpcode(u8, u16),
+
+    /// The emulated code did not finish within the cycle limit
+    CycleLimitReached(u64),
+
+    /// An item was requested from beyond the end of a list
+    IndexOutOfRange(usize),
+
+    /// An error that occurred at a particular location in the source code
+    AtLocation(Arc<SourceLocation>, Box<RuntimeError>)
 }
 
 /// The result of a runtime operation (most common binding type of a frame monad)
 pub type RuntimeResult = Result<CellRef, RuntimeError>;
 
+impl RuntimeError {
+    ///
+    /// Attaches a source location to this error
+    ///
+    /// If the error already has a location that's contained within the new location, the error is returned unchanged (the existing
+    /// location is more precise). Otherwise the new location is added as context: this happens when the error occurred inside a macro,
+    /// a function or an imported file, and the new location is where that was used.
+    ///
+    pub fn with_location(self, location: Arc<SourceLocation>) -> RuntimeError {
+        match self.location() {
+            Some(existing_location) if location.contains(&existing_location)    => self,
+            _                                                                   => RuntimeError::AtLocation(location, Box::new(self))
+        }
+    }
+
+    ///
+    /// Retrieves the outermost location attached to this error, if there is one
+    ///
+    pub fn location(&self) -> Option<Arc<SourceLocation>> {
+        match self {
+            RuntimeError::AtLocation(location, _)   => Some(location.clone()),
+            RuntimeError::BindingError(error)       => error.location(),
+            _                                       => None
+        }
+    }
+}
+
 impl From<ParseError> for RuntimeError {
     fn from(error: ParseError) -> RuntimeError { 
         RuntimeError::ParseError(error)
@@ -1,20 +1,62 @@
+use crate::bitcode::*;
 use crate::exec::*;
 use crate::meta::*;
 
+///
+/// Truncates a number to the specified number of bits
+///
+fn truncate_bits(bits: u8, number: SafasNumber) -> SafasNumber {
+    let mask    = if bits >= 128 { u128::max_value() } else { (1u128<<bits)-1 };
+    let number  = match number {
+        SafasNumber::Plain(val)                     => val as u128,
+        SafasNumber::BitNumber(_bits, val)          => val,
+        SafasNumber::SignedBitNumber(_bits, val)    => val as u128
+    };
+
+    SafasNumber::BitNumber(bits, number & mask)
+}
+
+///
+/// Truncates a number to the specified number of bits and sign-extends the result
+///
+fn truncate_signed_bits(bits: u8, number: i128) -> SafasNumber {
+    // 0-bit numbers can only be 0, and numbers can't be extended past 128 bits
+    if bits == 0    { return SafasNumber::SignedBitNumber(0, 0); }
+    if bits >= 128  { return SafasNumber::SignedBitNumber(bits, number); }
+
+    let mask    = (1u128<<bits)-1;
+    let number  = (number as u128) & mask;
+
+    let number = if number & (1u128<<(bits-1)) != 0 {
+        let sign_extend = (-1 << bits) as u128;
+        let number      = number | sign_extend;
+        number as i128
+    } else {
+        number as i128
+    };
+
+    SafasNumber::SignedBitNumber(bits, number as i128)
+}
+
+///
+/// Returns a bitcode monad containing a converted number, which will produce an error during assembly if the original number is out of range
+///
+fn checked_number(converted: SafasNumber, in_range: bool, original: SafasNumber, bits: u8) -> CellRef {
+    let value = CellRef::new(SafasCell::Number(converted));
+
+    if in_range {
+        BitCodeMonad::with_value(value).to_cell()
+    } else {
+        BitCodeMonad::with_deferred_error(value, RuntimeError::ValueOutOfRange(original, bits)).to_cell()
+    }
+}
+
 ///
 /// (bits 3 8) -> 8u3
 ///
 pub fn bits_fn() -> impl FrameMonad<Binding=RuntimeResult> {
     FnMonad::from(|(CellValue(bits), number): (_, SafasNumber)| {
-        let mask    = (1u128<<bits)-1;
-        let number  = match number {
-            SafasNumber::Plain(val)                     => val as u128,
-            SafasNumber::BitNumber(_bits, val)          => val,
-            SafasNumber::SignedBitNumber(_bits, val)    => val as u128
-        };
-        let number  = number & mask;
-
-        CellRef::new(SafasCell::Number(SafasNumber::BitNumber(bits, number)))
+        CellRef::new(SafasCell::Number(truncate_bits(bits, number)))
     })
 }
 
@@ -24,24 +66,85 @@ pub fn bits_fn() -> impl FrameMonad<Binding=RuntimeResult> {
 ///
 pub fn sbits_fn() -> impl FrameMonad<Binding=RuntimeResult> {
     FnMonad::from(|(CellValue(bits), CellValue(number)): (_, CellValue<i128>)| {
-        let mask    = (1u128<<bits)-1;
-        let number  = (number as u128) & mask;
-
-        let number = if number & (1u128<<(bits-1)) != 0 {
-            let sign_extend = (-1 << bits) as u128;
-            let number      = number | sign_extend;
-            number as i128
-        } else {
-            number as i128
+        CellRef::new(SafasCell::Number(truncate_signed_bits(bits, number)))
+    })
+}
+
+///
+/// (checked_bits 8 $ae) -> $aeu8
+///
+/// As for `bits`, except the value must fit into the number of bits without being truncated. The result is a bitcode monad: if the
+/// value is out of range, a ValueOutOfRange error is produced by the final assembly pass.
+///
+pub fn checked_bits_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    ReturnsMonad(FnMonad::from(|(CellValue(bits), number): (CellValue<u8>, SafasNumber)| {
+        let in_range = match number {
+            SafasNumber::SignedBitNumber(_bits, val)    => val >= 0 && (bits >= 128 || (val as u128) < (1u128<<bits)),
+            other                                       => bits >= 128 || other.to_u128() < (1u128<<bits)
         };
 
-        CellRef::new(SafasCell::Number(SafasNumber::SignedBitNumber(bits, number as i128)))
+        checked_number(truncate_bits(bits, number), in_range, number, bits)
+    }))
+}
+
+///
+/// (checked_sbits 8 -2) -> -2i8
+///
+/// As for `sbits`, except the value (treated as signed) must fit into the number of bits without being truncated. The result is a
+/// bitcode monad: if the value is out of range, a ValueOutOfRange error is produced by the final assembly pass.
+///
+pub fn checked_sbits_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    ReturnsMonad(FnMonad::from(|(CellValue(bits), number): (CellValue<u8>, SafasNumber)| {
+        let value       = number.to_i128();
+        let in_range    = match bits {
+            0           => value == 0,
+            128..=255   => true,
+            _           => value >= -(1i128<<(bits-1)) && value < (1i128<<(bits-1))
+        };
+
+        checked_number(truncate_signed_bits(bits, value), in_range, number, bits)
+    }))
+}
+
+///
+/// Swaps the order of the bytes in a number that's the specified number of bits long
+///
+fn swap_bytes(bits: u8, number: SafasNumber) -> SafasNumber {
+    match truncate_bits(bits, number) {
+        SafasNumber::BitNumber(bits, val)   => SafasNumber::BitNumber(bits, val.swap_bytes() >> (128 - bits as u32)),
+        other                               => other
+    }
+}
+
+///
+/// (be16 $1234) -> $3412u16
+///
+/// Converts a number so that it's written with its most significant byte first by the `d` function
+///
+pub fn big_endian_fn(bits: u8) -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(move |(number, ): (SafasNumber, )| {
+        CellRef::new(SafasCell::Number(swap_bytes(bits, number)))
+    })
+}
+
+///
+/// (le16 $1234) -> $1234u16
+///
+/// Converts a number so that it's written with its least significant byte first by the `d` function (this is the order
+/// that `d` normally uses, so this is the same as using `bits`)
+///
+pub fn little_endian_fn(bits: u8) -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(move |(number, ): (SafasNumber, )| {
+        CellRef::new(SafasCell::Number(truncate_bits(bits, number)))
     })
 }
 
 #[cfg(test)]
 mod test {
     use crate::interactive::*;
+    use crate::bitcode::*;
+    use crate::exec::*;
+    use crate::test_support::{assembly_for};
 
     #[test]
     fn simple_bits() {
@@ -90,4 +193,72 @@ mod test {
             ).unwrap().to_string();
         assert!(val == "$ffffu16".to_string());
     }
+
+    #[test]
+    fn checked_bits_in_range() {
+        let bitcode = assembly_for("(d (checked_bits 8 $ae))").unwrap().bitcode;
+        assert!(bitcode == vec![BitCode::Bits(8, 0xae)]);
+    }
+
+    #[test]
+    fn checked_sbits_in_range() {
+        let bitcode = assembly_for("(d (checked_sbits 8 (- 2 4)))").unwrap().bitcode;
+        assert!(bitcode == vec![BitCode::Bits(8, -2i128 as u128)]);
+    }
+
+    #[test]
+    fn checked_bits_out_of_range() {
+        let error = assembly_for("(d (checked_bits 8 $100))").unwrap_err();
+        assert!(error_report(&error).starts_with("error: ValueOutOfRange(Plain(256), 8)\n --> <expr>:1:4\n"));
+    }
+
+    #[test]
+    fn checked_sbits_out_of_range() {
+        let error = assembly_for("(d (checked_sbits 8 128))").unwrap_err();
+        assert!(error_report(&error).starts_with("error: ValueOutOfRange(Plain(128), 8)\n"));
+    }
+
+    #[test]
+    fn sbits_zero_width() {
+        let val = eval(
+                "(sbits 0 5)"
+            ).unwrap().to_string();
+        assert!(val == "0i0".to_string());
+    }
+
+    #[test]
+    fn checked_sbits_zero_width() {
+        let error = assembly_for("(d (checked_sbits 0 5))").unwrap_err();
+        assert!(error_report(&error).starts_with("error: ValueOutOfRange(Plain(5), 0)\n"));
+    }
+
+    #[test]
+    fn checked_sbits_128_bits() {
+        let bitcode = assembly_for("(d (checked_sbits 128 5))").unwrap().bitcode;
+        assert!(bitcode == vec![BitCode::Bits(128, 5)]);
+    }
+
+    #[test]
+    fn checked_sbits_forward_reference() {
+        let bitcode = assembly_for("(label start) (d $1u8) (d (checked_sbits 8 (/ (- end start) 8))) (d $2u8) (label end)").unwrap().bitcode;
+        assert!(bitcode == vec![BitCode::Bits(8, 0x1), BitCode::Bits(8, 0x3), BitCode::Bits(8, 0x2)]);
+    }
+
+    #[test]
+    fn big_endian_16() {
+        let bytes = assembly_for("(d (be16 $1234))").unwrap().bytes();
+        assert!(bytes == vec![0x12, 0x34]);
+    }
+
+    #[test]
+    fn big_endian_32_signed() {
+        let bytes = assembly_for("(d (be32 (- 2)))").unwrap().bytes();
+        assert!(bytes == vec![0xff, 0xff, 0xff, 0xfe]);
+    }
+
+    #[test]
+    fn little_endian_32() {
+        let bytes = assembly_for("(d (le32 $12345678))").unwrap().bytes();
+        assert!(bytes == vec![0x78, 0x56, 0x34, 0x12]);
+    }
 }
@@ -0,0 +1,199 @@
+use crate::exec::*;
+use crate::meta::*;
+
+///
+/// (bit_and a b c) -> a&b&c (there's no width for an 'all bits set' result, so at least one number is needed)
+///
+pub fn bit_and_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|items: Vec<SafasNumber>| {
+        if items.len() == 0 { return Err(RuntimeError::NotEnoughArguments(NIL.clone())); }
+
+        let initial = items[0];
+        Ok(SafasCell::Number(items.into_iter().skip(1).fold(initial, |a, b| a&b)).into())
+    })
+}
+
+///
+/// (bit_or a b c) -> a|b|c
+///
+pub fn bit_or_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|items: Vec<SafasNumber>| {
+        CellRef::new(SafasCell::Number(items.into_iter().fold(SafasNumber::Plain(0), |a, b| a|b)))
+    })
+}
+
+///
+/// (bit_xor a b c) -> a^b^c
+///
+pub fn bit_xor_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|items: Vec<SafasNumber>| {
+        CellRef::new(SafasCell::Number(items.into_iter().fold(SafasNumber::Plain(0), |a, b| a^b)))
+    })
+}
+
+///
+/// (bit_not a) -> !a (inverting only the bits within the width of a)
+///
+pub fn bit_not_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(number, ): (SafasNumber, )| {
+        CellRef::new(SafasCell::Number(!number))
+    })
+}
+
+///
+/// (<< a 4) -> a<<4 (bits shifted out of the width of the number are lost)
+///
+pub fn shl_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(number, CellValue(amount)): (SafasNumber, CellValue<u32>)| {
+        CellRef::new(SafasCell::Number(number << amount))
+    })
+}
+
+///
+/// (>> a 4) -> a>>4 (logical shift for unsigned numbers, arithmetic shift for signed numbers)
+///
+pub fn shr_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(number, CellValue(amount)): (SafasNumber, CellValue<u32>)| {
+        CellRef::new(SafasCell::Number(number >> amount))
+    })
+}
+
+///
+/// (asr $80u8 4) -> $f8u8 (shifts right, treating the number as signed within its width)
+///
+pub fn asr_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(number, CellValue(amount)): (SafasNumber, CellValue<u32>)| {
+        CellRef::new(SafasCell::Number(number.arithmetic_shr(amount)))
+    })
+}
+
+///
+/// (mod a b) -> a mod b
+///
+pub fn mod_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(a, b): (SafasNumber, SafasNumber)| {
+        if b.to_u128() == 0 { return Err(RuntimeError::DivisionByZero); }
+
+        Ok(CellRef::new(SafasCell::Number(a % b)))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+
+    #[test]
+    fn bit_and() {
+        let val = eval(
+                "(bit_and $1234u16 $ff)"
+            ).unwrap().to_string();
+        assert!(val == "$34u16".to_string());
+    }
+
+    #[test]
+    fn bit_and_needs_a_number() {
+        let val = eval("(bit_and)");
+        assert!(format!("{:?}", val).contains("NotEnoughArguments"));
+    }
+
+    #[test]
+    fn bit_or() {
+        let val = eval(
+                "(bit_or $10u8 $01 $20)"
+            ).unwrap().to_string();
+        assert!(val == "$31u8".to_string());
+    }
+
+    #[test]
+    fn bit_xor() {
+        let val = eval(
+                "(bit_xor $ffu8 $0f)"
+            ).unwrap().to_string();
+        assert!(val == "$f0u8".to_string());
+    }
+
+    #[test]
+    fn bit_not() {
+        let val = eval(
+                "(bit_not $0fu8)"
+            ).unwrap().to_string();
+        assert!(val == "$f0u8".to_string());
+    }
+
+    #[test]
+    fn shift_left() {
+        let val = eval(
+                "(bit_or (<< 3u8 3) 5u8)"
+            ).unwrap().to_string();
+        assert!(val == "$1du8".to_string());
+    }
+
+    #[test]
+    fn high_byte() {
+        let val = eval(
+                "(bits 8 (>> $1234u16 8))"
+            ).unwrap().to_string();
+        assert!(val == "$12u8".to_string());
+    }
+
+    #[test]
+    fn shift_right_signed() {
+        let val = eval(
+                "(>> (sbits 8 $f0) 2)"
+            ).unwrap().to_string();
+        assert!(val == "-4i8".to_string());
+    }
+
+    #[test]
+    fn arithmetic_shift_right() {
+        let val = eval(
+                "(asr $80u8 4)"
+            ).unwrap().to_string();
+        assert!(val == "$f8u8".to_string());
+    }
+
+    #[test]
+    fn modulo() {
+        let val = eval(
+                "(mod 17 5)"
+            ).unwrap().to_string();
+        assert!(val == "2".to_string());
+    }
+
+    #[test]
+    fn modulo_negative() {
+        let val = eval(
+                "(mod (- 7) 4)"
+            ).unwrap().to_string();
+        assert!(val == "1i3".to_string());
+    }
+
+    #[test]
+    fn modulo_by_zero() {
+        let val = eval(
+                "(mod 5 0)"
+            );
+        assert!(format!("{:?}", val).contains("DivisionByZero"));
+
+        let val = eval(
+                "(mod 5u8 0u8)"
+            );
+        assert!(format!("{:?}", val).contains("DivisionByZero"));
+    }
+
+    #[test]
+    fn shift_left_is_masked() {
+        let val = eval(
+                "(list (<< $80u8 1) (<< $ffu8 4) (<< (sbits 8 $40) 1))"
+            ).unwrap().to_string();
+        assert!(val == "($0u8 $f0u8 -128i8)".to_string());
+    }
+
+    #[test]
+    fn bit_and_mixed_signedness() {
+        let val = eval(
+                "(bit_and $ffu8 (- 1))"
+            ).unwrap().to_string();
+        assert!(val == "-1i8".to_string());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::meta::*;
+use crate::exec::*;
+
+///
+/// Reads the value of an option passed to `set_limits`
+///
+fn limit_option(value: &CellRef) -> Result<usize, RuntimeError> {
+    value.number_value().map(|number| number.to_usize()).ok_or_else(|| RuntimeError::NotANumber(value.clone()))
+}
+
+///
+/// Frame monad that implements `set_limits` (this needs the frame, which holds the limits for the session)
+///
+struct SetLimits;
+
+impl FrameMonad for SetLimits {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##set_limits##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, Self::Binding) {
+        let args    = Vec::<CellRef>::args_from_frame(&frame);
+        let args    = match args { Ok(args) => args, Err(err) => return (frame, Err(err)) };
+
+        match read_limits(frame.limits.get(), &args) {
+            Ok(limits)  => { frame.limits.set(limits); (frame, Ok(NIL.clone())) },
+            Err(err)    => (frame, Err(err))
+        }
+    }
+}
+
+///
+/// Updates a set of limits from the options passed to `set_limits`
+///
+fn read_limits(limits: Limits, args: &Vec<CellRef>) -> Result<Limits, RuntimeError> {
+    let mut limits = limits;
+
+    // The options are pairs of values
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        let value = options.next().ok_or_else(|| RuntimeError::NotEnoughArguments(option.clone()))?;
+
+        match option.to_atom_id().map(name_for_atom_with_id).as_deref() {
+            Some(":max_passes")             => { limits.max_passes = limit_option(value)?; },
+            Some(":max_output_size")        => { limits.max_output_size = limit_option(value)? as u64; },
+            Some(":max_recursion_depth")    => { limits.max_recursion_depth = limit_option(value)?; },
+            Some(":max_macro_depth")        => { limits.max_macro_depth = limit_option(value)?; },
+
+            _                               => { return Err(RuntimeError::UnknownOption(option.clone())); }
+        }
+    }
+
+    Ok(limits)
+}
+
+///
+/// The 'set_limits' function
+///
+/// `(set_limits :max_passes 2000 :max_output_size $10000)` changes the limits that apply to the code that is evaluated and
+/// assembled after it in the same session. The options are `:max_passes`, `:max_output_size` (in bytes), `:max_recursion_depth`
+/// and `:max_macro_depth`: any limits that are not specified keep their current value.
+///
+pub fn set_limits_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    SetLimits
+}
+
+#[cfg(test)]
+mod test {
+    use crate::exec::*;
+    use crate::session::*;
+    use crate::interactive::*;
+
+    #[test]
+    fn recursion_limit() {
+        let result = eval("
+            (def f (fun (g x) (g g x)))
+            (f f 1)
+        ");
+
+        assert!(format!("{:?}", result).contains("RecursionLimitReached(500)"));
+    }
+
+    #[test]
+    fn recursion_limit_in_session() {
+        let mut session = Session::new().unwrap();
+        let result      = session.eval("(def f (fun (g x) (g g x))) (f f 1)");
+
+        assert!(format!("{:?}", result).contains("RecursionLimitReached(500)"));
+    }
+
+    #[test]
+    fn recursion_limit_on_small_stack() {
+        // The stack grows onto the heap when it runs out, so the limit is reached before the stack overflows even on a small thread
+        let thread = std::thread::Builder::new()
+            .stack_size(512*1024)
+            .spawn(|| format!("{:?}", eval("(def f (fun (g x) (g g x))) (f f 1)")))
+            .unwrap();
+
+        assert!(thread.join().unwrap().contains("RecursionLimitReached(500)"));
+    }
+
+    #[test]
+    fn deep_recursion() {
+        let result = eval("
+            (def count (fun (self n) (if ((= n 0)) (0) ((+ 1 (self self (- n 1)))))))
+            (count count 450)
+        ");
+
+        assert!(result.unwrap().to_string() == "450".to_string());
+    }
+
+    #[test]
+    fn set_recursion_limit() {
+        let result = eval("
+            (set_limits :max_recursion_depth 10)
+            (def f (fun (g x) (g g x)))
+            (f f 1)
+        ");
+
+        assert!(format!("{:?}", result).contains("RecursionLimitReached(10)"));
+    }
+
+    #[test]
+    fn output_size_limit() {
+        let result = eval("
+            (set_limits :max_output_size 16)
+            (assemble ((fun () (d $1u8) (m $1000))))
+        ");
+
+        assert!(format!("{:?}", result).contains("OutputTooLarge(512, 16)"));
+    }
+
+    #[test]
+    fn pass_limit() {
+        let result = eval("
+            (set_limits :max_passes 0)
+            (assemble ((fun () (d (bits 8 later)) (label later))))
+        ");
+
+        assert!(format!("{:?}", result).contains("TooManyPasses(0"));
+    }
+
+    #[test]
+    fn macro_depth_limit() {
+        // Macros are expanded when the statements are bound, which happens before set_limits is evaluated
+        let mut session = Session::without_default_library();
+        session.set_limits(Limits { max_macro_depth: 2, ..Limits::default() });
+
+        let result = session.eval("
+            (def_syntax w ((wrap <x>) (x)))
+            (w (wrap (w (wrap (w (wrap 1))))))
+        ");
+
+        assert!(format!("{:?}", result).contains("MacroDepthLimitReached(2)"));
+    }
+
+    #[test]
+    fn macro_depth_is_restored_after_expansion() {
+        // Expansions that follow each other don't count towards the depth
+        let mut session = Session::without_default_library();
+        session.set_limits(Limits { max_macro_depth: 2, ..Limits::default() });
+
+        let result = session.eval("
+            (def_syntax w ((wrap <x>) (x)))
+            (list (w (wrap 1)) (w (wrap 2)) (w (wrap 3)) (w (wrap 4)))
+        ");
+
+        assert!(result.unwrap().to_string() == "(1 2 3 4)".to_string());
+    }
+
+    #[test]
+    fn unknown_limit() {
+        let result = eval("(set_limits :max_speed 2)");
+
+        assert!(format!("{:?}", result).contains("UnknownOption"));
+    }
+}
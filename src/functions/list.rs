@@ -1,3 +1,6 @@
+use super::monad::*;
+
+use crate::bind::*;
 use crate::exec::*;
 use crate::meta::*;
 
@@ -35,9 +38,540 @@ pub fn cdr_fn() -> impl FrameMonad<Binding=RuntimeResult> {
     })
 }
 
+///
+/// Reads the items in a list argument
+///
+fn list_items(list: &CellRef) -> Result<Vec<CellRef>, RuntimeError> {
+    list.to_vec().ok_or_else(|| RuntimeError::TypeMismatch(list.clone()))
+}
+
+///
+/// (length (a b c)) -> 3
+///
+pub fn length_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(list, ): (CellRef, )| {
+        Ok(SafasCell::Number(SafasNumber::Plain(list_items(&list)?.len() as u128)).into())
+    })
+}
+
+///
+/// (append (a b) (c) (d e)) -> (a b c d e)
+///
+pub fn append_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|lists: Vec<CellRef>| {
+        let mut items = vec![];
+        for list in lists.iter() {
+            items.extend(list_items(list)?);
+        }
+
+        Ok(SafasCell::list_with_cells(items))
+    })
+}
+
+///
+/// (reverse (a b c)) -> (c b a)
+///
+pub fn reverse_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(list, ): (CellRef, )| {
+        Ok(SafasCell::list_with_cells(list_items(&list)?.into_iter().rev()))
+    })
+}
+
+///
+/// (nth (a b c) 1) -> b
+///
+pub fn nth_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(list, index): (CellRef, SafasNumber)| {
+        let index = index.to_usize();
+        list_items(&list)?.get(index).cloned().ok_or(RuntimeError::IndexOutOfRange(index))
+    })
+}
+
+///
+/// (assoc b ((a 1) (b 2))) -> (b 2)
+///
+/// Returns the first item in a list whose car is the key, or `()` if there is no such item
+///
+pub fn assoc_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(key, list): (CellRef, CellRef)| {
+        for item in list_items(&list)? {
+            if let SafasCell::List(item_key, _) = &*item {
+                if **item_key == *key {
+                    return Ok(item.clone());
+                }
+            }
+        }
+
+        Ok(NIL.clone())
+    })
+}
+
+///
+/// (zip (a b c) (1 2 3)) -> ((a 1) (b 2) (c 3))
+///
+/// The result is as long as the shortest list
+///
+pub fn zip_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|lists: Vec<CellRef>| {
+        let lists   = lists.iter().map(|list| list_items(list)).collect::<Result<Vec<_>, _>>()?;
+        let length  = lists.iter().map(|list| list.len()).min().unwrap_or(0);
+
+        Ok(SafasCell::list_with_cells((0..length).map(|index| SafasCell::list_with_cells(lists.iter().map(|list| list[index].clone())))))
+    })
+}
+
+///
+/// True if a value is a monad
+///
+fn is_monad(value: &CellRef) -> bool {
+    if let SafasCell::Monad(_, _) = &**value { true } else { false }
+}
+
+///
+/// Reads the arguments passed to a function, which should be a list of the specified length
+///
+fn function_args(frame: &Frame, count: usize) -> Result<Vec<CellRef>, RuntimeError> {
+    let args = list_items(&frame.cells[0])?;
+
+    if args.len() < count { return Err(RuntimeError::NotEnoughArguments(frame.cells[0].clone())); }
+    if args.len() > count { return Err(RuntimeError::TooManyArguments(frame.cells[0].clone())); }
+
+    Ok(args)
+}
+
+///
+/// Calls a SAFAS function with a list of arguments
+///
+fn call_function(function: &CellRef, args: Vec<CellRef>, frame: Frame) -> (Frame, RuntimeResult) {
+    match &**function {
+        SafasCell::FrameMonad(action)   => {
+            let mut frame   = frame;
+            frame.cells[0]  = SafasCell::list_with_cells(args);
+            action.execute(frame)
+        }
+
+        _                               => (frame, Err(RuntimeError::NotAFunction(function.clone())))
+    }
+}
+
+///
+/// Calls a SAFAS function for each item in a list, returning the results
+///
+fn call_for_each(function: &CellRef, items: Vec<CellRef>, frame: Frame) -> (Frame, Result<Vec<CellRef>, RuntimeError>) {
+    let mut frame   = frame;
+    let mut results = vec![];
+
+    for item in items {
+        let (new_frame, result) = call_function(function, vec![item], frame);
+        frame                   = new_frame;
+
+        match result {
+            Ok(result)  => results.push(result),
+            Err(err)    => return (frame, Err(err))
+        }
+    }
+
+    (frame, Ok(results))
+}
+
+///
+/// Flat_map function that adds the value of a monad to a list of values, then moves on to the next monad
+///
+struct CollectValues {
+    remaining: Vec<CellRef>,
+    collected: Vec<CellRef>
+}
+
+impl FrameMonad for CollectValues {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##collect_values##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let value           = frame.cells[0].to_vec().and_then(|args| args.into_iter().next()).unwrap_or_else(|| NIL.clone());
+        let mut collected   = self.collected.clone();
+        collected.push(value);
+
+        collect_values(self.remaining.clone(), collected, frame)
+    }
+
+    fn returns_monad(&self) -> bool { true }
+}
+
+///
+/// Returns a monad whose value is the list of the values of a series of monads (which are flat_mapped in order)
+///
+/// Values that are not monads are added to the list as they are.
+///
+fn collect_values(values: Vec<CellRef>, collected: Vec<CellRef>, frame: Frame) -> (Frame, RuntimeResult) {
+    let mut collected   = collected;
+    let mut values      = values.into_iter();
+
+    while let Some(value) = values.next() {
+        match &*value {
+            SafasCell::Monad(monad_value, monad_type)   => {
+                // Carry on collecting once the value of this monad is known
+                let map_fn = CollectValues { remaining: values.collect(), collected: collected };
+                let map_fn = SafasCell::FrameMonad(Box::new(map_fn)).into();
+
+                return monad_type.flat_map(monad_value.clone(), map_fn, frame);
+            }
+
+            _                                           => collected.push(value.clone())
+        }
+    }
+
+    (frame, Ok(wrap_value(SafasCell::list_with_cells(collected))))
+}
+
+///
+/// The `map` function: `(map f list)` calls `f` for each item in the list, and returns a list of the results
+///
+struct Map {
+    returns_monad: bool
+}
+
+impl FrameMonad for Map {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##map##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let args                = match function_args(&frame, 2) { Ok(args) => args, Err(err) => return (frame, Err(err)) };
+        let items               = match list_items(&args[1]) { Ok(items) => items, Err(err) => return (frame, Err(err)) };
+
+        let (frame, results)    = call_for_each(&args[0], items, frame);
+        let results             = match results { Ok(results) => results, Err(err) => return (frame, Err(err)) };
+
+        // Monad results are flat_mapped together so the result is a monad containing the list of values
+        if self.returns_monad || results.iter().any(is_monad) {
+            collect_values(results, vec![], frame)
+        } else {
+            (frame, Ok(SafasCell::list_with_cells(results)))
+        }
+    }
+
+    fn returns_monad(&self) -> bool { self.returns_monad }
+}
+
+///
+/// Flat_map function that filters a list of items using the list of results from the filter function
+///
+struct FilterItems {
+    items: Vec<CellRef>
+}
+
+impl FilterItems {
+    ///
+    /// Returns the items that have a true result
+    ///
+    fn filter(&self, results: &Vec<CellRef>) -> CellRef {
+        let items = self.items.iter().zip(results.iter())
+            .filter(|(_item, result)| if let SafasCell::Boolean(true) = &***result { true } else { false })
+            .map(|(item, _result)| item.clone());
+
+        SafasCell::list_with_cells(items)
+    }
+}
+
+impl FrameMonad for FilterItems {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##filter_items##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let results = frame.cells[0].to_vec().and_then(|args| args.into_iter().next()).and_then(|results| results.to_vec()).unwrap_or_else(|| vec![]);
+        let items   = self.filter(&results);
+
+        (frame, Ok(wrap_value(items)))
+    }
+
+    fn returns_monad(&self) -> bool { true }
+}
+
+///
+/// The `filter` function: `(filter f list)` returns the items in the list where `f` returns `=t`
+///
+struct Filter {
+    returns_monad: bool
+}
+
+impl FrameMonad for Filter {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##filter##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let args                = match function_args(&frame, 2) { Ok(args) => args, Err(err) => return (frame, Err(err)) };
+        let items               = match list_items(&args[1]) { Ok(items) => items, Err(err) => return (frame, Err(err)) };
+
+        let (frame, results)    = call_for_each(&args[0], items.clone(), frame);
+        let results             = match results { Ok(results) => results, Err(err) => return (frame, Err(err)) };
+        let filter              = FilterItems { items: items };
+
+        if self.returns_monad || results.iter().any(is_monad) {
+            // Collect the results from the monads, then filter the list once they're known
+            let (frame, results) = collect_values(results, vec![], frame);
+
+            match results.as_ref().map(|results| &**results) {
+                Ok(SafasCell::Monad(results, monad_type))   => monad_type.flat_map(results.clone(), SafasCell::FrameMonad(Box::new(filter)).into(), frame),
+                Ok(_)                                       => (frame, results),
+                Err(_)                                      => (frame, results)
+            }
+        } else {
+            (frame, Ok(filter.filter(&results)))
+        }
+    }
+
+    fn returns_monad(&self) -> bool { self.returns_monad }
+}
+
+///
+/// Calls a function to combine each item in a list with an accumulated value
+///
+/// If the function returns a monad, the rest of the list is folded once its value is known, and the result is a monad.
+///
+fn fold_items(function: CellRef, initial: CellRef, items: Vec<CellRef>, frame: Frame, wrap_result: bool) -> (Frame, RuntimeResult) {
+    let mut frame   = frame;
+    let mut value   = initial;
+    let mut items   = items.into_iter();
+
+    while let Some(item) = items.next() {
+        let (new_frame, result) = call_function(&function, vec![value, item], frame);
+        frame                   = new_frame;
+        let result              = match result { Ok(result) => result, Err(err) => return (frame, Err(err)) };
+
+        if let SafasCell::Monad(monad_value, monad_type) = &*result {
+            let map_fn = FoldStep { function: function, items: items.collect() };
+            let map_fn = SafasCell::FrameMonad(Box::new(map_fn)).into();
+
+            return monad_type.flat_map(monad_value.clone(), map_fn, frame);
+        }
+
+        value = result;
+    }
+
+    if wrap_result {
+        (frame, Ok(wrap_value(value)))
+    } else {
+        (frame, Ok(value))
+    }
+}
+
+///
+/// Flat_map function that carries on folding a list once the value returned by the fold function is known
+///
+struct FoldStep {
+    function:   CellRef,
+    items:      Vec<CellRef>
+}
+
+impl FrameMonad for FoldStep {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##fold_step##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let value = frame.cells[0].to_vec().and_then(|args| args.into_iter().next()).unwrap_or_else(|| NIL.clone());
+
+        fold_items(self.function.clone(), value, self.items.clone(), frame, true)
+    }
+
+    fn returns_monad(&self) -> bool { true }
+}
+
+///
+/// The `fold` function: `(fold f initial list)` calls `(f value item)` for each item in the list, where `value` is the
+/// result of the previous call (or `initial` for the first item), and returns the result of the last call
+///
+struct Fold {
+    returns_monad: bool
+}
+
+impl FrameMonad for Fold {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##fold##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let args    = match function_args(&frame, 3) { Ok(args) => args, Err(err) => return (frame, Err(err)) };
+        let items   = match list_items(&args[2]) { Ok(items) => items, Err(err) => return (frame, Err(err)) };
+
+        fold_items(args[0].clone(), args[1].clone(), items, frame, self.returns_monad)
+    }
+
+    fn returns_monad(&self) -> bool { self.returns_monad }
+}
+
+///
+/// The `apply` function: `(apply f list)` calls `f` with the items in the list as its arguments
+///
+struct Apply {
+    returns_monad: bool
+}
+
+impl FrameMonad for Apply {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##apply##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let args                = match function_args(&frame, 2) { Ok(args) => args, Err(err) => return (frame, Err(err)) };
+        let items               = match list_items(&args[1]) { Ok(items) => items, Err(err) => return (frame, Err(err)) };
+
+        let (frame, result)     = call_function(&args[0], items, frame);
+
+        match result {
+            Ok(result) if self.returns_monad && !is_monad(&result)  => (frame, Ok(wrap_value(result))),
+            result                                                  => (frame, result)
+        }
+    }
+
+    fn returns_monad(&self) -> bool { self.returns_monad }
+}
+
+///
+/// Binding monad for a function that takes another function as its first argument
+///
+/// The binder needs to know if a function call returns a monad, which for these functions depends on the function that's
+/// passed in. This binds calls to a version of the function that returns a monad when the function argument does.
+///
+struct HigherOrderFunction {
+    name:       String,
+    value_fn:   CellRef,
+    monad_fn:   CellRef
+}
+
+impl BindingMonad for HigherOrderFunction {
+    type Binding = SyntaxCompiler;
+
+    fn description(&self) -> String { self.name.clone() }
+
+    fn pre_bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Self::Binding) {
+        (bindings, SyntaxCompiler::default())
+    }
+
+    fn bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Result<Self::Binding, BindError>) {
+        // When used as a value rather than called, this evaluates to the version of the function that doesn't return a monad
+        let args = match bindings.args.clone() {
+            Some(args)  => args.to_vec().unwrap_or_else(|| vec![]),
+            None        => return (bindings, Ok(SyntaxCompiler::with_compiler_and_reftype(|function| compile_statement(function), self.value_fn.clone(), ReferenceType::Value)))
+        };
+
+        // Bind the function argument to find out if it returns a monad
+        let (function, bindings, args) = if args.len() > 0 {
+            match bind_statement(args[0].clone(), bindings) {
+                Ok((bound_fn, bindings))    => {
+                    let function    = if bound_fn.reference_type() == ReferenceType::ReturnsMonad { self.monad_fn.clone() } else { self.value_fn.clone() };
+                    let mut args    = args;
+                    args[0]         = bound_fn;
+
+                    (function, bindings, args)
+                }
+
+                Err((err, bindings))        => return (bindings, Err(err))
+            }
+        } else {
+            (self.value_fn.clone(), bindings, args)
+        };
+
+        // Bind as a call to the function
+        let call = SafasCell::list_with_cells(vec![function].into_iter().chain(args));
+        match bind_statement(call, bindings) {
+            Ok((call, bindings))    => {
+                let reference_type = call.reference_type();
+                (bindings, Ok(SyntaxCompiler::with_compiler_and_reftype(|call| compile_statement(call), call, reference_type)))
+            }
+
+            Err((err, bindings))    => (bindings, Err(err))
+        }
+    }
+}
+
+///
+/// Creates the binding monad for a function that takes another function as its first argument
+///
+fn higher_order_function<MakeFn, TFn>(name: &str, make_fn: MakeFn) -> impl BindingMonad<Binding=SyntaxCompiler>
+where   MakeFn: Fn(bool) -> TFn,
+        TFn:    'static+FrameMonad<Binding=RuntimeResult> {
+    HigherOrderFunction {
+        name:       name.to_string(),
+        value_fn:   SafasCell::FrameMonad(Box::new(make_fn(false))).into(),
+        monad_fn:   SafasCell::FrameMonad(Box::new(make_fn(true))).into()
+    }
+}
+
+///
+/// `(map f (a b c))` -> `((f a) (f b) (f c))`
+///
+/// If `f` returns a monad, the result is a monad containing the list of values (and any bitcode is generated in order).
+///
+pub fn map_syntax() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    higher_order_function("map", |returns_monad| Map { returns_monad: returns_monad })
+}
+
+///
+/// `(filter f (a b c))` -> the items where `(f item)` is `=t`
+///
+pub fn filter_syntax() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    higher_order_function("filter", |returns_monad| Filter { returns_monad: returns_monad })
+}
+
+///
+/// `(fold f initial (a b c))` -> `(f (f (f initial a) b) c)`
+///
+pub fn fold_syntax() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    higher_order_function("fold", |returns_monad| Fold { returns_monad: returns_monad })
+}
+
+///
+/// `(apply f (a b c))` -> `(f a b c)`
+///
+pub fn apply_syntax() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    higher_order_function("apply", |returns_monad| Apply { returns_monad: returns_monad })
+}
+
+///
+/// `(range end)`, `(range start end)` or `(range start end step)` -> `(start start+step ... )`
+///
+/// The list stops before `end`. The step can be negative (for signed numbers) to count down.
+///
+pub fn range_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|args: Vec<SafasNumber>| {
+        let (start, end, step) = match args.len() {
+            0   => return Err(RuntimeError::NotEnoughArguments(NIL.clone())),
+            1   => (SafasNumber::Plain(0), args[0], SafasNumber::Plain(1)),
+            2   => (args[0], args[1], SafasNumber::Plain(1)),
+            3   => (args[0], args[1], args[2]),
+            _   => return Err(RuntimeError::TooManyArguments(SafasCell::Number(args[3]).into()))
+        };
+
+        // Counting up or down depends on the sign of the step (stepping by 0 would never finish)
+        let ascending   = match step {
+            SafasNumber::SignedBitNumber(_, step)   => step > 0,
+            _                                       => step.to_u128() > 0
+        };
+        if step.to_u128() == 0 { return Err(RuntimeError::TypeMismatch(SafasCell::Number(step).into())); }
+
+        let (mut pos, _)    = start.coerce(end);
+        let mut items       = vec![];
+        while (ascending && pos < end) || (!ascending && pos > end) {
+            items.push(SafasCell::Number(pos).into());
+
+            // Stop if the count wraps around
+            let next = pos + step;
+            if (ascending && next <= pos) || (!ascending && next >= pos) { break; }
+            pos = next;
+        }
+
+        Ok(SafasCell::list_with_cells(items))
+    })
+}
+
 #[cfg(test)]
 mod test {
     use crate::interactive::*;
+    use crate::bitcode::*;
 
     #[test]
     fn make_list() {
@@ -86,4 +620,122 @@ mod test {
             ).unwrap().to_string();
         assert!(val == "(2 3)".to_string());
     }
+
+    #[test]
+    fn length() {
+        let val = eval(
+                "(list (length (list 1 2 3)) (length (list)))"
+            ).unwrap().to_string();
+        assert!(val == "(3 0)".to_string());
+    }
+
+    #[test]
+    fn append() {
+        let val = eval(
+                "(append (list 1 2) (list) (list 3) (list 4 5))"
+            ).unwrap().to_string();
+        assert!(val == "(1 2 3 4 5)".to_string());
+    }
+
+    #[test]
+    fn reverse() {
+        let val = eval(
+                "(reverse (list 1 2 3))"
+            ).unwrap().to_string();
+        assert!(val == "(3 2 1)".to_string());
+    }
+
+    #[test]
+    fn nth() {
+        let val = eval(
+                "(nth (list 1 2 3) 1)"
+            ).unwrap().to_string();
+        assert!(val == "2".to_string());
+    }
+
+    #[test]
+    fn nth_out_of_range() {
+        let val = eval(
+                "(nth (list 1 2 3) 3)"
+            );
+        assert!(format!("{:?}", val).contains("IndexOutOfRange(3)"));
+    }
+
+    #[test]
+    fn assoc() {
+        let val = eval(
+                "(list (assoc 2 (list (list 1 10) (list 2 20))) (assoc 3 (list (list 1 10) (list 2 20))))"
+            ).unwrap().to_string();
+        assert!(val == "((2 20) ())".to_string());
+    }
+
+    #[test]
+    fn zip() {
+        let val = eval(
+                "(zip (list 1 2 3) (list 4 5))"
+            ).unwrap().to_string();
+        assert!(val == "((1 4) (2 5))".to_string());
+    }
+
+    #[test]
+    fn map() {
+        let val = eval(
+                "(map (fun (x) (* x 2)) (list 1 2 3))"
+            ).unwrap().to_string();
+        assert!(val == "(2 4 6)".to_string());
+    }
+
+    #[test]
+    fn filter() {
+        let val = eval(
+                "(filter (fun (x) (> x 1)) (list 1 2 3))"
+            ).unwrap().to_string();
+        assert!(val == "(2 3)".to_string());
+    }
+
+    #[test]
+    fn fold() {
+        let val = eval(
+                "(fold (fun (total x) (+ total x)) 10 (list 1 2 3))"
+            ).unwrap().to_string();
+        assert!(val == "16".to_string());
+    }
+
+    #[test]
+    fn apply() {
+        let val = eval(
+                "(apply (fun (x y) (- x y)) (list 5 3))"
+            ).unwrap().to_string();
+        assert!(val == "2".to_string());
+    }
+
+    #[test]
+    fn map_as_value() {
+        let val = eval(
+                "(def double_all (fun (items) ((fun (f) (f (fun (x) (* x 2)) items)) map))) (double_all (list 1 2))"
+            ).unwrap().to_string();
+        assert!(val == "(2 4)".to_string());
+    }
+
+    #[test]
+    fn map_bitcode() {
+        let result  = eval("(map (fun (x) (d x) (+ x 1u8)) (list 1u8 2u8 3u8))").unwrap();
+        let monad   = BitCodeMonad::from_cell(&result).unwrap();
+        let (val, bitcode, _labels) = assemble(&monad).unwrap();
+        let bytes   = bitcode_to_bytes(bitcode.into_iter());
+
+        assert!(bytes == vec![1, 2, 3]);
+        assert!(val.to_string() == "($2u8 $3u8 $4u8)".to_string());
+    }
+
+    #[test]
+    fn fold_bitcode() {
+        let result  = eval("(fold (fun (total x) (d x) (+ total x)) 0u8 (list 1u8 2u8 3u8))").unwrap();
+        let monad   = BitCodeMonad::from_cell(&result).unwrap();
+        let (val, bitcode, _labels) = assemble(&monad).unwrap();
+        let bytes   = bitcode_to_bytes(bitcode.into_iter());
+
+        assert!(bytes == vec![1, 2, 3]);
+        assert!(val.to_string() == "$6u8".to_string());
+    }
 }
@@ -1,15 +1,21 @@
 mod list;
+mod string;
 mod bits;
 mod btree;
 mod monad;
 mod arithmetic;
+mod bitwise;
 mod comparison;
+mod limits;
 mod standard_functions;
 
 pub use self::list::*;
+pub use self::string::*;
 pub use self::bits::*;
 pub use self::btree::*;
 pub use self::monad::*;
 pub use self::arithmetic::*;
+pub use self::bitwise::*;
 pub use self::comparison::*;
+pub use self::limits::*;
 pub use self::standard_functions::*;
@@ -1,17 +1,23 @@
 use crate::exec::*;
 use crate::meta::*;
 
+///
+/// Wraps a value in a monad, in the same way as the `wrap` function
+///
+pub(crate) fn wrap_value(value: CellRef) -> CellRef {
+    let wrap_monad  = WrapFlatMap(value);
+    let wrap_monad  = SafasCell::FrameMonad(Box::new(wrap_monad)).into();
+
+    SafasCell::Monad(NIL.clone(), MonadType::new(wrap_monad)).into()
+}
+
 ///
 /// `(wrap x)` -> monad wrapping x
 ///
 pub fn wrap_fn() -> impl FrameMonad<Binding=RuntimeResult> {
     // Our monad just returns this wrapping function
     let wrap_fn     = FnMonad::from(|(val, ): (CellRef, )| {
-        let wrap_monad  = WrapFlatMap(val.clone());
-        let wrap_monad  = SafasCell::FrameMonad(Box::new(wrap_monad)).into();
-        let wrap_monad  = SafasCell::Monad(NIL.clone(), MonadType::new(wrap_monad)).into();
-
-        CellRef::new(wrap_monad)
+        wrap_value(val)
     });
     let wrap_fn     = ReturnsMonad(wrap_fn);
 
@@ -1,15 +1,19 @@
 use super::list::*;
+use super::string::*;
 use super::bits::*;
 use super::btree::*;
 use super::monad::*;
 use super::arithmetic::*;
+use super::bitwise::*;
 use super::comparison::*;
+use super::limits::*;
 
 use crate::meta::*;
 use crate::exec::*;
 use crate::bind::*;
 use crate::syntax::*;
 use crate::bitcode::*;
+use crate::emulator::*;
 
 use smallvec::*;
 
@@ -24,6 +28,17 @@ where Monad: 'static+FrameMonad<Binding=RuntimeResult> {
     define_symbol_value(AtomId::from(atom), monad)
 }
 
+///
+/// Defines a function that accepts keyword options, such as `:load` in `(run_6502 program :load $200)`
+///
+pub fn define_function_with_options<Monad>(atom: &str, monad: Monad) -> impl BindingMonad<Binding=SmallVec<[Action; 8]>>
+where Monad: 'static+FrameMonad<Binding=RuntimeResult> {
+    let monad   = SafasCell::FrameMonad(Box::new(monad));
+    let syntax  = SafasCell::Syntax(Box::new(function_with_options_keyword(monad.into())), NIL.clone());
+
+    define_symbol_value(AtomId::from(atom), syntax)
+}
+
 ///
 /// Creates the standard function bindings for the SAFAS language
 ///
@@ -33,10 +48,20 @@ pub fn standard_functions() -> impl BindingMonad<Binding=SmallVec<[Action; 8]>>
 
     // Bitcode functions
     let functions   = flat_map_binding_actions(move || define_function("d",             d_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("d_msb",         d_msb_fn()), functions);
     let functions   = flat_map_binding_actions(move || define_function("m",             m_fn()), functions);
     let functions   = flat_map_binding_actions(move || define_function("a",             a_fn()), functions);
     let functions   = flat_map_binding_actions(move || define_function("set_bit_pos",   set_bit_pos_fn()), functions);
     let functions   = flat_map_binding_actions(move || define_function("bit_pos",       bit_pos_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("region",        region_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("grow_only",     grow_only_fn()), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
+
+    // Diagnostic functions
+    let functions   = flat_map_binding_actions(move || define_function("print",         print_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("warn",          warn_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("error",         error_fn()), functions);
 
     let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
 
@@ -45,6 +70,18 @@ pub fn standard_functions() -> impl BindingMonad<Binding=SmallVec<[Action; 8]>>
     let functions   = flat_map_binding_actions(move || define_function("-",             sub_fn()), functions);
     let functions   = flat_map_binding_actions(move || define_function("/",             div_fn()), functions);
     let functions   = flat_map_binding_actions(move || define_function("*",             mul_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("mod",           mod_fn()), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
+
+    // Bitwise functions
+    let functions   = flat_map_binding_actions(move || define_function("bit_and",       bit_and_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("bit_or",        bit_or_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("bit_xor",       bit_xor_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("bit_not",       bit_not_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("<<",            shl_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function(">>",            shr_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("asr",           asr_fn()), functions);
 
     let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
 
@@ -63,6 +100,41 @@ pub fn standard_functions() -> impl BindingMonad<Binding=SmallVec<[Action; 8]>>
     let functions   = flat_map_binding_actions(move || define_function("cons",          cons_fn()), functions);
     let functions   = flat_map_binding_actions(move || define_function("car",           car_fn()), functions);
     let functions   = flat_map_binding_actions(move || define_function("cdr",           cdr_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("range",         range_fn()), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
+
+    let functions   = flat_map_binding_actions(move || define_function("length",        length_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("append",        append_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("reverse",       reverse_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("nth",           nth_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("assoc",         assoc_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("zip",           zip_fn()), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
+
+    // Higher-order list functions (these are syntax so the binder can tell if they return a monad)
+    let functions   = flat_map_binding_actions(move || define_symbol_value("map",       SafasCell::Syntax(Box::new(map_syntax()), NIL.clone())), functions);
+    let functions   = flat_map_binding_actions(move || define_symbol_value("filter",    SafasCell::Syntax(Box::new(filter_syntax()), NIL.clone())), functions);
+    let functions   = flat_map_binding_actions(move || define_symbol_value("fold",      SafasCell::Syntax(Box::new(fold_syntax()), NIL.clone())), functions);
+    let functions   = flat_map_binding_actions(move || define_symbol_value("apply",     SafasCell::Syntax(Box::new(apply_syntax()), NIL.clone())), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
+
+    // String functions
+    let functions   = flat_map_binding_actions(move || define_function("string_length",   string_length_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("substring",       substring_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("concat",          concat_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("string_upcase",   string_upcase_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("string_downcase", string_downcase_fn()), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
+
+    let functions   = flat_map_binding_actions(move || define_function("split",           split_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("format",          format_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("char->number",    char_to_number_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("number->char",    number_to_char_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("number->string",  number_to_string_fn()), functions);
 
     let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
 
@@ -76,6 +148,31 @@ pub fn standard_functions() -> impl BindingMonad<Binding=SmallVec<[Action; 8]>>
     // Bit manipulation functions
     let functions   = flat_map_binding_actions(move || define_function("bits",          bits_fn()), functions);
     let functions   = flat_map_binding_actions(move || define_function("sbits",         sbits_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("checked_bits",  checked_bits_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function("checked_sbits", checked_sbits_fn()), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
+
+    // Byte order functions
+    let functions   = flat_map_binding_actions(move || define_function("be16",          big_endian_fn(16)), functions);
+    let functions   = flat_map_binding_actions(move || define_function("be32",          big_endian_fn(32)), functions);
+    let functions   = flat_map_binding_actions(move || define_function("be64",          big_endian_fn(64)), functions);
+    let functions   = flat_map_binding_actions(move || define_function("le16",          little_endian_fn(16)), functions);
+    let functions   = flat_map_binding_actions(move || define_function("le32",          little_endian_fn(32)), functions);
+    let functions   = flat_map_binding_actions(move || define_function("le64",          little_endian_fn(64)), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
+
+    // Emulator functions
+    let functions   = flat_map_binding_actions(move || define_function_with_options("run_6502",      run_6502_fn()), functions);
+    let functions   = flat_map_binding_actions(move || define_function_with_options("run_65c02",     run_65c02_fn()), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
+
+    // Limits
+    let functions   = flat_map_binding_actions(move || define_function_with_options("set_limits",    set_limits_fn()), functions);
+
+    let functions: Box<dyn BindingMonad<Binding=_>> = Box::new(functions);
 
     // Monad functions
     let functions   = flat_map_binding_actions(move || define_function("wrap",          wrap_fn()), functions);
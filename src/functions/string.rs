@@ -0,0 +1,296 @@
+use crate::exec::*;
+use crate::meta::*;
+
+use radix_fmt::*;
+
+use std::convert::{TryFrom};
+
+///
+/// Formats a number in the specified radix, without the suffix used by SAFAS number literals
+///
+fn number_to_string(number: &SafasNumber, base: u8) -> String {
+    match number {
+        SafasNumber::SignedBitNumber(_bits, val) if *val < 0    => format!("-{}", radix(val.unsigned_abs(), base)),
+        other                                                   => format!("{}", radix(other.to_u128(), base))
+    }
+}
+
+///
+/// Returns the text used for a value when it's formatted into a string
+///
+/// Strings and characters are used as they are, and numbers are written in decimal
+///
+fn display_string(value: &CellRef) -> String {
+    match &**value {
+        SafasCell::String(string_value) => string_value.clone(),
+        SafasCell::Char(chr_value)      => chr_value.to_string(),
+        SafasCell::Number(number)       => number_to_string(number, 10),
+        other                           => other.to_string()
+    }
+}
+
+///
+/// Reads a string or a character as a string
+///
+fn string_or_char(value: &CellRef) -> Result<String, RuntimeError> {
+    match &**value {
+        SafasCell::String(string_value) => Ok(string_value.clone()),
+        SafasCell::Char(chr_value)      => Ok(chr_value.to_string()),
+        _                               => Err(RuntimeError::NotAString(value.clone()))
+    }
+}
+
+///
+/// (string_length "abc") -> 3
+///
+pub fn string_length_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(CellValue(string), ): (CellValue<String>, )| {
+        CellRef::new(SafasCell::Number(SafasNumber::Plain(string.chars().count() as u128)))
+    })
+}
+
+///
+/// (substring "hello" 1 3) -> "el"
+///
+/// The start and end are character positions, and the end is exclusive
+///
+pub fn substring_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(CellValue(string), start, end): (CellValue<String>, SafasNumber, SafasNumber)| {
+        let chars       = string.chars().collect::<Vec<_>>();
+        let start       = start.to_usize();
+        let end         = end.to_usize();
+
+        if end > chars.len()    { return Err(RuntimeError::IndexOutOfRange(end)); }
+        if start > end          { return Err(RuntimeError::IndexOutOfRange(start)); }
+
+        Ok(SafasCell::String(chars[start..end].iter().collect()).into())
+    })
+}
+
+///
+/// (concat "ab" 'c' "de") -> "abcde"
+///
+pub fn concat_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|strings: Vec<CellRef>| {
+        let mut result = String::new();
+        for string in strings.iter() {
+            result.push_str(&string_or_char(string)?);
+        }
+
+        Ok(SafasCell::String(result).into())
+    })
+}
+
+///
+/// (string_upcase "abc") -> "ABC"
+///
+pub fn string_upcase_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(CellValue(string), ): (CellValue<String>, )| {
+        CellRef::new(SafasCell::String(string.to_uppercase()))
+    })
+}
+
+///
+/// (string_downcase "ABC") -> "abc"
+///
+pub fn string_downcase_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(CellValue(string), ): (CellValue<String>, )| {
+        CellRef::new(SafasCell::String(string.to_lowercase()))
+    })
+}
+
+///
+/// (split "a,b,c" ",") -> ("a" "b" "c")
+///
+/// The separator can be a string or a character
+///
+pub fn split_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(CellValue(string), separator): (CellValue<String>, CellRef)| {
+        let separator = string_or_char(&separator)?;
+        if separator.len() == 0 { return Err(RuntimeError::TypeMismatch(SafasCell::String(separator).into())); }
+
+        let parts = string.split(&separator[..]).map(|part| SafasCell::String(part.to_string()).into()).collect::<Vec<_>>();
+        Ok(SafasCell::list_with_cells(parts))
+    })
+}
+
+///
+/// (format "v{}.{}" 1 2) -> "v1.2"
+///
+/// Each `{}` is replaced by the next argument: strings and characters are inserted as they are, and numbers are written in
+/// decimal. `{{` and `}}` can be used to write a literal brace.
+///
+pub fn format_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|args: Vec<CellRef>| {
+        if args.len() == 0 { return Err(RuntimeError::NotEnoughArguments(NIL.clone())); }
+
+        let format      = match &*args[0] { SafasCell::String(format) => format.clone(), _ => return Err(RuntimeError::NotAString(args[0].clone())) };
+        let mut values  = args[1..].iter();
+        let mut result  = String::new();
+        let mut chars   = format.chars().peekable();
+
+        while let Some(chr) = chars.next() {
+            match (chr, chars.peek()) {
+                ('{', Some('{'))    => { chars.next(); result.push('{'); }
+                ('}', Some('}'))    => { chars.next(); result.push('}'); }
+                ('{', Some('}'))    => {
+                    chars.next();
+
+                    let value = values.next().ok_or_else(|| RuntimeError::NotEnoughArguments(SafasCell::list_with_cells(args.iter().cloned())))?;
+                    result.push_str(&display_string(value));
+                }
+                (chr, _)            => result.push(chr)
+            }
+        }
+
+        if values.next().is_some() { return Err(RuntimeError::TooManyArguments(SafasCell::list_with_cells(args.iter().cloned()))); }
+
+        Ok(SafasCell::String(result).into())
+    })
+}
+
+///
+/// (char->number 'A') -> 65
+///
+pub fn char_to_number_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(CellValue(chr), ): (CellValue<char>, )| {
+        CellRef::new(SafasCell::Number(SafasNumber::Plain(chr as u128)))
+    })
+}
+
+///
+/// (number->char 65) -> 'A'
+///
+pub fn number_to_char_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|(number, ): (SafasNumber, )| {
+        let chr = u32::try_from(number.to_u128()).ok().and_then(std::char::from_u32);
+        let chr = chr.ok_or_else(|| RuntimeError::NotAChar(SafasCell::Number(number).into()))?;
+
+        Ok(SafasCell::Char(chr).into())
+    })
+}
+
+///
+/// (number->string 255) -> "255"
+/// (number->string 255 16) -> "ff"
+///
+/// The radix can be between 2 and 36, and is 10 if it's not specified
+///
+pub fn number_to_string_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    FnMonad::from(|args: Vec<SafasNumber>| {
+        let (number, base) = match args.len() {
+            0   => return Err(RuntimeError::NotEnoughArguments(NIL.clone())),
+            1   => (args[0], 10),
+            2   => (args[0], args[1].to_u128()),
+            _   => return Err(RuntimeError::TooManyArguments(SafasCell::list_with_cells(args.into_iter().map(|arg| SafasCell::Number(arg).into()))))
+        };
+
+        if !(2..=36).contains(&base) { return Err(RuntimeError::TypeMismatch(SafasCell::Number(args[1]).into())); }
+
+        Ok(SafasCell::String(number_to_string(&number, base as u8)).into())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::meta::*;
+    use crate::interactive::*;
+
+    #[test]
+    fn string_length() {
+        let val = eval(
+                "(list (string_length \"hello\") (string_length \"\"))"
+            ).unwrap().to_string();
+        assert!(val == "(5 0)".to_string());
+    }
+
+    #[test]
+    fn substring() {
+        let val = eval(
+                "(substring \"hello\" 1 3)"
+            ).unwrap().to_string();
+        assert!(val == "\"el\"".to_string());
+    }
+
+    #[test]
+    fn substring_out_of_range() {
+        let val = eval(
+                "(substring \"hello\" 1 6)"
+            );
+        assert!(format!("{:?}", val).contains("IndexOutOfRange(6)"));
+    }
+
+    #[test]
+    fn concat() {
+        let val = eval(
+                "(concat \"ab\" 'c' \"de\")"
+            ).unwrap().to_string();
+        assert!(val == "\"abcde\"".to_string());
+    }
+
+    #[test]
+    fn string_upcase() {
+        let val = eval(
+                "(list (string_upcase \"Hello\") (string_downcase \"Hello\"))"
+            ).unwrap().to_string();
+        assert!(val == "(\"HELLO\" \"hello\")".to_string());
+    }
+
+    #[test]
+    fn split() {
+        let val = eval(
+                "(list (split \"a,b,,c\" \",\") (split \"a b\" ' '))"
+            ).unwrap().to_string();
+        assert!(val == "((\"a\" \"b\" \"\" \"c\") (\"a\" \"b\"))".to_string());
+    }
+
+    #[test]
+    fn format() {
+        let val = eval(
+                "(format \"v{}.{} {}{} {{}}\" 1 $10u8 \"beta\" '!')"
+            ).unwrap().to_string();
+        assert!(val == "\"v1.16 beta! {}\"".to_string());
+    }
+
+    #[test]
+    fn format_not_enough_arguments() {
+        let val = eval(
+                "(format \"{} {}\" 1)"
+            );
+        assert!(val.is_err());
+    }
+
+    #[test]
+    fn char_to_number() {
+        let val = eval(
+                "(list (char->number 'A') (number->char 97))"
+            ).unwrap().to_string();
+        assert!(val == "(65 'a')".to_string());
+    }
+
+    #[test]
+    fn number_to_string() {
+        let val = eval(
+                "(list (number->string 255) (number->string 255 16) (number->string 5 2) (number->string (- 0i8 12i8)))"
+            ).unwrap().to_string();
+        assert!(val == "(\"255\" \"ff\" \"101\" \"-12\")".to_string());
+    }
+
+    #[test]
+    fn number_to_string_most_negative() {
+        let val = eval(
+                "(number->string (sbits 8 $80))"
+            ).unwrap().to_string();
+        assert!(val == "\"-128\"".to_string());
+
+        assert!(super::number_to_string(&SafasNumber::SignedBitNumber(128, i128::MIN), 16) == "-80000000000000000000000000000000".to_string());
+    }
+
+    #[test]
+    fn number_to_string_bad_radix() {
+        let val = eval(
+                "(number->string 255 37)"
+            );
+        assert!(val.is_err());
+    }
+}
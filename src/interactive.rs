@@ -4,6 +4,8 @@ use crate::bind::*;
 use crate::exec::*;
 use crate::syntax::*;
 use crate::functions::*;
+use crate::bitcode::*;
+use crate::io::*;
 
 use std::io;
 use std::io::{Write};
@@ -20,7 +22,8 @@ pub fn eval(expr: &str) -> Result<CellRef, RuntimeError> {
     let (frame, bindings)       = setup_standard_bindings(frame, bindings);
 
     // Parse the expression
-    let expr = parse_safas(&mut TokenReadBuffer::new(expr.chars()), FileLocation::new("<expr>"))?;
+    let (expr, locations) = parse_safas_with_locations(&mut TokenReadBuffer::new(expr.chars()), FileLocation::new("<expr>"))?;
+    bindings.source_locations.add_locations(&locations);
 
     // Evaluate the expression
     let (result, _bindings, _frame) = eval_statements(expr, NIL.clone(), bindings, frame);
@@ -38,7 +41,12 @@ pub fn eval(expr: &str) -> Result<CellRef, RuntimeError> {
 pub fn setup_standard_bindings(frame: Frame, bindings: SymbolBindings) -> (Frame, SymbolBindings) {
     // Apply the standard bindings
     let mut frame               = frame;
+    let mut bindings            = bindings;
     let syntax                  = standard_syntax();
+
+    // The bindings use the same limits as the frame, so `set_limits` affects both of them
+    bindings.limits             = frame.limits.clone();
+
     let functions               = standard_functions();
     let (bindings, actions)     = syntax.bind(bindings);
     let (bindings, fn_actions)  = functions.bind(bindings);
@@ -49,9 +57,173 @@ pub fn setup_standard_bindings(frame: Frame, bindings: SymbolBindings) -> (Frame
     (frame, bindings)
 }
 
+///
+/// The commands that can be entered in interactive mode (as well as SAFAS statements)
+///
+#[derive(Clone, Debug, PartialEq)]
+enum InteractiveCommand {
+    /// `:load <file>` - imports a file
+    Load(String),
+
+    /// `:reload` - restores the state from before the first `:load` and loads the same files again
+    Reload,
+
+    /// `:bindings` - lists the symbols that are currently bound
+    Bindings,
+
+    /// `:assemble` - assembles the current monad and displays a hexdump of the result
+    Assemble,
+
+    /// `:bitcode` - assembles the current monad and displays the bitcode it generates
+    BitCode,
+
+    /// `:quit` - leaves interactive mode
+    Quit,
+
+    /// A command that's not recognised
+    Unknown(String)
+}
+
+///
+/// Parses a line of input as an interactive command (returns None if the line does not start with ':')
+///
+fn parse_command(input: &str) -> Option<InteractiveCommand> {
+    let input = input.trim();
+    if !input.starts_with(':') { return None; }
+
+    let mut parts   = input[1..].splitn(2, char::is_whitespace);
+    let command     = parts.next().unwrap_or("");
+    let argument    = parts.next().map(|arg| arg.trim()).unwrap_or("");
+
+    match (command, argument) {
+        ("load", "")        => Some(InteractiveCommand::Unknown(input.to_string())),
+        ("load", file)      => Some(InteractiveCommand::Load(file.to_string())),
+        ("reload", "")      => Some(InteractiveCommand::Reload),
+        ("bindings", "")    => Some(InteractiveCommand::Bindings),
+        ("assemble", "")    => Some(InteractiveCommand::Assemble),
+        ("bitcode", "")     => Some(InteractiveCommand::BitCode),
+        ("quit", "")        => Some(InteractiveCommand::Quit),
+        _                   => Some(InteractiveCommand::Unknown(input.to_string()))
+    }
+}
+
+///
+/// Reads a statement from stdin, carrying on to the next line while there are unclosed brackets
+///
+/// Returns None once the end of the input is reached. Commands are only read from the first line of the statement.
+///
+fn read_statement() -> Option<String> {
+    let mut input = String::new();
+
+    loop {
+        // Read a line
+        print!("{}", if input.is_empty() { "-> " } else { ".. " });
+        io::stdout().flush().unwrap();
+        let num_bytes = io::stdin().read_line(&mut input).unwrap();
+
+        // EOF if num_bytes = 0
+        if num_bytes == 0 {
+            return if input.trim().is_empty() { None } else { Some(input) };
+        }
+
+        // Commands are always a single line
+        if parse_command(&input).is_some() {
+            return Some(input);
+        }
+
+        // Keep reading while the parser is still looking for a closing bracket
+        match parse_safas(&mut TokenReadBuffer::new(input.chars()), FileLocation::new("<stdin>")) {
+            Err(ParseError::MissingCloseParen(_))   => { },
+            _                                       => { return Some(input); }
+        }
+    }
+}
+
+///
+/// Returns the names of the symbols that are bound in a set of bindings (and their parents), in alphabetical order
+///
+fn bound_symbol_names(bindings: &SymbolBindings) -> Vec<String> {
+    let mut names       = vec![];
+    let mut bindings    = Some(bindings);
+
+    while let Some(current) = bindings {
+        names.extend(current.symbols.keys().map(|atom_id| name_for_atom_with_id(*atom_id)));
+        bindings = current.parent.as_ref().map(|parent| &**parent);
+    }
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+///
+/// Assembles the result of an interactive statement, if it's a bitcode monad
+///
+fn assemble_result(result: &CellRef, limits: &Limits) -> Option<Result<Vec<BitCode>, RuntimeError>> {
+    let monad = BitCodeMonad::from_cell(result)?;
+
+    Some(assemble_with_listing(&monad, limits, &mut vec![]).map(|(_value, bitcode, _labels, _listing)| bitcode))
+}
+
+///
+/// Displays the bytes generated by assembling the result of a statement
+///
+fn display_hexdump(result: &CellRef, limits: &Limits) {
+    match assemble_result(result, limits) {
+        Some(Ok(bitcode))   => println!("{}", hexdump(&bitcode_to_bytes(bitcode))),
+        Some(Err(err))      => print!("{}", error_report(&err)),
+        None                => println!("!! Not a bitcode monad")
+    }
+}
+
+///
+/// Parses and evaluates some source code, following on from a monad value
+///
+fn eval_source(source: &str, location: &str, monad_value: CellRef, bindings: SymbolBindings, frame: Frame) -> (CellRef, SymbolBindings, Frame) {
+    match parse_safas_with_locations(&mut TokenReadBuffer::new(source.chars()), FileLocation::new(location)) {
+        Ok((statements, locations)) => {
+            bindings.source_locations.add_locations(&locations);
+            eval_statements(statements, monad_value, bindings, frame)
+        },
+
+        Err(err)                    => (RuntimeError::ParseError(err).into(), bindings, frame)
+    }
+}
+
+///
+/// Loads a series of files in order, in the same way as the `:load` command (stopping at the first error)
+///
+/// Files are searched for in the same way as `import_file`, so the import path and the built-in sources can be used.
+///
+fn load_files(files: &Vec<String>, monad_value: CellRef, bindings: SymbolBindings, frame: Frame) -> (CellRef, SymbolBindings, Frame) {
+    let mut result      = monad_value;
+    let mut bindings    = bindings;
+    let mut frame       = frame;
+
+    for file in files.iter() {
+        let (source, location) = match read_import_file(file, &bindings, true) {
+            Ok(source)  => source,
+            Err(err)    => return (err.into(), bindings, frame)
+        };
+
+        let (next_result, next_bindings, next_frame) = eval_source(&source, &location, result, bindings, frame);
+        result      = next_result;
+        bindings    = next_bindings;
+        frame       = next_frame;
+
+        if let SafasCell::Error(_) = &*result { break; }
+    }
+
+    (result, bindings, frame)
+}
+
 ///
 /// Runs the parser and interpreter in interactive mode, displaying the results to the user
 ///
+/// Statements carry on over several lines until their brackets are closed. Lines starting with ':' are commands: `:load <file>`,
+/// `:reload`, `:bindings`, `:assemble`, `:bitcode` and `:quit` are supported. `:reload` goes back to the state from before the
+/// first `:load` and then loads the same files again.
+///
 pub fn run_interactive(frame: Frame, bindings: SymbolBindings) {
     println!("{} version {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
     println!("Interactive interpreter");
@@ -59,42 +231,147 @@ pub fn run_interactive(frame: Frame, bindings: SymbolBindings) {
     let mut frame               = frame;
     let mut bindings            = bindings;
     let mut monad_value         = NIL.clone();
+    let mut loaded_files        = vec![];
+    let mut before_load         = None;
 
     loop {
-        // Read a line
-        let mut input = String::new();
+        // Read the next statement
         println!();
-        print!("-> ");
-        io::stdout().flush().unwrap();
-        let num_bytes = io::stdin().read_line(&mut input).unwrap();
+        let input = match read_statement() {
+            Some(input) => input,
+            None        => break
+        };
 
-        // EOF if num_bytes = 0
-        if num_bytes == 0 {
-            break;
-        }
+        // Run the command if there is one
+        let (result, next_bindings, next_frame) = match parse_command(&input) {
+            None                                    => eval_source(&input, "<stdin>", monad_value.clone(), bindings, frame),
 
-        // Parse the input
-        let input = parse_safas(&mut TokenReadBuffer::new(input.chars()), FileLocation::new("<stdin>"));
-        let input = match input {
-            Ok(input)   => input,
-            Err(error)  => {
-                println!("!! Parse error: {:?}", error);
+            Some(InteractiveCommand::Quit)          => break,
+            Some(InteractiveCommand::Unknown(cmd))  => { println!("!! Unknown command: {}", cmd); continue; }
+            Some(InteractiveCommand::Assemble)      => { display_hexdump(&monad_value, &frame.limits.get()); continue; }
+
+            Some(InteractiveCommand::BitCode)       => {
+                match assemble_result(&monad_value, &frame.limits.get()) {
+                    Some(Ok(bitcode))   => println!("{}", bitcode.iter().map(|code| code.to_string()).collect::<Vec<_>>().join(" ")),
+                    Some(Err(err))      => print!("{}", error_report(&err)),
+                    None                => println!("!! Not a bitcode monad")
+                }
+                continue;
+            }
+
+            Some(InteractiveCommand::Bindings)      => {
+                for names in bound_symbol_names(&bindings).chunks(8) {
+                    println!("{}", names.join(" "));
+                }
                 continue;
             }
-        };
 
-        // Evaluate the frame
-        let (result, next_bindings, next_frame) = eval_statements(input, monad_value, bindings, frame);
+            Some(InteractiveCommand::Load(file))    => {
+                if let ImportFile::NotFound = locate_import_file(&file, &bindings, true) { println!("!! Could not find {}", file); continue; }
 
-        // Display the next result
+                // Remember the state before the first file was loaded so that :reload can start again from there
+                if before_load.is_none() {
+                    before_load = Some((monad_value.clone(), bindings.clone(), frame.clone()));
+                }
+
+                loaded_files.push(file.clone());
+                load_files(&vec![file], monad_value.clone(), bindings, frame)
+            }
+
+            Some(InteractiveCommand::Reload)        => {
+                let (initial_value, initial_bindings, initial_frame) = match &before_load {
+                    Some(state) => state.clone(),
+                    None        => { println!("!! No file has been loaded"); continue; }
+                };
+
+                monad_value = initial_value.clone();
+                load_files(&loaded_files, initial_value, initial_bindings, initial_frame)
+            }
+        };
+
+        // Display the next result (bitcode is assembled and displayed as a hexdump)
         match &*result {
-            SafasCell::Error(err)   => println!("!! Error: {:?}", err),
-            _                       => println!("{}", result.to_string())
+            SafasCell::Error(err)   => print!("{}", error_report(err)),
+            _                       => {
+                if BitCodeMonad::from_cell(&result).is_some() {
+                    display_hexdump(&result, &next_frame.limits.get());
+                } else {
+                    println!("{}", result.to_string())
+                }
+            }
         }
 
-        // Update to the next result
+        // Update to the next result (the monad value is left alone if there was an error)
         frame       = next_frame;
         bindings    = next_bindings;
-        monad_value = result;
+        if !matches!(&*result, SafasCell::Error(_)) {
+            monad_value = result;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_load_command() {
+        assert!(parse_command(":load examples/x16_hello_world.sf") == Some(InteractiveCommand::Load("examples/x16_hello_world.sf".to_string())));
+    }
+
+    #[test]
+    fn parse_simple_commands() {
+        assert!(parse_command(":reload\n") == Some(InteractiveCommand::Reload));
+        assert!(parse_command(":bindings") == Some(InteractiveCommand::Bindings));
+        assert!(parse_command(":assemble") == Some(InteractiveCommand::Assemble));
+        assert!(parse_command(":bitcode") == Some(InteractiveCommand::BitCode));
+        assert!(parse_command(":quit") == Some(InteractiveCommand::Quit));
+        assert!(parse_command(":frobnicate") == Some(InteractiveCommand::Unknown(":frobnicate".to_string())));
+    }
+
+    #[test]
+    fn statements_are_not_commands() {
+        assert!(parse_command("(d $1u8)") == None);
+    }
+
+    #[test]
+    fn bound_symbols_are_listed() {
+        let frame               = Frame::new(1, None);
+        let bindings            = SymbolBindings::new();
+        let (_frame, bindings)  = setup_standard_bindings(frame, bindings);
+        let names               = bound_symbol_names(&bindings);
+
+        assert!(names.contains(&"def".to_string()));
+        assert!(names.contains(&"d".to_string()));
+    }
+
+    #[test]
+    fn load_builtin_file() {
+        let (frame, bindings)   = setup_standard_bindings(Frame::new(1, None), SymbolBindings::new());
+        let files               = vec!["standard/default".to_string(), "cpu/6502".to_string()];
+        let (result, _, _)      = load_files(&files, NIL.clone(), bindings, frame);
+
+        assert!(!matches!(&*result, SafasCell::Error(_)));
+    }
+
+    #[test]
+    fn reload_starts_from_saved_state() {
+        let path = std::env::temp_dir().join("safas_reload_test.sf");
+        std::fs::write(&path, "(d $1u8)").unwrap();
+
+        let (frame, bindings)   = setup_standard_bindings(Frame::new(1, None), SymbolBindings::new());
+        let files               = vec![path.to_string_lossy().to_string()];
+        let before_load         = (NIL.clone(), bindings.clone(), frame.clone());
+
+        // Load the file, then reload it from the state saved before the load
+        let (loaded, _, _)      = load_files(&files, NIL.clone(), bindings, frame);
+        let (value, bindings, frame) = before_load;
+        let (reloaded, _, _)    = load_files(&files, value, bindings, frame);
+
+        let loaded              = assemble_result(&loaded, &Limits::default()).unwrap().unwrap();
+        let reloaded            = assemble_result(&reloaded, &Limits::default()).unwrap().unwrap();
+
+        assert!(bitcode_to_bytes(loaded) == vec![1]);
+        assert!(bitcode_to_bytes(reloaded) == vec![1]);
     }
 }
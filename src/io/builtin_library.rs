@@ -47,3 +47,32 @@ pub fn builtin_library() -> CellRef {
 
     builtin_library
 }
+
+#[cfg(test)]
+mod test {
+    use crate::test_support::*;
+
+    #[test]
+    fn z80_relative_jump_backwards() {
+        let mut session = cpu_session("z80");
+        let assembly    = session.assemble("(assemble_z80 (set_ip $8000) (label loop) (nop) (djnz loop) (jr z, loop))").unwrap();
+
+        assert!(assembly.bytes() == vec![0x00, 0x10, 0xfd, 0x28, 0xfb]);
+    }
+
+    #[test]
+    fn z80_relative_jump_out_of_range() {
+        let mut session = cpu_session("z80");
+        let error       = session.assemble("(assemble_z80 (jr 200))").unwrap_err();
+
+        assert!(format!("{:?}", error).contains("ValueOutOfRange"));
+    }
+
+    #[test]
+    fn z80_index_offset_out_of_range() {
+        let mut session = cpu_session("z80");
+        let error       = session.assemble("(assemble_z80 (ld a, (ix - 129)))").unwrap_err();
+
+        assert!(format!("{:?}", error).contains("ValueOutOfRange"));
+    }
+}
@@ -0,0 +1,195 @@
+use super::builtin_library::*;
+
+use crate::meta::*;
+use crate::session::*;
+
+use std::fs;
+use std::path::{PathBuf};
+
+///
+/// An instruction from a conformance table, and the bytes it should assemble to
+///
+struct ExpectedEncoding {
+    /// The table and line number that this instruction was read from
+    location: String,
+
+    /// The instruction to assemble
+    instruction: String,
+
+    /// The bytes that the instruction should assemble to
+    bytes: Vec<u8>
+}
+
+///
+/// Returns the path of the conformance table for a CPU library
+///
+fn conformance_table_path(cpu_name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("conformance").join("cpu").join(format!("{}.txt", cpu_name))
+}
+
+///
+/// Returns the names of the CPU libraries in the built-in library (eg, `6502` for `cpu/6502.sf`)
+///
+fn cpu_libraries() -> Vec<String> {
+    btree_iterate(builtin_library())
+        .filter_map(|(path, _content)| path.string_value())
+        .filter_map(|path| {
+            if path.starts_with("cpu/") && path.ends_with(".sf") {
+                Some(path["cpu/".len()..(path.len()-".sf".len())].to_string())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+///
+/// Reads the conformance table for a CPU library
+///
+/// Each line of the table is an instruction, followed by `=>` and the bytes it should assemble to in hex, eg
+/// `(lda #$12) => a9 12`. `include <cpu>` reads the instructions from the table for another CPU (for CPUs that
+/// extend another one). Blank lines and lines starting with `;` are ignored.
+///
+fn read_conformance_table(cpu_name: &str) -> Result<Vec<ExpectedEncoding>, String> {
+    let path    = conformance_table_path(cpu_name);
+    let table   = fs::read_to_string(&path).map_err(|err| format!("cpu/{}: could not read {}: {}", cpu_name, path.display(), err))?;
+
+    let mut encodings = vec![];
+
+    for (line_num, line) in table.lines().enumerate() {
+        let line        = line.trim();
+        let location    = format!("{}.txt:{}", cpu_name, line_num+1);
+
+        if line.is_empty() || line.starts_with(";") {
+            // Comment
+            continue;
+        } else if line.starts_with("include ") {
+            // Instructions from another table
+            encodings.extend(read_conformance_table(line["include ".len()..].trim())?);
+        } else {
+            // Instruction => bytes
+            let mut parts   = line.splitn(2, "=>");
+            let instruction = parts.next().unwrap_or("").trim().to_string();
+            let bytes       = parts.next().ok_or_else(|| format!("{}: missing '=>'", location))?;
+            let bytes       = bytes.split_whitespace()
+                .map(|byte| u8::from_str_radix(byte, 16).map_err(|_| format!("{}: '{}' is not a hex byte", location, byte)))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            encodings.push(ExpectedEncoding { location: location, instruction: instruction, bytes: bytes });
+        }
+    }
+
+    Ok(encodings)
+}
+
+///
+/// Returns the mnemonic for an instruction (eg, `lda` for `(lda #$12)`)
+///
+fn mnemonic(instruction: &str) -> &str {
+    instruction.trim_start_matches('(').split(|c: char| c.is_whitespace() || c == ')').next().unwrap_or("")
+}
+
+///
+/// Replaces the numbers in an instruction with a placeholder, which gives the addressing mode or pattern that the
+/// instruction is testing (eg, `(lda $1234, X)` becomes `(lda <n>, X)`)
+///
+fn instruction_pattern(instruction: &str) -> String {
+    let mut pattern     = String::new();
+    let mut in_number   = false;
+    let mut last_char   = ' ';
+
+    for chr in instruction.chars() {
+        let is_word_char = chr.is_alphanumeric() || chr == '_' || chr == '$';
+
+        if in_number && is_word_char {
+            // Skip the rest of the number
+        } else if !last_char.is_alphanumeric() && last_char != '_' && (chr == '$' || chr.is_digit(10)) {
+            // Start of a number
+            pattern.push_str("<n>");
+            in_number = true;
+        } else {
+            pattern.push(chr);
+            in_number = false;
+        }
+
+        last_char = chr;
+    }
+
+    pattern
+}
+
+///
+/// Formats some bytes as hex
+///
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+///
+/// Assembles every instruction in the conformance table for a CPU library, and returns a description of each one that
+/// did not assemble to the expected bytes
+///
+fn check_cpu_library(cpu_name: &str) -> Vec<String> {
+    let encodings = match read_conformance_table(cpu_name) {
+        Ok(encodings)   => encodings,
+        Err(err)        => return vec![err]
+    };
+
+    // Load the library into a new session
+    let mut session = match Session::new() {
+        Ok(session)     => session,
+        Err(err)        => return vec![format!("cpu/{}: could not create session: {:?}", cpu_name, err)]
+    };
+
+    if let Err(err) = session.eval(&format!("(import \"cpu/{}\")", cpu_name)) {
+        return vec![format!("cpu/{}: could not import library: {:?}", cpu_name, err)];
+    }
+
+    // Assemble each instruction and compare against the table
+    let mut failures = vec![];
+
+    for encoding in encodings.iter() {
+        let assembly    = session.assemble(&format!("(assemble_{} {})", cpu_name, encoding.instruction));
+        let failure     = match assembly {
+            Ok(assembly)    => if assembly.bytes() != encoding.bytes { Some(format!("expected {}, got {}", hex_bytes(&encoding.bytes), hex_bytes(&assembly.bytes()))) } else { None },
+            Err(err)        => Some(format!("expected {}, got error {:?}", hex_bytes(&encoding.bytes), err))
+        };
+
+        if let Some(failure) = failure {
+            failures.push(format!("cpu/{}: {} {} ({} at {}): {}", cpu_name, mnemonic(&encoding.instruction), instruction_pattern(&encoding.instruction), encoding.instruction, encoding.location, failure));
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn instruction_patterns() {
+        assert!(instruction_pattern("(lda #$12)") == "(lda #<n>)".to_string());
+        assert!(instruction_pattern("(sbc $1234, Y)") == "(sbc <n>, Y)".to_string());
+        assert!(instruction_pattern("(ld a, (ix + 5))") == "(ld a, (ix + <n>))".to_string());
+        assert!(instruction_pattern("(bbr0 $12 0)") == "(bbr0 <n> <n>)".to_string());
+    }
+
+    #[test]
+    fn instruction_mnemonics() {
+        assert!(mnemonic("(lda #$12)") == "lda");
+        assert!(mnemonic("(nop)") == "nop");
+    }
+
+    #[test]
+    fn every_cpu_library_conforms() {
+        let cpus = cpu_libraries();
+        assert!(cpus.len() > 0);
+
+        let failures = cpus.iter()
+            .flat_map(|cpu_name| check_cpu_library(cpu_name))
+            .collect::<Vec<_>>();
+
+        assert!(failures.len() == 0, "{} instructions did not match their conformance tables:\n{}", failures.len(), failures.join("\n"));
+    }
+}
@@ -146,35 +146,42 @@ pub fn locate_import_file(filename: &str, bindings: &SymbolBindings, allow_relat
 }
 
 ///
-/// Imports a file using the specified symbol bindings as the environment (for non-absolute paths, the `import_path` atom can be defined
-/// to a list of places to look. `built_ins` can be used to supply a set of built-in files as strings that are used if the file can't
-/// be found on the import path)
+/// Locates a file to import in the same way as `import_file`, and returns its contents and the name of the file that was found
 ///
-pub fn import_file(filename: &str, bindings: SymbolBindings, frame: Frame, allow_relative: bool) -> (CellRef, SymbolBindings, Frame) {
-    let file_path = locate_import_file(filename, &bindings, allow_relative);
-
-    // Read the file contents
-    let (file_content, file_path) = match file_path {
+pub fn read_import_file(filename: &str, bindings: &SymbolBindings, allow_relative: bool) -> Result<(String, String), RuntimeError> {
+    match locate_import_file(filename, bindings, allow_relative) {
         ImportFile::FromPath(file_path) => {
-            let content = fs::read_to_string(file_path.as_path());
-            let content = match content { Ok(content) => content, Err(_err) => { return (RuntimeError::IOError.into(), bindings, frame); } };
+            let content = fs::read_to_string(file_path.as_path()).map_err(|_err| RuntimeError::IOError)?;
 
-            (content, String::from(file_path.to_string_lossy()))
+            Ok((content, String::from(file_path.to_string_lossy())))
         },
 
         ImportFile::BuiltIn(file_path, content) => {
-            (content, file_path)
+            Ok((content, file_path))
         },
 
         ImportFile::NotFound => {
-            // File not found
-            return (RuntimeError::FileNotFound(filename.to_string()).into(), bindings, frame);
+            Err(RuntimeError::FileNotFound(filename.to_string()))
         }
+    }
+}
+
+///
+/// Imports a file using the specified symbol bindings as the environment (for non-absolute paths, the `import_path` atom can be defined
+/// to a list of places to look. `built_ins` can be used to supply a set of built-in files as strings that are used if the file can't
+/// be found on the import path)
+///
+pub fn import_file(filename: &str, bindings: SymbolBindings, frame: Frame, allow_relative: bool) -> (CellRef, SymbolBindings, Frame) {
+    // Read the file contents
+    let (file_content, file_path) = match read_import_file(filename, &bindings, allow_relative) {
+        Ok(file)    => file,
+        Err(err)    => { return (err.into(), bindings, frame); }
     };
 
     // Parse the file
-    let file_content = parse_safas(&mut TokenReadBuffer::new(file_content.chars()), FileLocation::new(&file_path));
-    let file_content = match file_content { Ok(content) => content, Err(err) => { return (RuntimeError::ParseError(err).into(), bindings, frame); } };
+    let file_content                = parse_safas_with_locations(&mut TokenReadBuffer::new(file_content.chars()), FileLocation::new(&file_path));
+    let (file_content, locations)   = match file_content { Ok(content) => content, Err(err) => { return (RuntimeError::ParseError(err).into(), bindings, frame); } };
+    bindings.source_locations.add_locations(&locations);
 
     // Evaluate the file
     let bindings                    = bindings.push_interior_frame();
@@ -223,18 +230,18 @@ impl BindingMonad for LocateImportFile {
 pub fn import_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
     LocateImportFile.map_result(|(filename, file_path)| {
         // Read the file content
-        let file_content = match file_path {
-            ImportFile::NotFound                => "".to_string(),
-            ImportFile::FromPath(path)          => fs::read_to_string(path.as_path()).map_err(|_io| BindError::IOError)?,
-            ImportFile::BuiltIn(_name, data)    => data
+        let (file_content, file_name) = match file_path {
+            ImportFile::NotFound                => ("".to_string(), filename.clone()),
+            ImportFile::FromPath(path)          => (fs::read_to_string(path.as_path()).map_err(|_io| BindError::IOError)?, String::from(path.to_string_lossy())),
+            ImportFile::BuiltIn(name, data)     => (data, name)
         };
 
-        // Parse the file content
-        let parsed = parse_safas(&mut TokenReadBuffer::new(file_content.chars()), FileLocation::new(&filename))?;
+        // Parse the file content (locations in the file use the name of the file that was found)
+        let (parsed, locations) = parse_safas_with_locations(&mut TokenReadBuffer::new(file_content.chars()), FileLocation::new(&file_name))?;
 
         // Pass through the parsed content
-        Ok((filename, parsed))
-    }).and_then(|(_filename, parsed_input)| {
+        Ok((filename, parsed, locations))
+    }).and_then(|(_filename, parsed_input, locations)| {
 
         // Bind the result
         BindingFn::from_binding_fn(move |bindings| {
@@ -242,6 +249,8 @@ pub fn import_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
             let mut bindings    = bindings;
             let mut pos         = &*parsed_input;
 
+            bindings.source_locations.add_locations(&locations);
+
             // Pre-bind each of the statements
             while let SafasCell::List(statement, next) = pos {
                 let (next_bindings, _result)    = pre_bind_statement(statement.clone(), bindings);
@@ -325,4 +334,12 @@ mod test {
             (import \"cpu/65c02\")"
         ).unwrap();
     }
+
+    #[test]
+    fn load_z80() {
+        eval(
+            "(import \"standard/default.sf\")
+            (import \"cpu/z80\")"
+        ).unwrap();
+    }
 }
@@ -0,0 +1,203 @@
+use super::import::*;
+
+use crate::bind::*;
+use crate::meta::*;
+use crate::exec::*;
+use crate::bitcode::*;
+
+use smallvec::*;
+use std::fs;
+use std::convert::{TryFrom};
+
+///
+/// Reads the contents of a binary file, searching for it the same way as `import` searches for source files
+///
+fn read_binary_file(filename: &str, bindings: &SymbolBindings) -> Result<Vec<u8>, BindError> {
+    match locate_import_file(filename, bindings, false) {
+        ImportFile::FromPath(path)          => fs::read(path.as_path()).map_err(|_io| BindError::IOError),
+        ImportFile::BuiltIn(_name, data)    => Ok(data.into_bytes()),
+        ImportFile::NotFound                => Err(BindError::FileNotFound(filename.to_string()))
+    }
+}
+
+///
+/// Binding monad that reads a binary file when it's bound
+///
+fn read_binary_file_binding(filename: String) -> impl BindingMonad<Binding=Vec<u8>> {
+    BindingFn::from_binding_fn(move |bindings| {
+        let data = read_binary_file(&filename, &bindings);
+        (bindings, data)
+    })
+}
+
+///
+/// Reads an offset or a length passed to `incbin`, which must be a positive number that fits in a usize
+///
+fn file_position(value: &CellRef) -> Result<usize, RuntimeError> {
+    let number      = value.number_value().ok_or_else(|| RuntimeError::NotANumber(value.clone()))?;
+    let is_negative = match number { SafasNumber::SignedBitNumber(_bits, val) => val < 0, _ => false };
+
+    if is_negative { return Err(RuntimeError::ValueOutOfRange(number, usize::BITS as u8)); }
+
+    usize::try_from(number.to_u128()).map_err(|_| RuntimeError::ValueOutOfRange(number, usize::BITS as u8))
+}
+
+///
+/// `(incbin_data filename bitcode [offset] [length])` - the function that the `incbin` keyword calls to write the contents of the file
+///
+fn incbin_data_fn() -> impl FrameMonad<Binding=RuntimeResult> {
+    ReturnsMonad(FnMonad::from(|args: Vec<CellRef>| {
+        let filename    = match &*args[0] { SafasCell::String(filename) => filename.clone(), _ => String::new() };
+        let data        = match &*args[1] { SafasCell::BitCode(data) => data, _ => return Err(RuntimeError::NotBitCode(args[1].clone())) };
+
+        // The offset and length are both optional
+        let offset      = match args.get(2) { Some(offset) => file_position(offset)?, None => 0 };
+        let length      = match args.get(3) { Some(length) => file_position(length)?, None => data.len().saturating_sub(offset) };
+
+        if offset > data.len() || length > data.len() - offset {
+            return Err(RuntimeError::ReadPastEndOfFile(filename, offset.saturating_add(length)));
+        }
+
+        Ok(BitCodeMonad::write_bitcode(data[offset..(offset+length)].iter().cloned()).to_cell())
+    }))
+}
+
+///
+/// The `incbin` keyword writes the contents of a binary file to the output
+///
+/// `(incbin "path" [offset] [length])` - the file is located using the import path when the statement is bound, and its
+/// bytes (starting at `offset`, and limited to `length` bytes if these are supplied) are written as 8-bit values.
+///
+pub fn incbin_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    get_expression_arguments().and_then(|ListWithTail((CellValue(filename), ), range): ListWithTail<(CellValue<String>, ), CellRef>| {
+        let range = range.clone();
+
+        read_binary_file_binding(filename.clone()).and_then(move |data| {
+            let filename    = filename.clone();
+            let range       = range.clone();
+
+            BindingFn::from_binding_fn(move |bindings| {
+                let mut bindings    = bindings;
+                let data            = data.iter().map(|byte| BitCode::Bits(8, *byte as u128)).collect::<Vec<_>>();
+                let incbin_data     = SafasCell::FrameMonad(Box::new(incbin_data_fn()));
+
+                // The binding is a call to the incbin_data function with the data from the file and the bound offset and length
+                let mut call        = vec![incbin_data.into(), SafasCell::String(filename.clone()).into(), SafasCell::BitCode(data).into()];
+                let mut pos         = &*range;
+
+                while let SafasCell::List(arg, next) = pos {
+                    match bind_statement(arg.clone(), bindings) {
+                        Ok((bound_arg, new_bindings))   => { bindings = new_bindings; call.push(bound_arg); }
+                        Err((err, new_bindings))        => { return (new_bindings, Err(err)); }
+                    }
+
+                    pos = &*next;
+                }
+
+                if call.len() > 5 { return (bindings, Err(BindError::TooManyArguments)); }
+
+                (bindings, Ok(SafasCell::list_with_cells(call)))
+            })
+        })
+    }).map(|call: CellRef| {
+        let compiler = |call: CellRef| compile_statement(call);
+
+        SyntaxCompiler::with_compiler_and_reftype(compiler, call.clone(), ReferenceType::Monad)
+    })
+}
+
+///
+/// The `file_size` keyword returns the size of a binary file in bytes
+///
+/// `(file_size "path")` - the file is located using the import path in the same way as for `incbin`.
+///
+pub fn file_size_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    get_expression_arguments().and_then(|ListTuple((CellValue(filename), )): ListTuple<(CellValue<String>, )>| {
+        read_binary_file_binding(filename)
+    }).map(|data: Vec<u8>| {
+        // The size of the file is a constant that's found when the statement is bound
+        let size        = SafasCell::Number(SafasNumber::Plain(data.len() as u128)).into();
+        let compiler    = |size: CellRef| Ok(smallvec![Action::Value(size)].into());
+
+        SyntaxCompiler::with_compiler(compiler, size)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+    use crate::test_support::*;
+
+    use std::env;
+    use std::fs;
+
+    fn test_file(name: &str, content: &[u8]) -> String {
+        let path = env::temp_dir().join(format!("safas_incbin_{}.bin", name));
+        fs::write(&path, content).unwrap();
+
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn include_whole_file() {
+        let path    = test_file("whole", &[1, 2, 3, 4]);
+        let bytes   = assembly_for(&format!("(d $ffu8) (incbin \"{}\")", path)).unwrap().bytes();
+
+        assert!(bytes == vec![0xff, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn include_with_offset_and_length() {
+        let path    = test_file("range", &[1, 2, 3, 4, 5]);
+        let bytes   = assembly_for(&format!("(incbin \"{}\" 1 (+ 1 2))", path)).unwrap().bytes();
+
+        assert!(bytes == vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn include_past_end_of_file() {
+        let path    = test_file("past_end", &[1, 2, 3]);
+        let error   = assembly_for(&format!("(incbin \"{}\" 2 2)", path)).unwrap_err();
+
+        assert!(format!("{:?}", error).contains("ReadPastEndOfFile"));
+    }
+
+    #[test]
+    fn include_huge_length() {
+        let path    = test_file("huge_length", &[1, 2, 3]);
+        let error   = assembly_for(&format!("(incbin \"{}\" 1 $ffffffffffffffff)", path)).unwrap_err();
+
+        assert!(format!("{:?}", error).contains("ReadPastEndOfFile"));
+    }
+
+    #[test]
+    fn include_negative_offset() {
+        let path    = test_file("negative_offset", &[1, 2, 3]);
+        let error   = assembly_for(&format!("(incbin \"{}\" (- 0i8 1i8))", path)).unwrap_err();
+
+        assert!(format!("{:?}", error).contains("ValueOutOfRange"));
+    }
+
+    #[test]
+    fn include_too_wide_length() {
+        let path    = test_file("too_wide_length", &[1, 2, 3]);
+        let error   = assembly_for(&format!("(incbin \"{}\" 0 $10000000000000000)", path)).unwrap_err();
+
+        assert!(format!("{:?}", error).contains("ValueOutOfRange"));
+    }
+
+    #[test]
+    fn include_missing_file() {
+        let error = assembly_for("(incbin \"./does/not/exist.bin\")").unwrap_err();
+
+        assert!(format!("{:?}", error).contains("FileNotFound(\"./does/not/exist.bin\")"));
+    }
+
+    #[test]
+    fn size_of_file() {
+        let path    = test_file("size", &[0; 37]);
+        let val     = eval(&format!("(file_size \"{}\")", path)).unwrap().to_string();
+
+        assert!(val == "37".to_string());
+    }
+}
@@ -1,7 +1,17 @@
 mod import;
 mod hexdump;
+mod output_format;
+mod record_formats;
+mod symbol_file;
 mod builtin_library;
+mod incbin;
+
+#[cfg(test)] mod cpu_conformance;
 
 pub use self::import::*;
 pub use self::hexdump::*;
+pub use self::output_format::*;
+pub use self::record_formats::*;
+pub use self::symbol_file::*;
 pub use self::builtin_library::*;
+pub use self::incbin::*;
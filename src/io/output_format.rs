@@ -0,0 +1,96 @@
+use super::hexdump::*;
+use super::record_formats::*;
+
+use crate::exec::*;
+use crate::bitcode::*;
+
+///
+/// The formats that the assembler can write its output in
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Raw bytes, with any gaps filled with zeros
+    Binary,
+
+    /// A human-readable hex dump of the raw bytes
+    HexDump,
+
+    /// Intel HEX, using extended addresses only if they're needed
+    IntelHex,
+
+    /// Intel HEX with 16-bit addresses
+    I8Hex,
+
+    /// Intel HEX with 32-bit addresses
+    I32Hex,
+
+    /// Motorola S-records, using the smallest address size that fits the output
+    SRecord,
+
+    /// Motorola S-records with 16-bit addresses
+    S19,
+
+    /// Motorola S-records with 24-bit addresses
+    S28,
+
+    /// Motorola S-records with 32-bit addresses
+    S37
+}
+
+/// The names of the output formats, as they can be specified on the command line
+pub const OUTPUT_FORMAT_NAMES: [&str; 9] = ["binary", "hexdump", "ihex", "i8hex", "i32hex", "srec", "s19", "s28", "s37"];
+
+impl OutputFormat {
+    ///
+    /// Retrieves the output format with the specified name
+    ///
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "binary"    => Some(OutputFormat::Binary),
+            "hexdump"   => Some(OutputFormat::HexDump),
+            "ihex"      => Some(OutputFormat::IntelHex),
+            "i8hex"     => Some(OutputFormat::I8Hex),
+            "i32hex"    => Some(OutputFormat::I32Hex),
+            "srec"      => Some(OutputFormat::SRecord),
+            "s19"       => Some(OutputFormat::S19),
+            "s28"       => Some(OutputFormat::S28),
+            "s37"       => Some(OutputFormat::S37),
+            _           => None
+        }
+    }
+
+    ///
+    /// Converts assembled bitcode to the data that should be written out for this format
+    ///
+    pub fn write_bitcode(&self, bitcode: &Vec<BitCode>) -> Result<Vec<u8>, RuntimeError> {
+        use self::OutputFormat::*;
+
+        match self {
+            Binary                          => Ok(bitcode_to_bytes(bitcode.iter().cloned())),
+            HexDump                         => Ok((hexdump(&bitcode_to_bytes(bitcode.iter().cloned())) + "\n").into_bytes()),
+
+            IntelHex | I8Hex | I32Hex       => {
+                let segments    = bitcode_to_segments(bitcode);
+                let format      = match self {
+                    I8Hex   => RecordFormat::I8Hex,
+                    I32Hex  => RecordFormat::I32Hex,
+                    _       => RecordFormat::intel_hex_for(&segments)
+                };
+
+                Ok(segments_to_intel_hex(&segments, format)?.into_bytes())
+            },
+
+            SRecord | S19 | S28 | S37       => {
+                let segments    = bitcode_to_segments(bitcode);
+                let format      = match self {
+                    S19     => RecordFormat::S19,
+                    S28     => RecordFormat::S28,
+                    S37     => RecordFormat::S37,
+                    _       => RecordFormat::srecord_for(&segments)
+                };
+
+                Ok(segments_to_srecords(&segments, format)?.into_bytes())
+            }
+        }
+    }
+}
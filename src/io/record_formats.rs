@@ -0,0 +1,348 @@
+use crate::exec::*;
+use crate::bitcode::*;
+
+use std::fmt::{Write};
+
+/// Number of data bytes to write in each record
+const BYTES_PER_RECORD: usize = 16;
+
+///
+/// The variants of the Intel HEX and Motorola S-record formats that can be generated
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordFormat {
+    /// Intel HEX with 16-bit addresses only (no extended address records)
+    I8Hex,
+
+    /// Intel HEX with extended linear address records (32-bit addresses)
+    I32Hex,
+
+    /// Motorola S-records with 16-bit addresses (S1/S9)
+    S19,
+
+    /// Motorola S-records with 24-bit addresses (S2/S8)
+    S28,
+
+    /// Motorola S-records with 32-bit addresses (S3/S7)
+    S37
+}
+
+impl RecordFormat {
+    ///
+    /// The largest address (exclusive) that can be represented in this format
+    ///
+    fn address_limit(&self) -> u64 {
+        match self {
+            RecordFormat::I8Hex     => 0x1_0000,
+            RecordFormat::I32Hex    => 0x1_0000_0000,
+            RecordFormat::S19       => 0x1_0000,
+            RecordFormat::S28       => 0x100_0000,
+            RecordFormat::S37       => 0x1_0000_0000
+        }
+    }
+
+    ///
+    /// Chooses the smallest Intel HEX format that can represent the specified segments
+    ///
+    pub fn intel_hex_for(segments: &Vec<(u64, Vec<u8>)>) -> RecordFormat {
+        if end_address(segments) <= RecordFormat::I8Hex.address_limit() {
+            RecordFormat::I8Hex
+        } else {
+            RecordFormat::I32Hex
+        }
+    }
+
+    ///
+    /// Chooses the smallest S-record format that can represent the specified segments
+    ///
+    pub fn srecord_for(segments: &Vec<(u64, Vec<u8>)>) -> RecordFormat {
+        let end_address = end_address(segments);
+
+        if end_address <= RecordFormat::S19.address_limit() {
+            RecordFormat::S19
+        } else if end_address <= RecordFormat::S28.address_limit() {
+            RecordFormat::S28
+        } else {
+            RecordFormat::S37
+        }
+    }
+}
+
+///
+/// Returns the address after the last byte in a set of segments
+///
+fn end_address(segments: &Vec<(u64, Vec<u8>)>) -> u64 {
+    segments.iter()
+        .map(|(address, data)| address + (data.len() as u64))
+        .max()
+        .unwrap_or(0)
+}
+
+///
+/// Converts a bitcode sequence into a list of contiguous segments of bytes, as (address, data) pairs
+///
+/// Unlike `bitcode_to_bytes`, areas that are skipped over by a `Move` and never written are left out
+/// of the result rather than being filled with zeros. Segments are returned in address order.
+///
+pub fn bitcode_to_segments(bitcode: &Vec<BitCode>) -> Vec<(u64, Vec<u8>)> {
+    // Work out which byte ranges are written to by the bitcode
+    let mut written     = vec![];
+    let mut bit_pos     = 0u64;
+
+    for code in bitcode.iter() {
+        let start_pos   = bit_pos;
+        bit_pos         = BitCode::position_after(bit_pos, vec![code]);
+
+        match code {
+            BitCode::Move(_)    => { },
+            _                   => {
+                if bit_pos > start_pos {
+                    written.push((start_pos/8, (bit_pos+7)/8));
+                }
+            }
+        }
+    }
+
+    // Merge the ranges that overlap or touch each other
+    written.sort();
+
+    let mut ranges: Vec<(u64, u64)> = vec![];
+    for (start, end) in written {
+        if let Some(last_range) = ranges.last_mut() {
+            if start <= last_range.1 {
+                last_range.1 = u64::max(last_range.1, end);
+                continue;
+            }
+        }
+
+        ranges.push((start, end));
+    }
+
+    // Read the data for each range from the assembled bytes
+    let bytes = bitcode_to_bytes(bitcode.iter().cloned());
+
+    ranges.into_iter()
+        .map(|(start, end)| (start, bytes[(start as usize)..(end as usize)].to_vec()))
+        .collect()
+}
+
+///
+/// Writes a single Intel HEX record
+///
+fn write_intel_hex_record(result: &mut String, address: u16, record_type: u8, data: &[u8]) {
+    let mut checksum = (data.len() as u8)
+        .wrapping_add((address >> 8) as u8)
+        .wrapping_add(address as u8)
+        .wrapping_add(record_type);
+
+    write!(result, ":{:02X}{:04X}{:02X}", data.len(), address, record_type).ok();
+    for byte in data.iter() {
+        write!(result, "{:02X}", byte).ok();
+        checksum = checksum.wrapping_add(*byte);
+    }
+
+    writeln!(result, "{:02X}", (!checksum).wrapping_add(1)).ok();
+}
+
+///
+/// Generates an Intel HEX file from a set of segments
+///
+pub fn segments_to_intel_hex(segments: &Vec<(u64, Vec<u8>)>, format: RecordFormat) -> Result<String, RuntimeError> {
+    // The whole file must fit into the address space of the format
+    let end_address = end_address(segments);
+    if end_address > format.address_limit() {
+        return Err(RuntimeError::AddressTooLarge(end_address-1));
+    }
+
+    let mut result          = String::new();
+    let mut upper_address   = 0u64;
+
+    for (address, data) in segments.iter() {
+        let mut pos = 0;
+
+        while pos < data.len() {
+            // Records cannot cross a 64k boundary, so cut them short when they reach one
+            let record_address  = address + (pos as u64);
+            let to_boundary     = 0x1_0000 - (record_address & 0xffff);
+            let record_len      = usize::min(BYTES_PER_RECORD, data.len() - pos);
+            let record_len      = usize::min(record_len, to_boundary as usize);
+
+            // Write an extended linear address record when the upper 16 bits of the address change
+            if (record_address >> 16) != upper_address {
+                upper_address = record_address >> 16;
+                write_intel_hex_record(&mut result, 0, 0x04, &[(upper_address >> 8) as u8, upper_address as u8]);
+            }
+
+            write_intel_hex_record(&mut result, record_address as u16, 0x00, &data[pos..(pos+record_len)]);
+            pos += record_len;
+        }
+    }
+
+    // End of file record
+    write_intel_hex_record(&mut result, 0, 0x01, &[]);
+
+    Ok(result)
+}
+
+///
+/// Writes a single Motorola S-record
+///
+fn write_srecord(result: &mut String, record_type: u8, address: u64, address_len: usize, data: &[u8]) {
+    let count       = (address_len + data.len() + 1) as u8;
+    let mut checksum = count;
+
+    write!(result, "S{}{:02X}", record_type, count).ok();
+    for byte_idx in (0..address_len).rev() {
+        let byte = (address >> (byte_idx*8)) as u8;
+        write!(result, "{:02X}", byte).ok();
+        checksum = checksum.wrapping_add(byte);
+    }
+
+    for byte in data.iter() {
+        write!(result, "{:02X}", byte).ok();
+        checksum = checksum.wrapping_add(*byte);
+    }
+
+    writeln!(result, "{:02X}", !checksum).ok();
+}
+
+///
+/// Generates a Motorola S-record file from a set of segments
+///
+pub fn segments_to_srecords(segments: &Vec<(u64, Vec<u8>)>, format: RecordFormat) -> Result<String, RuntimeError> {
+    // The whole file must fit into the address space of the format
+    let end_address = end_address(segments);
+    if end_address > format.address_limit() {
+        return Err(RuntimeError::AddressTooLarge(end_address-1));
+    }
+
+    // The data and termination record types depend on the size of the addresses
+    let (data_type, end_type, address_len) = match format {
+        RecordFormat::S28   => (2, 8, 3),
+        RecordFormat::S37   => (3, 7, 4),
+        _                   => (1, 9, 2)
+    };
+
+    // Header record
+    let mut result      = String::new();
+    let mut num_records = 0usize;
+    write_srecord(&mut result, 0, 0, 2, env!("CARGO_PKG_NAME").as_bytes());
+
+    // Data records
+    for (address, data) in segments.iter() {
+        for (record_idx, record) in data.chunks(BYTES_PER_RECORD).enumerate() {
+            let record_address = address + ((record_idx*BYTES_PER_RECORD) as u64);
+
+            write_srecord(&mut result, data_type, record_address, address_len, record);
+            num_records += 1;
+        }
+    }
+
+    // Record count (S5 for 16-bit counts, S6 for 24-bit counts)
+    if num_records <= 0xffff {
+        write_srecord(&mut result, 5, num_records as u64, 2, &[]);
+    } else if num_records <= 0xff_ffff {
+        write_srecord(&mut result, 6, num_records as u64, 3, &[]);
+    }
+
+    // Termination record
+    write_srecord(&mut result, end_type, 0, address_len, &[]);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn segments_from_contiguous_bitcode() {
+        let segments = bitcode_to_segments(&vec![BitCode::Bits(8, 1), BitCode::Bits(8, 2), BitCode::Bits(8, 3)]);
+        assert!(segments == vec![(0, vec![1, 2, 3])]);
+    }
+
+    #[test]
+    fn segments_leave_gaps() {
+        let segments = bitcode_to_segments(&vec![BitCode::Bits(8, 1), BitCode::Move(0x100*8), BitCode::Bits(8, 2), BitCode::Bits(8, 3)]);
+        assert!(segments == vec![(0, vec![1]), (0x100, vec![2, 3])]);
+    }
+
+    #[test]
+    fn segments_are_sorted_and_merged() {
+        let segments = bitcode_to_segments(&vec![BitCode::Move(0x10*8), BitCode::Bits(8, 3), BitCode::Move(0x0e*8), BitCode::Bits(16, 0x0201)]);
+        assert!(segments == vec![(0x0e, vec![1, 2, 3])]);
+    }
+
+    #[test]
+    fn segments_include_alignment() {
+        let segments = bitcode_to_segments(&vec![BitCode::Bits(8, 1), BitCode::Align(8, 0xea, 32)]);
+        assert!(segments == vec![(0, vec![1, 0xea, 0xea, 0xea])]);
+    }
+
+    #[test]
+    fn intel_hex_data_record() {
+        let hex = segments_to_intel_hex(&vec![(0x0100, vec![0x21, 0x46, 0x01, 0x36, 0x01, 0x21, 0x47, 0x01, 0x36, 0x00, 0x7E, 0xFE, 0x09, 0xD2, 0x19, 0x01])], RecordFormat::I8Hex).unwrap();
+        assert!(hex == ":10010000214601360121470136007EFE09D2190140\n:00000001FF\n");
+    }
+
+    #[test]
+    fn intel_hex_splits_records() {
+        let hex = segments_to_intel_hex(&vec![(0, vec![0; 20])], RecordFormat::I8Hex).unwrap();
+        assert!(hex == ":1000000000000000000000000000000000000000F0\n:0400100000000000EC\n:00000001FF\n");
+    }
+
+    #[test]
+    fn intel_hex_extended_address() {
+        let hex = segments_to_intel_hex(&vec![(0x1fffe, vec![1, 2, 3, 4])], RecordFormat::I32Hex).unwrap();
+        assert!(hex == ":020000040001F9\n:02FFFE000102FE\n:020000040002F8\n:020000000304F7\n:00000001FF\n");
+    }
+
+    #[test]
+    fn i8hex_address_too_large() {
+        let hex = segments_to_intel_hex(&vec![(0x1fffe, vec![1, 2, 3, 4])], RecordFormat::I8Hex);
+        assert!(matches!(hex, Err(RuntimeError::AddressTooLarge(0x20001))));
+    }
+
+    #[test]
+    fn choose_intel_hex_format() {
+        assert!(RecordFormat::intel_hex_for(&vec![(0xfffe, vec![1, 2])]) == RecordFormat::I8Hex);
+        assert!(RecordFormat::intel_hex_for(&vec![(0xfffe, vec![1, 2, 3])]) == RecordFormat::I32Hex);
+    }
+
+    #[test]
+    fn srecord_s19() {
+        let srec = segments_to_srecords(&vec![(0x0038, vec![0x48, 0x65, 0x6C, 0x6C, 0x6F])], RecordFormat::S19).unwrap();
+        let lines = srec.lines().collect::<Vec<_>>();
+
+        assert!(lines.len() == 4);
+        assert!(lines[0].starts_with("S0"));
+        assert!(lines[1] == "S108003848656C6C6FCB");
+        assert!(lines[2] == "S5030001FB");
+        assert!(lines[3] == "S9030000FC");
+    }
+
+    #[test]
+    fn srecord_s28() {
+        let srec = segments_to_srecords(&vec![(0x012345, vec![0xaa])], RecordFormat::S28).unwrap();
+        let lines = srec.lines().collect::<Vec<_>>();
+
+        assert!(lines[1] == "S205012345AAE7");
+        assert!(lines[3] == "S804000000FB");
+    }
+
+    #[test]
+    fn srecord_s37() {
+        let srec = segments_to_srecords(&vec![(0x01234567, vec![0xaa])], RecordFormat::S37).unwrap();
+        let lines = srec.lines().collect::<Vec<_>>();
+
+        assert!(lines[1] == "S30601234567AA7F");
+        assert!(lines[3] == "S70500000000FA");
+    }
+
+    #[test]
+    fn choose_srecord_format() {
+        assert!(RecordFormat::srecord_for(&vec![(0xff00, vec![1])]) == RecordFormat::S19);
+        assert!(RecordFormat::srecord_for(&vec![(0x10000, vec![1])]) == RecordFormat::S28);
+        assert!(RecordFormat::srecord_for(&vec![(0x1000000, vec![1])]) == RecordFormat::S37);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::bitcode::*;
+
+///
+/// The formats that the assembler can write symbol files in
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolFileFormat {
+    /// A list of `name = $addr` lines
+    Plain,
+
+    /// VICE monitor labels (`al C:xxxx .name`), which can be loaded with the `ll` command
+    Vice,
+
+    /// FCEUX `.nl` name lists (`$xxxx#name#`), which can also be imported by Mesen
+    NameList
+}
+
+/// The names of the symbol file formats, as they can be specified on the command line
+pub const SYMBOL_FILE_FORMAT_NAMES: [&str; 3] = ["plain", "vice", "nl"];
+
+impl SymbolFileFormat {
+    ///
+    /// Retrieves the symbol file format with the specified name
+    ///
+    pub fn from_name(name: &str) -> Option<SymbolFileFormat> {
+        match name {
+            "plain"     => Some(SymbolFileFormat::Plain),
+            "vice"      => Some(SymbolFileFormat::Vice),
+            "nl"        => Some(SymbolFileFormat::NameList),
+            _           => None
+        }
+    }
+
+    ///
+    /// Writes out the symbols for a set of label values in this format
+    ///
+    pub fn write_symbols(&self, label_values: &LabelValues) -> String {
+        use self::SymbolFileFormat::*;
+
+        let mut result = String::new();
+
+        for symbol in label_values.symbols() {
+            let name = symbol.qualified_name();
+
+            let line = match self {
+                Plain       => format!("{} = ${:04X}\n", name, symbol.value),
+                Vice        => format!("al C:{:04x} .{}\n", symbol.value, name),
+                NameList    => format!("${:04X}#{}#\n", symbol.value, name)
+            };
+
+            result.push_str(&line);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::*;
+
+    fn symbols_in_format(expr: &str, format: SymbolFileFormat) -> String {
+        let assembly = assembly_for(expr).unwrap();
+
+        format.write_symbols(&assembly.labels)
+    }
+
+    #[test]
+    fn format_names() {
+        for name in SYMBOL_FILE_FORMAT_NAMES.iter() {
+            assert!(SymbolFileFormat::from_name(name).is_some());
+        }
+
+        assert!(SymbolFileFormat::from_name("nonsense").is_none());
+    }
+
+    #[test]
+    fn plain_symbols() {
+        let symbols = symbols_in_format("(set_bit_pos $8000u64) (label start) (d $42u8) (label next)", SymbolFileFormat::Plain);
+
+        assert!(symbols == "start = $8000\nnext = $8008\n");
+    }
+
+    #[test]
+    fn vice_symbols() {
+        let symbols = symbols_in_format("(def label_value (fun (pos) (/ pos 8))) (set_bit_pos $8010u64) (label start) (d $42u8) (label next)", SymbolFileFormat::Vice);
+
+        assert!(symbols == "al C:1002 .start\nal C:1003 .next\n");
+    }
+
+    #[test]
+    fn name_list_symbols() {
+        let symbols = symbols_in_format("(def label_value (fun (pos) (/ pos 8))) (set_bit_pos $60000u64) (label start)", SymbolFileFormat::NameList);
+
+        assert!(symbols == "$C000#start#\n");
+    }
+}
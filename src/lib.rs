@@ -0,0 +1,40 @@
+//!
+//! # SAFAS - the Self-Aware Functional Assembler
+//!
+//! This crate can be used to embed the assembler in another Rust program. A `Session` holds the bindings and the
+//! execution frame for a set of SAFAS statements: code can be evaluated in it, imported from files or in-memory sources
+//! and assembled to bytes along with the values of the labels it declares.
+//!
+//! ```
+//! # use safas::*;
+//! let mut session = Session::new().unwrap();
+//! let assembly    = session.assemble("(d $12u8 $34u8)").unwrap();
+//!
+//! assert!(assembly.bytes() == vec![0x12, 0x34]);
+//! ```
+//!
+//! Functions written in Rust can be added to a session using `define_function` with any `FrameMonad` (`FnMonad` is the
+//! easiest way to create these).
+//!
+
+#[macro_use] extern crate lazy_static;
+
+pub mod io;
+pub mod meta;
+pub mod bind;
+pub mod exec;
+pub mod parse;
+pub mod syntax;
+pub mod bitcode;
+pub mod functions;
+pub mod emulator;
+pub mod interactive;
+mod session;
+
+#[cfg(test)] mod test_support;
+
+pub use self::session::*;
+pub use self::interactive::{eval};
+pub use self::meta::{CellRef, SafasCell, SafasNumber, CellValue, NIL};
+pub use self::exec::{RuntimeError, RuntimeResult, FrameMonad, FnMonad, ReturnsMonad, error_report};
+pub use self::bitcode::{BitCode, BitCodeMonad, LabelValues, LabelSymbol, Listing, Diagnostic, DiagnosticLevel, bitcode_to_bytes};
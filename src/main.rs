@@ -1,25 +1,12 @@
-#[macro_use] extern crate lazy_static;
-
-mod io;
-mod meta;
-mod bind;
-mod exec;
-mod parse;
-mod syntax;
-mod bitcode;
-mod functions;
-mod interactive;
-
-use crate::io::*;
-use crate::bind::*;
-use crate::meta::*;
-use crate::exec::*;
-use crate::bitcode::*;
-use crate::interactive::*;
+use safas::io::*;
+use safas::exec::*;
+use safas::bitcode::*;
+use safas::*;
 
 use clap::{App, Arg};
 use std::io::{Write};
 use std::fs::{File};
+use std::env;
 use std::process::{exit};
 
 fn main() {
@@ -47,79 +34,151 @@ fn main() {
             .long("output")
             .help("Sets the location to write the output to")
             .value_name("OUTPUT"))
+        .arg(Arg::with_name("format")
+            .short("f")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&OUTPUT_FORMAT_NAMES)
+            .help("Sets the format of the output (binary is the default when writing to a file, hexdump when writing to the console)")
+            .value_name("FORMAT"))
+        .arg(Arg::with_name("listing")
+            .short("l")
+            .long("listing")
+            .takes_value(true)
+            .help("Writes a listing showing the address and bytes generated by each source statement")
+            .value_name("LISTING"))
+        .arg(Arg::with_name("symbols")
+            .short("s")
+            .long("symbols")
+            .takes_value(true)
+            .help("Writes a symbol file containing the values of the labels")
+            .value_name("SYMBOLS"))
+        .arg(Arg::with_name("symbol-format")
+            .long("symbol-format")
+            .takes_value(true)
+            .possible_values(&SYMBOL_FILE_FORMAT_NAMES)
+            .help("Sets the format of the symbol file (plain is the default)")
+            .value_name("FORMAT"))
         .arg(Arg::with_name("import-path")
             .short("I")
             .long("import-path")
             .takes_value(true)
             .multiple(true)
             .number_of_values(1)
-            .help("Adds another path to search for imported files"))
+            .help("Adds another path to search for imported files (paths can also be set in the SAFAS_PATH environment variable)"))
+        .arg(Arg::with_name("define")
+            .short("D")
+            .long("define")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Defines a symbol before the input file is imported (the value is =t if it's not specified)")
+            .value_name("NAME=VALUE"))
         .arg(Arg::with_name("no-default-library")
             .long("no-default-library")
             .help("Do not load the default set of library functions (only the built-in functions will be provided)"))
+        .arg(Arg::with_name("max-passes")
+            .long("max-passes")
+            .takes_value(true)
+            .help("Sets the maximum number of passes the assembler will make while waiting for the labels to stabilise")
+            .value_name("PASSES"))
+        .arg(Arg::with_name("max-output-size")
+            .long("max-output-size")
+            .takes_value(true)
+            .help("Sets the maximum size of the assembled output, in bytes")
+            .value_name("BYTES"))
+        .arg(Arg::with_name("max-recursion-depth")
+            .long("max-recursion-depth")
+            .takes_value(true)
+            .help("Sets the maximum number of function calls that can be in progress at once")
+            .value_name("DEPTH"))
+        .arg(Arg::with_name("max-macro-depth")
+            .long("max-macro-depth")
+            .takes_value(true)
+            .help("Sets the maximum number of macro expansions that can be nested inside each other")
+            .value_name("DEPTH"))
         .get_matches();
 
-    // Create the initial execution frame and bindings
-    let frame               = Frame::new(1, None);
-    let bindings            = SymbolBindings::new();
+    // Set up the limits
+    let mut limits  = Limits::default();
+    let limit       = |name: &str, default: usize| -> usize {
+        match params.value_of(name).map(|value| value.parse::<usize>()) {
+            None            => default,
+            Some(Ok(value)) => value,
+            Some(Err(_))    => {
+                eprintln!("!! --{} should be a number", name);
+                exit(1);
+            }
+        }
+    };
+
+    limits.max_passes           = limit("max-passes", limits.max_passes);
+    limits.max_output_size      = limit("max-output-size", limits.max_output_size as usize) as u64;
+    limits.max_recursion_depth  = limit("max-recursion-depth", limits.max_recursion_depth);
+    limits.max_macro_depth      = limit("max-macro-depth", limits.max_macro_depth);
 
-    // Apply the standard bindings
-    let (mut frame, mut bindings)   = setup_standard_bindings(frame, bindings);
-    let mut output                  = NIL.clone();
+    // Create a session with the standard bindings
+    let mut session = Session::without_default_library();
+    let mut output  = NIL.clone();
 
-    // Add any extra import paths
-    if let Some(import_paths) = params.values_of("import-path") {
-        // These get added to the import path
-        let import_atom = get_id_for_atom_with_name("import_path");
+    session.set_limits(limits);
 
-        for path in import_paths.rev() {
-            // Get the current path
-            let import_path = bindings.look_up(import_atom).map(|(cell, _depth)| cell).unwrap_or(NIL.clone());
+    // Add any extra import paths (paths from the command line are searched before the ones from the environment)
+    if let Some(environment_paths) = env::var_os("SAFAS_PATH") {
+        let environment_paths = env::split_paths(&environment_paths).collect::<Vec<_>>();
 
-            // Add this path to the beginning
-            let import_path = SafasCell::List(SafasCell::String(path.to_string()).into(), import_path);
+        for path in environment_paths.iter().rev() {
+            session.add_import_path(&path.to_string_lossy());
+        }
+    }
 
-            // Update the binding
-            bindings.symbols.insert(import_atom, import_path.into());
+    if let Some(import_paths) = params.values_of("import-path") {
+        for path in import_paths.rev() {
+            session.add_import_path(path);
         }
     }
 
     // Load the default library
     if params.occurrences_of("no-default-library") == 0 {
-        let (import_result, new_bindings, new_frame) = import_file("standard/default.sf", bindings, frame, false);
-
-        if let SafasCell::Error(err) = &*import_result {
-            println!("!! Failed to load default library");
-            println!("!! {:?}", err);
-            println!();
+        if let Err(err) = session.load_default_library() {
+            eprintln!("!! Failed to load default library");
+            eprint!("{}", error_report(&err));
             exit(1);
         }
-
-        bindings    = new_bindings;
-        frame       = new_frame;
     }
 
-    // Import any input files into the frame
-    if let Some(input_file) = params.value_of("INPUT") {
-        let (import_result, new_bindings, new_frame) = import_file(input_file, bindings, frame, true);
-
-        // Report any errors
-        if let SafasCell::Error(err) = &*import_result {
-            println!("!! {:?}", err);
-            println!();
-            exit(1);
+    // Define any symbols from the command line
+    if let Some(definitions) = params.values_of("define") {
+        for definition in definitions {
+            let mut parts   = definition.splitn(2, '=');
+            let name        = parts.next().unwrap_or("");
+            let result      = match parts.next() {
+                Some(value) => session.define_symbol_from_source(name, value),
+                None        => session.define_symbol(name, SafasCell::Boolean(true).into())
+            };
+
+            if let Err(err) = result {
+                eprintln!("!! Could not define {}", name);
+                eprint!("{}", error_report(&err));
+                exit(1);
+            }
         }
+    }
 
-        // Update the syntax
-        bindings    = new_bindings;
-        frame       = new_frame;
-
-        output      = import_result;
+    // Import any input files into the session
+    if let Some(input_file) = params.value_of("INPUT") {
+        output = match session.import_file(input_file) {
+            Ok(result)  => result,
+            Err(err)    => {
+                eprint!("{}", error_report(&err));
+                exit(1);
+            }
+        };
     }
 
     // Start in interactive mode if the -i parameter is passed in
     if params.occurrences_of("interactive") > 0 {
-        run_interactive(frame, bindings);
+        session.run_interactive();
         return;
     }
 
@@ -129,30 +188,58 @@ fn main() {
         println!("{}", params.usage());
     }
 
-    if let Some(output) = BitCodeMonad::from_cell(&output) {
+    if BitCodeMonad::from_cell(&output).is_some() {
         // Assemble the result
-        let assemble_result = assemble(&output);
-        let (val, bitcode)  = match assemble_result { 
+        let assemble_result         = session.assemble_value(&output);
+
+        // Display any messages generated during assembly
+        for diagnostic in session.take_diagnostics().iter() {
+            eprint!("{}", diagnostic.report());
+        }
+
+        let Assembly { value: val, bitcode, labels, listing } = match assemble_result { 
             Ok(result)  => result, 
             Err(err)    => {
-                println!("!! {:?}", err);
-                println!();
+                eprint!("{}", error_report(&err));
                 exit(1);
             }
         };
 
+        // Write the listing if one was requested
+        if let Some(listing_file) = params.value_of("listing") {
+            let mut listing_file = File::create(listing_file).unwrap();
+            listing_file.write_all(listing.to_string().as_bytes()).unwrap();
+        }
+
+        // Write the symbols if they were requested
+        if let Some(symbols_file) = params.value_of("symbols") {
+            let format              = params.value_of("symbol-format").and_then(SymbolFileFormat::from_name).unwrap_or(SymbolFileFormat::Plain);
+            let mut symbols_file    = File::create(symbols_file).unwrap();
+            symbols_file.write_all(format.write_symbols(&labels).as_bytes()).unwrap();
+        }
+
         // Generate the output
         if !val.is_nil() {
             println!("{}", val.to_string());
         }
 
-        let bytes = bitcode_to_bytes(bitcode);
+        // Default format is binary for files and a hexdump for the console
+        let default_format  = if params.value_of("output").is_some() { OutputFormat::Binary } else { OutputFormat::HexDump };
+        let format          = params.value_of("format").and_then(OutputFormat::from_name).unwrap_or(default_format);
+
+        let bytes           = match format.write_bitcode(&bitcode) {
+            Ok(bytes)   => bytes,
+            Err(err)    => {
+                eprint!("{}", error_report(&err));
+                exit(1);
+            }
+        };
 
         if let Some(output_file) = params.value_of("output") {
             let mut output_file = File::create(output_file).unwrap();
             output_file.write_all(&bytes).unwrap();
         } else {
-            println!("{}", hexdump(&bytes));
+            std::io::stdout().write_all(&bytes).unwrap();
         }
     } else {
         println!("{}", output.to_string());
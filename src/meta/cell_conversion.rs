@@ -91,6 +91,16 @@ impl TryFrom<CellRef> for CellValue<String> {
     }
 }
 
+impl TryFrom<CellRef> for CellValue<char> {
+    type Error=RuntimeError;
+    fn try_from(cell: CellRef) -> Result<Self, RuntimeError> {
+        match &*cell {
+            SafasCell::Char(chr_value)  => Ok(CellValue(*chr_value)),
+            _                           => Err(RuntimeError::NotAChar(cell))
+        }
+    }
+}
+
 ///
 /// Represents an atom ID, used for conversions
 ///
@@ -1,7 +1,7 @@
 use radix_fmt::*;
 
 use std::cmp::{Ordering};
-use std::ops::{Add, Sub, Mul, Div};
+use std::ops::{Add, Sub, Mul, Div, Rem, BitAnd, BitOr, BitXor, Not, Shl, Shr};
 
 ///
 /// How SAFAS represents a number
@@ -103,6 +103,25 @@ impl SafasNumber {
         }
     }
 
+    ///
+    /// Wraps this number so its value fits into its width (signed numbers are sign-extended from their top bit)
+    ///
+    pub fn wrap_to_width(self) -> SafasNumber {
+        use self::SafasNumber::*;
+
+        match self {
+            Plain(_)                                        => self,
+            BitNumber(bits, _) | SignedBitNumber(bits, _)   if bits >= 128 => self,
+
+            BitNumber(bits, val)                            => BitNumber(bits, val & ((1u128<<bits)-1)),
+            SignedBitNumber(0, _)                           => SignedBitNumber(0, 0),
+            SignedBitNumber(bits, val)                      => {
+                let shift = 128 - bits as u32;
+                SignedBitNumber(bits, (val << shift) >> shift)
+            }
+        }
+    }
+
     ///
     /// Returns this number as a usize
     ///
@@ -115,6 +134,31 @@ impl SafasNumber {
             SignedBitNumber(_bits, num) => *num as usize
         }
     }
+
+    ///
+    /// Shifts this number right, treating it as a signed value of its width (so the sign bit is copied into the bits that are shifted in)
+    ///
+    pub fn arithmetic_shr(self, amount: u32) -> SafasNumber {
+        use self::SafasNumber::*;
+
+        let amount = u32::min(amount, 127);
+
+        match self {
+            Plain(val)                  => Plain(((val as i128) >> amount) as u128),
+            SignedBitNumber(bits, val)  => SignedBitNumber(bits, val >> amount),
+            BitNumber(bits, val)        => {
+                if bits == 0 || bits >= 128 {
+                    BitNumber(bits, ((val as i128) >> amount) as u128)
+                } else {
+                    // Sign-extend from the width of the number, shift, then mask back to the width again
+                    let mask    = (1u128<<bits)-1;
+                    let val     = if val & (1u128<<(bits-1)) != 0 { val | !mask } else { val & mask };
+
+                    BitNumber(bits, (((val as i128) >> amount) as u128) & mask)
+                }
+            }
+        }
+    }
 }
 
 impl Add for SafasNumber {
@@ -181,6 +225,115 @@ impl Div for SafasNumber {
     }
 }
 
+impl Rem for SafasNumber {
+    type Output = SafasNumber;
+
+    fn rem(self, to: SafasNumber) -> SafasNumber {
+        use self::SafasNumber::*;
+
+        let (a, b) = self.coerce(to);
+
+        match a {
+            SignedBitNumber(bits, val)  => SignedBitNumber(u8::max(bits, b.bits()), val.rem_euclid(b.to_i128())),
+            BitNumber(bits, val)        => BitNumber(u8::max(bits, b.bits()), val % b.to_u128()),
+            Plain(val)                  => Plain(val % b.to_u128())
+        }
+    }
+}
+
+impl BitAnd for SafasNumber {
+    type Output = SafasNumber;
+
+    fn bitand(self, to: SafasNumber) -> SafasNumber {
+        use self::SafasNumber::*;
+
+        let (a, b) = self.coerce(to);
+
+        match a {
+            SignedBitNumber(bits, val)  => SignedBitNumber(u8::max(bits, b.bits()), val & b.to_i128()).wrap_to_width(),
+            BitNumber(bits, val)        => BitNumber(u8::max(bits, b.bits()), val & b.to_u128()),
+            Plain(val)                  => Plain(val & b.to_u128())
+        }
+    }
+}
+
+impl BitOr for SafasNumber {
+    type Output = SafasNumber;
+
+    fn bitor(self, to: SafasNumber) -> SafasNumber {
+        use self::SafasNumber::*;
+
+        let (a, b) = self.coerce(to);
+
+        match a {
+            SignedBitNumber(bits, val)  => SignedBitNumber(u8::max(bits, b.bits()), val | b.to_i128()).wrap_to_width(),
+            BitNumber(bits, val)        => BitNumber(u8::max(bits, b.bits()), val | b.to_u128()),
+            Plain(val)                  => Plain(val | b.to_u128())
+        }
+    }
+}
+
+impl BitXor for SafasNumber {
+    type Output = SafasNumber;
+
+    fn bitxor(self, to: SafasNumber) -> SafasNumber {
+        use self::SafasNumber::*;
+
+        let (a, b) = self.coerce(to);
+
+        match a {
+            SignedBitNumber(bits, val)  => SignedBitNumber(u8::max(bits, b.bits()), val ^ b.to_i128()).wrap_to_width(),
+            BitNumber(bits, val)        => BitNumber(u8::max(bits, b.bits()), val ^ b.to_u128()),
+            Plain(val)                  => Plain(val ^ b.to_u128())
+        }
+    }
+}
+
+impl Not for SafasNumber {
+    type Output = SafasNumber;
+
+    fn not(self) -> SafasNumber {
+        use self::SafasNumber::*;
+
+        match self {
+            SignedBitNumber(bits, val)  => SignedBitNumber(bits, !val),
+            BitNumber(bits, val)        => {
+                let mask = if bits >= 128 { u128::max_value() } else { (1u128<<bits)-1 };
+                BitNumber(bits, !val & mask)
+            },
+            Plain(val)                  => Plain(!val)
+        }
+    }
+}
+
+impl Shl<u32> for SafasNumber {
+    type Output = SafasNumber;
+
+    fn shl(self, amount: u32) -> SafasNumber {
+        use self::SafasNumber::*;
+
+        match self {
+            SignedBitNumber(bits, val)  => SignedBitNumber(bits, val.checked_shl(amount).unwrap_or(0)).wrap_to_width(),
+            BitNumber(bits, val)        => BitNumber(bits, val.checked_shl(amount).unwrap_or(0)).wrap_to_width(),
+            Plain(val)                  => Plain(val.checked_shl(amount).unwrap_or(0))
+        }
+    }
+}
+
+impl Shr<u32> for SafasNumber {
+    type Output = SafasNumber;
+
+    fn shr(self, amount: u32) -> SafasNumber {
+        use self::SafasNumber::*;
+
+        match self {
+            SignedBitNumber(_, _)       => self.arithmetic_shr(amount),
+            BitNumber(bits, val)        => BitNumber(bits, val.checked_shr(amount).unwrap_or(0)),
+            Plain(val)                  => Plain(val.checked_shr(amount).unwrap_or(0))
+        }
+    }
+}
+
 impl PartialOrd for SafasNumber {
     fn partial_cmp(&self, rhs: &SafasNumber) -> Option<Ordering> {
         use self::SafasNumber::*;
@@ -214,4 +367,16 @@ mod test {
 
         assert!(division == SafasNumber::SignedBitNumber(4, 2));
     }
+
+    #[test]
+    fn arithmetic_shift_right_uses_width() {
+        assert!(SafasNumber::BitNumber(8, 0x80).arithmetic_shr(2) == SafasNumber::BitNumber(8, 0xe0));
+        assert!(SafasNumber::BitNumber(8, 0x40).arithmetic_shr(2) == SafasNumber::BitNumber(8, 0x10));
+    }
+
+    #[test]
+    fn not_uses_width() {
+        assert!(!SafasNumber::BitNumber(8, 0x0f) == SafasNumber::BitNumber(8, 0xf0));
+        assert!(!SafasNumber::SignedBitNumber(8, 0) == SafasNumber::SignedBitNumber(8, -1));
+    }
 }
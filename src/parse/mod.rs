@@ -3,9 +3,11 @@ mod error;
 mod tokenizer;
 mod file_location;
 mod read_buffer;
+mod source_location;
 
 pub use self::parser::*;
 pub use self::error::*;
 pub use self::tokenizer::*;
 pub use self::file_location::*;
 pub use self::read_buffer::*;
+pub use self::source_location::*;
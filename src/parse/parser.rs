@@ -2,6 +2,7 @@ use super::error::*;
 use super::tokenizer::*;
 use super::read_buffer::*;
 use super::file_location::*;
+use super::source_location::*;
 
 use crate::meta::*;
 
@@ -12,14 +13,22 @@ use std::result::{Result};
 /// Parses a file in SAFAS format and returns the resulting cell
 ///
 pub fn parse_safas<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<Chars>, location: FileLocation) -> Result<CellRef, ParseError> {
+    parse_safas_with_locations(code, location).map(|(cell, _locations)| cell)
+}
+
+///
+/// Parses a file in SAFAS format and returns the resulting cell, along with the locations that the lists in it were read from
+///
+pub fn parse_safas_with_locations<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<Chars>, location: FileLocation) -> Result<(CellRef, SourceLocations), ParseError> {
     // Initial location
     let mut location    = location;
+    let locations       = SourceLocations::new();
 
     // Results, stored as a 
     let mut results     = vec![];
 
     loop {
-        let (next_cell, next_location) = parse_cell(code, location)?;
+        let (next_cell, next_location) = parse_cell(code, location, &locations)?;
         location = next_location;
 
         if let Some(next_cell) = next_cell {
@@ -32,24 +41,24 @@ pub fn parse_safas<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<Chars>
 
     }
 
-    Ok(SafasCell::list_with_cells(results))
+    Ok((SafasCell::list_with_cells(results), locations))
 }
 
 ///
 /// Parses the next cell on the token stream (returning None if there is no following cell)
 ///
-fn parse_cell<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<Chars>, location: FileLocation) -> Result<(Option<CellRef>, FileLocation), ParseError> {
+fn parse_cell<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<Chars>, location: FileLocation, locations: &SourceLocations) -> Result<(Option<CellRef>, FileLocation), ParseError> {
     // Skip whitespace and comments to find the first meaningful token
     let original_location               = location.clone();
     let (token, token_text, location)   = tokenize_no_comments(code, location);
 
-    parse_cell_from_token(code, original_location, token, token_text, location)
+    parse_cell_from_token(code, original_location, token, token_text, location, locations)
 }
 
 ///
 /// Parses the next cell on the token stream, with a token read from the stream (returning None if there is no following cell)
 ///
-fn parse_cell_from_token<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<Chars>, original_location: FileLocation, token: Token, token_text: String, location: FileLocation) -> Result<(Option<CellRef>, FileLocation), ParseError> {
+fn parse_cell_from_token<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<Chars>, original_location: FileLocation, token: Token, token_text: String, location: FileLocation, locations: &SourceLocations) -> Result<(Option<CellRef>, FileLocation), ParseError> {
     // Action depends on the token
     match token {
         Token::Whitespace | Token::Comment => { Err(ParseError::InternalError(original_location, "Whitespace should not make it through to this point".to_string())) },
@@ -78,6 +87,9 @@ fn parse_cell_from_token<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<
             let mut list        = vec![];
             let mut location    = location;
 
+            // The list starts at the opening parenthesis (the location we're passed is just after it)
+            let list_location   = FileLocation { chr_idx: location.chr_idx-1, file_idx: location.file_idx-1, ..location.clone() };
+
             loop {
                 // Read the next token
                 let start_location                          = location.clone();
@@ -91,7 +103,7 @@ fn parse_cell_from_token<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<
 
                     other_token         => {
                         // Other symbols indicate a different cell of some kind
-                        let (cell, next_location) = parse_cell_from_token(code, start_location, other_token, next_text, location)?;
+                        let (cell, next_location) = parse_cell_from_token(code, start_location, other_token, next_text, location, locations)?;
                         location = next_location;
 
                         if let Some(cell) = cell {
@@ -105,7 +117,14 @@ fn parse_cell_from_token<Chars: Iterator<Item=char>>(code: &mut TokenReadBuffer<
                 }
             }
 
-            Ok((Some(SafasCell::list_with_cells(list)), location))
+            // Remember where the list was read from (empty lists are all the same nil cell, so have no location)
+            let list            = SafasCell::list_with_cells(list);
+            if !list.is_nil() {
+                let list_text   = code.source_text(list_location.file_idx, location.file_idx);
+                locations.set_location(&list, SourceLocation::new(list_location, &list_text));
+            }
+
+            Ok((Some(list), location))
         }
     }
 }
@@ -394,6 +413,24 @@ mod test {
         assert!(parse_result == "((1 (2 3) 4))".to_string());
     }
 
+    #[test]
+    fn parse_list_location() {
+        let mut buf         = TokenReadBuffer::new("(1 2)\n  (3\n 4)".chars());
+        let (parse_result, locations)   = parse_safas_with_locations(&mut buf, FileLocation::new("test")).unwrap();
+        let lists                       = parse_result.to_vec().unwrap();
+
+        let first                       = locations.location(&lists[0]).unwrap();
+        let second                      = locations.location(&lists[1]).unwrap();
+
+        assert!(first.location.line == 1);
+        assert!(first.location.chr_idx == 1);
+        assert!(first.text == "(1 2)".to_string());
+
+        assert!(second.location.line == 2);
+        assert!(second.location.chr_idx == 3);
+        assert!(second.text == "(3".to_string());
+    }
+
     #[test]
     fn parse_bool_1() {
         let mut buf         = TokenReadBuffer::new("=t".chars());
@@ -13,7 +13,10 @@ pub struct TokenReadBuffer<Chars: Iterator<Item=char>> {
     pushed_back: VecDeque<char>,
 
     /// Characters that have been read from this buffer
-    read: VecDeque<char>
+    read: VecDeque<char>,
+
+    /// Every character that has been taken from the source so far (used to retrieve the source text for a range of the file)
+    source: Vec<char>
 }
 
 impl<Chars: Iterator<Item=char>> TokenReadBuffer<Chars> {
@@ -24,7 +27,8 @@ impl<Chars: Iterator<Item=char>> TokenReadBuffer<Chars> {
         TokenReadBuffer {
             chars:          read_from,
             pushed_back:    VecDeque::new(),
-            read:           VecDeque::new()
+            read:           VecDeque::new(),
+            source:         vec![]
         }
     }
 
@@ -33,7 +37,17 @@ impl<Chars: Iterator<Item=char>> TokenReadBuffer<Chars> {
     ///
     pub fn read_next(&mut self) -> Option<char> {
         // Read the next character
-        let next_chr = self.pushed_back.pop_back().or_else(|| self.chars.next());
+        let next_chr = match self.pushed_back.pop_back() {
+            Some(pushed_back)   => Some(pushed_back),
+            None                => {
+                // Characters from the source are remembered so that source_text can retrieve them later on
+                let next_chr = self.chars.next();
+                if let Some(next_chr) = next_chr {
+                    self.source.push(next_chr);
+                }
+                next_chr
+            }
+        };
 
         // Add to the pending 'read characters' buffer
         if let Some(next_chr) = next_chr {
@@ -66,6 +80,16 @@ impl<Chars: Iterator<Item=char>> TokenReadBuffer<Chars> {
     pub fn read_characters(&self) -> String {
         self.read.iter().map(|chr| *chr).collect()
     }
+
+    ///
+    /// Retrieves the source text between two file indexes (as used by `FileLocation::file_idx`), which must have already been read
+    ///
+    pub fn source_text(&self, start_idx: u32, end_idx: u32) -> String {
+        let end_idx     = usize::min(end_idx as usize, self.source.len());
+        let start_idx   = usize::min(start_idx as usize, end_idx);
+
+        self.source[start_idx..end_idx].iter().collect()
+    }
 }
 
 #[cfg(test)]
@@ -130,4 +154,19 @@ mod test {
         buf.read_next();
         assert!(buf.read_characters() == String::from("tes"));
     }
+
+    #[test]
+    fn read_source_text() {
+        let mut buf = TokenReadBuffer::new("test".chars());
+
+        buf.read_next();
+        buf.read_next();
+        buf.push_back();
+        buf.read_next();
+        buf.read_next();
+
+        assert!(buf.source_text(0, 3) == String::from("tes"));
+        assert!(buf.source_text(1, 3) == String::from("es"));
+        assert!(buf.source_text(1, 10) == String::from("es"));
+    }
 }
@@ -0,0 +1,186 @@
+use super::file_location::*;
+
+use crate::meta::*;
+
+use std::sync::*;
+use std::collections::{HashMap};
+
+/// The minimum number of locations we store before we start to look for ones attached to cells that have been freed
+const MIN_PRUNE_SIZE: usize = 1024;
+
+///
+/// Describes where a cell was read from in the source code
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    /// Where the cell starts in its file
+    pub location: FileLocation,
+
+    /// The text of the first line of the cell's definition
+    pub text: String,
+
+    /// The length of the cell's definition, in characters
+    pub length: u32
+}
+
+///
+/// The table mapping cells to their source locations
+///
+struct CellLocations {
+    /// The locations, indexed by cell address
+    locations: HashMap<usize, (Weak<SafasCell>, Arc<SourceLocation>)>,
+
+    /// Number of entries to allow before we look for locations that can be freed
+    prune_size: usize
+}
+
+///
+/// The locations that a set of cells were read from
+///
+/// The parser returns the locations of the lists it reads along with the cells, and these are added to the symbol bindings
+/// that are used to evaluate them. Cells are identified by their address: the weak reference ensures that the address is not
+/// reused by another cell while the entry exists (and lets us discover entries for cells that no longer exist).
+///
+/// Cloning this shares the same table: locations added via one copy can be found via every copy.
+///
+#[derive(Clone)]
+pub struct SourceLocations(Arc<Mutex<CellLocations>>);
+
+impl SourceLocation {
+    ///
+    /// Creates a new source location
+    ///
+    pub fn new(location: FileLocation, text: &str) -> SourceLocation {
+        SourceLocation {
+            location:   location,
+            text:       text.lines().nth(0).unwrap_or("").trim_end().to_string(),
+            length:     text.chars().count() as u32
+        }
+    }
+
+    ///
+    /// True if the text of another location is contained within the text of this one
+    ///
+    pub fn contains(&self, other: &SourceLocation) -> bool {
+        let start   = self.location.file_idx;
+        let end     = start + self.length;
+
+        self.location.filename == other.location.filename
+            && other.location.file_idx >= start
+            && other.location.file_idx + other.length <= end
+    }
+}
+
+impl SourceLocations {
+    ///
+    /// Creates an empty set of source locations
+    ///
+    pub fn new() -> SourceLocations {
+        SourceLocations(Arc::new(Mutex::new(CellLocations { locations: HashMap::new(), prune_size: MIN_PRUNE_SIZE })))
+    }
+
+    ///
+    /// Records the location that a cell was read from
+    ///
+    pub fn set_location(&self, cell: &CellRef, location: SourceLocation) {
+        let mut cell_locations = self.0.lock().unwrap();
+
+        cell_locations.insert(cell, Arc::new(location));
+    }
+
+    ///
+    /// Retrieves the location that a cell was read from, if it's known
+    ///
+    pub fn location(&self, cell: &CellRef) -> Option<Arc<SourceLocation>> {
+        let cell_locations  = self.0.lock().unwrap();
+        let address         = Arc::as_ptr(cell) as usize;
+
+        cell_locations.locations.get(&address)
+            .map(|(_cell, location)| Arc::clone(location))
+    }
+
+    ///
+    /// Adds the locations from another set (eg, the locations returned by the parser) to this one
+    ///
+    pub fn add_locations(&self, locations: &SourceLocations) {
+        if Arc::ptr_eq(&self.0, &locations.0) { return; }
+
+        let new_locations       = locations.0.lock().unwrap();
+        let mut cell_locations  = self.0.lock().unwrap();
+
+        for (cell, location) in new_locations.locations.values() {
+            if let Some(cell) = cell.upgrade() {
+                cell_locations.insert(&cell, Arc::clone(location));
+            }
+        }
+    }
+}
+
+impl Default for SourceLocations {
+    fn default() -> SourceLocations {
+        SourceLocations::new()
+    }
+}
+
+impl CellLocations {
+    ///
+    /// Adds the location for a cell to this table
+    ///
+    fn insert(&mut self, cell: &CellRef, location: Arc<SourceLocation>) {
+        // Remove any locations for cells that have been freed if the table is getting large
+        if self.locations.len() >= self.prune_size {
+            self.locations.retain(|_address, (cell, _location)| cell.strong_count() > 0);
+            self.prune_size = usize::max(MIN_PRUNE_SIZE, self.locations.len() * 2);
+        }
+
+        let address = Arc::as_ptr(cell) as usize;
+        self.locations.insert(address, (Arc::downgrade(cell), location));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn retrieve_location() {
+        let locations   = SourceLocations::new();
+        let cell        = SafasCell::list_with_cells(vec![SafasCell::atom("test")]);
+        locations.set_location(&cell, SourceLocation::new(FileLocation::new("test.sf"), "(test)\n(more)"));
+
+        let location = locations.location(&cell).unwrap();
+        assert!(location.text == "(test)".to_string());
+        assert!(location.location.line == 1 && location.location.chr_idx == 1);
+    }
+
+    #[test]
+    fn add_locations_from_parser() {
+        let parsed_locations    = SourceLocations::new();
+        let locations           = SourceLocations::new();
+        let cell                = SafasCell::list_with_cells(vec![SafasCell::atom("test")]);
+        parsed_locations.set_location(&cell, SourceLocation::new(FileLocation::new("test.sf"), "(test)"));
+
+        assert!(locations.location(&cell).is_none());
+
+        locations.add_locations(&parsed_locations);
+        assert!(locations.location(&cell).unwrap().text == "(test)".to_string());
+    }
+
+    #[test]
+    fn nested_location_is_contained() {
+        let outer = SourceLocation::new(FileLocation::new("test.sf"), "(outer (inner))");
+        let inner = SourceLocation::new(FileLocation { chr_idx: 8, file_idx: 7, ..FileLocation::new("test.sf") }, "(inner)");
+        let other = SourceLocation::new(FileLocation::new("other.sf"), "(inner)");
+
+        assert!(outer.contains(&inner));
+        assert!(!inner.contains(&outer));
+        assert!(!outer.contains(&other));
+    }
+
+    #[test]
+    fn no_location_for_new_cells() {
+        let locations   = SourceLocations::new();
+        let cell        = SafasCell::list_with_cells(vec![SafasCell::atom("test")]);
+        assert!(locations.location(&cell).is_none());
+    }
+}
@@ -90,6 +90,20 @@ pub fn tokenize<Chars: Iterator<Item=char>>(buffer: &mut TokenReadBuffer<Chars>,
                 (Token::Symbol('#'), buffer.read_characters(), buffer.update_location(location))
             }
         },
+        Some(':')       => {
+            // ':name' is an atom (used for named arguments), as are ':+' and ':-' (used for anonymous labels)
+            let next_char = buffer.read_next();
+            if next_char.map(|chr| chr.is_alphabetic() || chr == '_').unwrap_or(false) {
+                read_atom(buffer, location)
+            } else if next_char == Some('+') || next_char == Some('-') {
+                read_repeated_symbol(next_char.unwrap(), buffer, location)
+            } else {
+                if next_char.is_some() {
+                    buffer.push_back();
+                }
+                (Token::Symbol(':'), buffer.read_characters(), buffer.update_location(location))
+            }
+        },
 
         Some(symbol)    => {
             if symbol.is_alphabetic() || symbol == '_' {
@@ -243,8 +257,41 @@ fn read_atom<Chars: Iterator<Item=char>>(buffer: &mut TokenReadBuffer<Chars>, lo
         let next_char = buffer.read_next();
 
         match next_char {
+            Some('*')       => {
+                // Atoms can end with a '*' (eg, 'let*')
+                break;
+            }
+            Some('-')       => {
+                // Atoms can contain '->' (eg, 'char->number')
+                match buffer.read_next() {
+                    Some('>')   => { }
+                    Some(_)     => { buffer.push_back(); buffer.push_back(); break; }
+                    None        => { buffer.push_back(); break; }
+                }
+            }
             Some(chr)       => {
-                if !chr.is_alphanumeric() && chr != '_' {
+                if !chr.is_alphanumeric() && chr != '_' && chr != '.' {
+                    buffer.push_back();
+                    break;
+                }
+            }
+            None            => break,
+        }
+    }
+
+    (Token::Atom, buffer.read_characters(), buffer.update_location(location))
+}
+
+///
+/// Reads the rest of an atom made up of a repeated symbol (eg, the '++' in ':++')
+///
+fn read_repeated_symbol<Chars: Iterator<Item=char>>(symbol: char, buffer: &mut TokenReadBuffer<Chars>, location: FileLocation) -> (Token, String, FileLocation) {
+    loop {
+        let next_char = buffer.read_next();
+
+        match next_char {
+            Some(chr)       => {
+                if chr != symbol {
                     buffer.push_back();
                     break;
                 }
@@ -499,6 +546,40 @@ mod test {
         assert!(tokens_for("<<atom>>") == vec![Token::Atom, Token::Atom, Token::Atom]);
     }
 
+    #[test]
+    fn tokenize_named_argument() {
+        // :name is used for named arguments
+        assert!(tokens_for(":start") == vec![Token::Atom]);
+        assert!(tokens_for(": start") == vec![Token::Symbol(':'), Token::Whitespace, Token::Atom]);
+    }
+
+    #[test]
+    fn tokenize_anonymous_label_reference() {
+        assert!(tokens_for(":+") == vec![Token::Atom]);
+        assert!(tokens_for(":--") == vec![Token::Atom]);
+        assert!(tokens_for("(bne :+)") == vec![Token::OpenParen, Token::Atom, Token::Whitespace, Token::Atom, Token::CloseParen]);
+        assert!(tokens_for(":+-") == vec![Token::Atom, Token::Symbol('-')]);
+    }
+
+    #[test]
+    fn tokenize_scoped_atom() {
+        assert!(tokens_for("name.loop") == vec![Token::Atom]);
+        assert!(tokens_for(".loop") == vec![Token::Symbol('.'), Token::Atom]);
+    }
+
+    #[test]
+    fn tokenize_starred_atom() {
+        assert!(tokens_for("let*") == vec![Token::Atom]);
+        assert!(tokens_for("let*x") == vec![Token::Atom, Token::Atom]);
+    }
+
+    #[test]
+    fn tokenize_arrow_atom() {
+        assert!(tokens_for("char->number") == vec![Token::Atom]);
+        assert!(tokens_for("x-1") == vec![Token::Atom, Token::Symbol('-'), Token::IntNumber]);
+        assert!(tokens_for("x-") == vec![Token::Atom, Token::Symbol('-')]);
+    }
+
     #[test]
     fn tokenize_atom_9() {
         // But symbols don't combine into longer atoms when they're repeated
@@ -0,0 +1,411 @@
+use crate::io::*;
+use crate::bind::*;
+use crate::meta::*;
+use crate::exec::*;
+use crate::parse::*;
+use crate::bitcode::*;
+use crate::syntax::*;
+use crate::functions::*;
+use crate::interactive::*;
+
+use smallvec::*;
+use std::mem;
+
+///
+/// The result of assembling some SAFAS code
+///
+#[derive(Clone, Debug)]
+pub struct Assembly {
+    /// The value returned by the code that was assembled
+    pub value: CellRef,
+
+    /// The bitcode generated by the assembler
+    pub bitcode: Vec<BitCode>,
+
+    /// The final values of the labels
+    pub labels: LabelValues,
+
+    /// A listing relating the source code to the addresses and bytes it generated
+    pub listing: Listing
+}
+
+impl Assembly {
+    ///
+    /// Converts the bitcode generated by the assembler to bytes
+    ///
+    pub fn bytes(&self) -> Vec<u8> {
+        bitcode_to_bytes(self.bitcode.iter().cloned())
+    }
+
+    ///
+    /// Returns the symbols for the named labels in the assembled code
+    ///
+    pub fn symbols(&self) -> Vec<LabelSymbol> {
+        self.labels.symbols()
+    }
+}
+
+///
+/// A SAFAS session, which is a set of symbol bindings and an execution frame that code can be evaluated and assembled in
+///
+/// Definitions made by code evaluated in a session are available to all of the code that is evaluated after it.
+///
+pub struct Session {
+    /// The execution frame for this session
+    frame: Frame,
+
+    /// The symbols that are bound in this session
+    bindings: SymbolBindings,
+
+    /// The messages generated by the code assembled in this session that have not been retrieved yet
+    diagnostics: Vec<Diagnostic>,
+
+    /// The limits that apply to the code evaluated and assembled in this session (shared with the frame and the bindings)
+    limits: SharedLimits
+}
+
+impl Session {
+    ///
+    /// Creates a new session with the standard bindings and the default library
+    ///
+    pub fn new() -> Result<Session, RuntimeError> {
+        let mut session = Session::without_default_library();
+        session.load_default_library()?;
+
+        Ok(session)
+    }
+
+    ///
+    /// Creates a new session with just the standard bindings (only the built-in functions are available)
+    ///
+    pub fn without_default_library() -> Session {
+        let limits              = SharedLimits::default();
+        let mut frame           = Frame::new(1, None);
+        let bindings            = SymbolBindings::new();
+        frame.limits            = limits.clone();
+        let (frame, bindings)   = setup_standard_bindings(frame, bindings);
+
+        Session {
+            frame:          frame,
+            bindings:       bindings,
+            diagnostics:    vec![],
+            limits:         limits
+        }
+    }
+
+    ///
+    /// Retrieves the limits that currently apply to this session
+    ///
+    pub fn limits(&self) -> Limits {
+        self.limits.get()
+    }
+
+    ///
+    /// Changes the limits that apply to the code evaluated and assembled in this session
+    ///
+    /// Code in the session can also change its limits by calling `set_limits`. Other sessions are not affected.
+    ///
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits.set(limits);
+    }
+
+    ///
+    /// Loads the default library into this session (this is searched for on the import path, so a different version
+    /// of the library can be supplied by calling `add_import_path` before this)
+    ///
+    pub fn load_default_library(&mut self) -> Result<(), RuntimeError> {
+        self.import("standard/default.sf", false)?;
+
+        Ok(())
+    }
+
+    ///
+    /// Adds a path to the start of the list of paths searched for imported files
+    ///
+    pub fn add_import_path(&mut self, path: &str) {
+        let import_atom = get_id_for_atom_with_name("import_path");
+        let import_path = self.bindings.look_up(import_atom).map(|(cell, _depth)| cell).unwrap_or(NIL.clone());
+        let import_path = SafasCell::List(SafasCell::String(path.to_string()).into(), import_path);
+
+        self.bindings.symbols.insert(import_atom, import_path.into());
+    }
+
+    ///
+    /// Adds a source file that can be imported without being read from disk
+    ///
+    /// Sources added this way are used when the file can't be found on the import path.
+    ///
+    pub fn add_source(&mut self, name: &str, source: &str) -> Result<(), RuntimeError> {
+        let built_ins_atom  = get_id_for_atom_with_name("built_ins");
+        let built_ins       = self.bindings.look_up(built_ins_atom).map(|(cell, _depth)| cell).unwrap_or_else(btree_new);
+        let built_ins       = btree_insert(built_ins, (SafasCell::String(name.to_string()).into(), SafasCell::String(source.to_string()).into()))?;
+
+        self.bindings.symbols.insert(built_ins_atom, built_ins);
+
+        Ok(())
+    }
+
+    ///
+    /// Makes a function implemented in Rust available to the code in this session
+    ///
+    pub fn define_function<TMonad>(&mut self, name: &str, function: TMonad) -> Result<(), RuntimeError>
+    where TMonad: 'static+FrameMonad<Binding=RuntimeResult> {
+        self.define(define_function(name, function))
+    }
+
+    ///
+    /// Defines a symbol to have a particular value in this session (as if `(def <name> (quote <value>))` had been evaluated)
+    ///
+    pub fn define_symbol(&mut self, name: &str, value: CellRef) -> Result<(), RuntimeError> {
+        self.define(define_symbol(name, value))
+    }
+
+    ///
+    /// Defines a symbol to have the value of a SAFAS literal (for example `42`, `"text"` or `(1 2 3)`)
+    ///
+    /// The literal is parsed but not evaluated, so a list is defined as a list rather than as a function call.
+    ///
+    pub fn define_symbol_from_source(&mut self, name: &str, source: &str) -> Result<(), RuntimeError> {
+        let values = parse_safas(&mut TokenReadBuffer::new(source.chars()), FileLocation::new("<define>"))?;
+
+        match &*values {
+            SafasCell::List(value, next) if next.is_nil()   => self.define_symbol(name, value.clone()),
+            SafasCell::List(_, _)                           => Err(RuntimeError::TooManyArguments(values.clone())),
+            _                                               => Err(RuntimeError::NotEnoughArguments(values.clone()))
+        }
+    }
+
+    ///
+    /// Binds and executes a definition in this session
+    ///
+    fn define<TBinding>(&mut self, definition: TBinding) -> Result<(), RuntimeError>
+    where TBinding: BindingMonad<Binding=SmallVec<[Action; 8]>> {
+        let (mut frame, bindings)   = self.take_state();
+        let (bindings, actions)     = definition.bind(bindings);
+        frame.allocate_for_bindings(&bindings);
+
+        let result = match actions {
+            Ok(actions) => {
+                let (frame, result) = actions.execute(frame);
+                self.frame          = frame;
+                result.map(|_| ())
+            },
+
+            Err(err)    => {
+                self.frame          = frame;
+                Err(err.into())
+            }
+        };
+
+        self.bindings = bindings;
+        result
+    }
+
+    ///
+    /// Evaluates some SAFAS statements in this session, returning the value of the last statement
+    ///
+    pub fn eval(&mut self, source: &str) -> Result<CellRef, RuntimeError> {
+        let (statements, locations)     = parse_safas_with_locations(&mut TokenReadBuffer::new(source.chars()), FileLocation::new("<eval>"))?;
+        let (frame, bindings)           = self.take_state();
+        bindings.source_locations.add_locations(&locations);
+        let (result, bindings, frame)   = eval_statements(statements, NIL.clone(), bindings, frame);
+
+        self.restore_state(frame, bindings);
+        Self::to_result(result)
+    }
+
+    ///
+    /// Imports a file into this session, returning the value of the last statement in the file
+    ///
+    /// Files are searched for in the current directory, then on the import path, then in the sources added by `add_source`.
+    ///
+    pub fn import_file(&mut self, path: &str) -> Result<CellRef, RuntimeError> {
+        self.import(path, true)
+    }
+
+    ///
+    /// Evaluates and assembles some SAFAS statements
+    ///
+    pub fn assemble(&mut self, source: &str) -> Result<Assembly, RuntimeError> {
+        let value = self.eval(source)?;
+        self.assemble_value(&value)
+    }
+
+    ///
+    /// Imports and assembles a file
+    ///
+    pub fn assemble_file(&mut self, path: &str) -> Result<Assembly, RuntimeError> {
+        let value = self.import_file(path)?;
+        self.assemble_value(&value)
+    }
+
+    ///
+    /// Assembles a value returned by `eval` or `import_file` (values that are not bitcode monads generate no bitcode)
+    ///
+    /// Any messages generated during assembly can be retrieved by calling `take_diagnostics`.
+    ///
+    pub fn assemble_value(&mut self, value: &CellRef) -> Result<Assembly, RuntimeError> {
+        let monad                               = BitCodeMonad::from_cell(value).unwrap_or_else(|| BitCodeMonad::with_value(value.clone()));
+        let (value, bitcode, labels, listing)   = assemble_with_listing(&monad, &self.limits.get(), &mut self.diagnostics)?;
+
+        Ok(Assembly {
+            value:      value,
+            bitcode:    bitcode,
+            labels:     labels,
+            listing:    listing
+        })
+    }
+
+    ///
+    /// Retrieves the messages generated while assembling code in this session (since the last time this was called)
+    ///
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        mem::take(&mut self.diagnostics)
+    }
+
+    ///
+    /// Runs the interactive interpreter using the definitions from this session
+    ///
+    pub fn run_interactive(self) {
+        run_interactive(self.frame, self.bindings);
+    }
+
+    ///
+    /// Imports a file into this session (paths relative to the current directory are only searched if allow_relative is set)
+    ///
+    fn import(&mut self, path: &str, allow_relative: bool) -> Result<CellRef, RuntimeError> {
+        let (frame, bindings)           = self.take_state();
+        let (result, bindings, frame)   = import_file(path, bindings, frame, allow_relative);
+
+        self.restore_state(frame, bindings);
+        Self::to_result(result)
+    }
+
+    ///
+    /// Takes the frame and bindings from this session, so they can be passed to a function that updates them
+    ///
+    fn take_state(&mut self) -> (Frame, SymbolBindings) {
+        let frame       = mem::replace(&mut self.frame, Frame::new(1, None));
+        let bindings    = mem::replace(&mut self.bindings, SymbolBindings::new());
+
+        (frame, bindings)
+    }
+
+    ///
+    /// Replaces the frame and bindings for this session after they've been updated
+    ///
+    fn restore_state(&mut self, frame: Frame, bindings: SymbolBindings) {
+        self.frame      = frame;
+        self.bindings   = bindings;
+    }
+
+    ///
+    /// Converts the result of evaluating some statements to a Result
+    ///
+    fn to_result(result: CellRef) -> Result<CellRef, RuntimeError> {
+        if let SafasCell::Error(err) = &*result {
+            Err(err.clone())
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn definitions_are_kept_between_evaluations() {
+        let mut session = Session::without_default_library();
+
+        session.eval("(def x 42)").unwrap();
+        let val = session.eval("x").unwrap().to_string();
+
+        assert!(val == "42".to_string());
+    }
+
+    #[test]
+    fn assemble_to_bytes_and_symbols() {
+        let mut session = Session::without_default_library();
+        let assembly    = session.assemble("(d $1u8) (label start) (d $2u8 $3u8)").unwrap();
+
+        assert!(assembly.bytes() == vec![1, 2, 3]);
+        assert!(assembly.symbols().iter().any(|symbol| symbol.name == "start".to_string() && symbol.value == 8));
+    }
+
+    #[test]
+    fn import_in_memory_source() {
+        let mut session = Session::without_default_library();
+
+        session.add_source("my_library.sf", "(def answer 42) (export answer)").unwrap();
+        let val = session.eval("(import \"my_library\") answer").unwrap().to_string();
+
+        assert!(val == "42".to_string());
+    }
+
+    #[test]
+    fn call_custom_function() {
+        let mut session = Session::without_default_library();
+
+        session.define_function("double", FnMonad::from(|(number, ): (SafasNumber, )| {
+            CellRef::new(SafasCell::Number(number + number))
+        })).unwrap();
+        let val = session.eval("(double 21)").unwrap().to_string();
+
+        assert!(val == "42".to_string());
+    }
+
+    #[test]
+    fn define_symbols_from_source() {
+        let mut session = Session::without_default_library();
+
+        session.define_symbol_from_source("PAL", "1").unwrap();
+        session.define_symbol_from_source("NAME", "\"game\"").unwrap();
+        session.define_symbol_from_source("BANKS", "(0 1 2)").unwrap();
+        let val = session.eval("(list PAL NAME (cdr BANKS))").unwrap().to_string();
+
+        assert!(val == "(1 \"game\" (1 2))".to_string());
+    }
+
+    #[test]
+    fn define_symbol_needs_one_value() {
+        let mut session = Session::without_default_library();
+
+        assert!(session.define_symbol_from_source("EMPTY", "").is_err());
+        assert!(session.define_symbol_from_source("TWO", "1 2").is_err());
+    }
+
+    #[test]
+    fn default_library_is_loaded() {
+        let mut session = Session::new().unwrap();
+        let assembly    = session.assemble("(set_ip $8000) (label start) (d $1u8)").unwrap();
+
+        assert!(assembly.symbols().iter().any(|symbol| symbol.name == "start".to_string() && symbol.value == 0x8000));
+    }
+
+    #[test]
+    fn assembly_diagnostics_are_collected() {
+        let mut session = Session::without_default_library();
+        session.assemble("(print \"hello\")").unwrap();
+
+        let diagnostics = session.take_diagnostics();
+        assert!(diagnostics.len() == 1);
+        assert!(diagnostics[0].message == "hello".to_string());
+        assert!(session.take_diagnostics().len() == 0);
+    }
+
+    #[test]
+    fn limits_are_per_session() {
+        let mut limited = Session::without_default_library();
+        let mut other   = Session::without_default_library();
+
+        limited.set_limits(Limits { max_output_size: 1, ..Limits::default() });
+        other.eval("(set_limits :max_passes 2)").unwrap();
+
+        assert!(limited.assemble("(d $1u8 $2u8)").is_err());
+        assert!(other.assemble("(d $1u8 $2u8)").unwrap().bytes() == vec![1, 2]);
+        assert!(limited.limits().max_passes == DEFAULT_MAX_PASSES);
+        assert!(other.limits().max_passes == 2);
+    }
+}
@@ -3,28 +3,38 @@ use crate::exec::*;
 use crate::meta::*;
 use crate::bitcode::*;
 
-use std::convert::*;
-
 ///
 /// Function that takes a monad parameter and returns the assembled bitcode
 /// 
 /// Normally monad parameters to functions are remapped so that the monad's content is used instead of the
-/// monad itself, so this requres assistance from syntax (the assemble keyword in this case) to work.
+/// monad itself, so this requres assistance from syntax (the assemble keyword in this case) to work. The
+/// assembler uses the limits from the frame that calls this function.
 ///
-fn assemble_fn() -> impl FrameMonad<Binding=RuntimeResult> {
-    FnMonad::from(|(monad, ): (CellRef, )| {
+struct AssembleFn;
+
+impl FrameMonad for AssembleFn {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##assemble##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, Self::Binding) {
+        let args            = <(CellRef, )>::args_from_frame(&frame);
+        let (monad, )       = match args { Ok(args) => args, Err(err) => return (frame, Err(err)) };
+        let limits          = frame.limits.get();
 
-        if let Some(monad) = BitCodeMonad::from_cell(&monad) {
-            let (result, bitcode)   = assemble(&monad)?;
-            let bitcode             = CellRef::new(SafasCell::BitCode(bitcode));
+        let result          = if let Some(monad) = BitCodeMonad::from_cell(&monad) {
+            assemble_with_listing(&monad, &limits, &mut vec![]).map(|(result, bitcode, _labels, _listing)| {
+                let bitcode = CellRef::new(SafasCell::BitCode(bitcode));
 
-            Ok(SafasCell::list_with_cells(vec![bitcode, result]))
+                SafasCell::list_with_cells(vec![bitcode, result])
+            })
         } else {
             // Only works on bitcode monads
             Err(RuntimeError::NotBitCode(monad))
-        }
+        };
 
-    })
+        (frame, result)
+    }
 }
 
 ///
@@ -32,7 +42,7 @@ fn assemble_fn() -> impl FrameMonad<Binding=RuntimeResult> {
 ///
 pub fn assemble_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
     // Create a copy of the assemble function to use when compiling
-    let assemble_fn = CellRef::new(SafasCell::FrameMonad(Box::new(assemble_fn())));
+    let assemble_fn = CellRef::new(SafasCell::FrameMonad(Box::new(AssembleFn)));
 
     get_expression_arguments().and_then(|ListTuple((assemble_monad, )): ListTuple<(CellRef, )>| {
 
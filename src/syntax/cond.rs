@@ -0,0 +1,343 @@
+use super::conditional::*;
+
+use crate::bind::*;
+use crate::meta::*;
+use crate::exec::*;
+
+use std::convert::*;
+
+lazy_static! {
+    /// The atom that marks the clause that's evaluated when no other clause matches
+    static ref ELSE_ATOM: u64 = get_id_for_atom_with_name("else");
+}
+
+///
+/// Binds a list of statements into a syntax compiler that evaluates them in order
+///
+fn bind_statements(statements: CellRef, bindings: SymbolBindings) -> (SymbolBindings, Result<SyntaxCompiler, BindError>) {
+    match bind_several_statements(statements, bindings) {
+        Ok(((statements, reference_type), bindings))    => (bindings, Ok(SyntaxCompiler::with_compiler_and_reftype(compile_several_statements, statements, reference_type))),
+        Err((err, bindings))                            => (bindings, Err(err))
+    }
+}
+
+///
+/// Binds `(if (<conditional>) <if_true> <if_false>)`, where the branches are lists of statements
+///
+/// The branches can contain statements that are already bound, which is how the rest of a `cond` or `and` statement is
+/// chained on to the end of an `if`
+///
+fn bind_if(conditional: CellRef, if_true: CellRef, if_false: CellRef, bindings: SymbolBindings) -> (SymbolBindings, Result<SyntaxCompiler, BindError>) {
+    let mut bindings    = bindings;
+    bindings.args       = Some(SafasCell::list_with_cells(vec![SafasCell::list_with_cells(vec![conditional]), if_true, if_false]));
+
+    if_keyword().bind(bindings)
+}
+
+///
+/// Binds the clauses of a cond statement as a chain of `if` statements
+///
+fn bind_cond_clauses(clauses: &[(CellRef, CellRef)], otherwise: CellRef, bindings: SymbolBindings) -> (SymbolBindings, Result<SyntaxCompiler, BindError>) {
+    if clauses.len() == 0 {
+        // Once all the clauses have been tried, evaluate the 'else' statements
+        bind_statements(otherwise, bindings)
+    } else {
+        // Try the first clause, otherwise evaluate the rest
+        let (test, statements)  = &clauses[0];
+        let (bindings, rest)    = bind_cond_clauses(&clauses[1..], otherwise, bindings);
+        let rest                = match rest { Ok(rest) => rest, Err(err) => return (bindings, Err(err)) };
+        let rest                = SafasCell::list_with_cells(vec![SafasCell::BoundSyntax(rest).into()]);
+
+        bind_if(test.clone(), statements.clone(), rest, bindings)
+    }
+}
+
+///
+/// Reads the clauses for a `cond` or `case` statement, returning the clauses and the statements in the `else` clause
+///
+/// Each clause is a list of `(<test> <statements>)`, and the final clause can be `(else <statements>)`. Without an `else`
+/// clause, the statement evaluates to `()` when nothing matches.
+///
+fn read_clauses(clauses: CellRef) -> Result<(Vec<(CellRef, CellRef)>, CellRef), BindError> {
+    let clauses         = clauses.to_vec().ok_or(BindError::SyntaxExpectingList)?;
+    let mut result      = vec![];
+    let mut otherwise   = None;
+
+    for clause in clauses {
+        // Nothing can follow the 'else' clause
+        if otherwise.is_some() { return Err(BindError::TooManyArguments); }
+
+        match &*clause {
+            SafasCell::List(test, statements)   => {
+                if statements.is_nil() { return Err(BindError::MissingArgument); }
+
+                if test.to_atom_id() == Some(*ELSE_ATOM) {
+                    otherwise = Some(statements.clone());
+                } else {
+                    result.push((test.clone(), statements.clone()));
+                }
+            }
+
+            _                                   => return Err(BindError::SyntaxExpectingList)
+        }
+    }
+
+    let otherwise = otherwise.unwrap_or_else(|| SafasCell::list_with_cells(vec![NIL.clone()]));
+    Ok((result, otherwise))
+}
+
+///
+/// The `cond` keyword evaluates the statements for the first clause whose test is true
+///
+/// ```(cond (<test> <statements>) ... (else <statements>))```
+///
+/// For example, `(cond ((< x $100) (d 1u8)) ((< x $10000) (d 2u8)) (else (d 3u8)))`. The tests are evaluated in order until
+/// one of them is true, and the else clause is evaluated if none of them are. The result is `()` if no clause matches and
+/// there's no else clause.
+///
+pub fn cond_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    BindingFn::from_binding_fn(|bindings: SymbolBindings| {
+        let args                    = bindings.args.clone().unwrap_or_else(|| NIL.clone());
+        let (clauses, otherwise)    = match read_clauses(args) { Ok(clauses) => clauses, Err(err) => return (bindings, Err(err)) };
+
+        bind_cond_clauses(&clauses, otherwise, bindings)
+    })
+}
+
+///
+/// The `case` keyword evaluates the statements for the first clause that lists the value of a key
+///
+/// ```(case <key> ((<values>) <statements>) ... (else <statements>))```
+///
+/// For example, `(case mode ((zero_page) 2) ((absolute absolute_x) 3) (else 1))`. The values are not evaluated, so they are
+/// usually numbers or atoms, and are compared with the key as for `=`. The key is only evaluated once.
+///
+pub fn case_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    BindingFn::from_binding_fn(|bindings: SymbolBindings| {
+        // Arguments are the key and the clauses
+        let args                    = bindings.args.clone().unwrap_or_else(|| NIL.clone());
+        let (key, clauses)          = match &*args {
+            SafasCell::List(key, clauses)   => (key.clone(), clauses.clone()),
+            _                               => return (bindings, Err(BindError::MissingArgument))
+        };
+        let (clauses, otherwise)    = match read_clauses(clauses) { Ok(clauses) => clauses, Err(err) => return (bindings, Err(err)) };
+
+        // The key is evaluated into a cell so the tests can read it
+        let (mut bindings, key)     = match bind_statement(key, bindings) { Ok((key, bindings)) => (bindings, key), Err((err, bindings)) => return (bindings, Err(err)) };
+        let key_cell                = bindings.alloc_cell();
+        let key_ref: CellRef        = SafasCell::FrameReference(key_cell, 0, key.reference_type()).into();
+
+        // Each clause tests the key against its values with a function call (which will be flat_mapped if the key is a monad)
+        let clauses = clauses.into_iter()
+            .map(|(values, statements)| {
+                let values  = values.to_vec().unwrap_or_else(|| vec![values.clone()]);
+                let matches = FnMonad::from(move |(key, ): (CellRef, )| Ok(SafasCell::Boolean(values.iter().any(|value| **value == *key)).into()));
+                let matches = SafasCell::FrameMonad(Box::new(matches)).into();
+
+                (SafasCell::list_with_cells(vec![matches, key_ref.clone()]), statements)
+            })
+            .collect::<Vec<_>>();
+
+        // Bind the clauses as for cond
+        let (bindings, cond)        = bind_cond_clauses(&clauses, otherwise, bindings);
+        let cond                    = match cond { Ok(cond) => cond, Err(err) => return (bindings, Err(err)) };
+        let reference_type          = cond.reference_type();
+
+        // Evaluate the key, then the clauses
+        let compile = |case: CellRef| {
+            let ListTuple((key, FrameReference(key_cell, _, _), cond)): ListTuple<(CellRef, FrameReference, CellRef)> = case.try_into()?;
+
+            let mut actions = compile_statement(key)?;
+            actions.push(Action::StoreCell(key_cell));
+            actions.extend(compile_statement(cond)?);
+
+            Ok(actions)
+        };
+
+        let case = SafasCell::list_with_cells(vec![key, key_ref, SafasCell::BoundSyntax(cond).into()]);
+        (bindings, Ok(SyntaxCompiler::with_compiler_and_reftype(compile, case, reference_type)))
+    })
+}
+
+///
+/// Binds the values of an `and` statement as a chain of `if` statements
+///
+fn bind_and(values: &[CellRef], bindings: SymbolBindings) -> (SymbolBindings, Result<SyntaxCompiler, BindError>) {
+    if values.len() == 0 {
+        bind_statements(SafasCell::list_with_cells(vec![SafasCell::Boolean(true).into()]), bindings)
+    } else {
+        let (bindings, rest)    = bind_and(&values[1..], bindings);
+        let rest                = match rest { Ok(rest) => rest, Err(err) => return (bindings, Err(err)) };
+        let rest                = SafasCell::list_with_cells(vec![SafasCell::BoundSyntax(rest).into()]);
+
+        bind_if(values[0].clone(), rest, SafasCell::list_with_cells(vec![SafasCell::Boolean(false).into()]), bindings)
+    }
+}
+
+///
+/// Binds the values of an `or` statement as a chain of `if` statements
+///
+fn bind_or(values: &[CellRef], bindings: SymbolBindings) -> (SymbolBindings, Result<SyntaxCompiler, BindError>) {
+    if values.len() == 0 {
+        bind_statements(SafasCell::list_with_cells(vec![SafasCell::Boolean(false).into()]), bindings)
+    } else {
+        let (bindings, rest)    = bind_or(&values[1..], bindings);
+        let rest                = match rest { Ok(rest) => rest, Err(err) => return (bindings, Err(err)) };
+        let rest                = SafasCell::list_with_cells(vec![SafasCell::BoundSyntax(rest).into()]);
+
+        bind_if(values[0].clone(), SafasCell::list_with_cells(vec![SafasCell::Boolean(true).into()]), rest, bindings)
+    }
+}
+
+///
+/// The `and` keyword evaluates to `=t` if all of its values are true
+///
+/// ```(and <values>)```
+///
+/// The values are evaluated in order, and evaluation stops at the first one that is not true.
+///
+pub fn and_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    BindingFn::from_binding_fn(|bindings: SymbolBindings| {
+        let args = bindings.args.clone().unwrap_or_else(|| NIL.clone());
+        let args = match args.to_vec() { Some(args) => args, None => return (bindings, Err(BindError::SyntaxExpectingList)) };
+
+        bind_and(&args, bindings)
+    })
+}
+
+///
+/// The `or` keyword evaluates to `=t` if any of its values are true
+///
+/// ```(or <values>)```
+///
+/// The values are evaluated in order, and evaluation stops at the first one that is true.
+///
+pub fn or_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    BindingFn::from_binding_fn(|bindings: SymbolBindings| {
+        let args = bindings.args.clone().unwrap_or_else(|| NIL.clone());
+        let args = match args.to_vec() { Some(args) => args, None => return (bindings, Err(BindError::SyntaxExpectingList)) };
+
+        bind_or(&args, bindings)
+    })
+}
+
+///
+/// The `not` keyword evaluates to `=t` if its value is not true
+///
+/// ```(not <value>)```
+///
+pub fn not_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    BindingFn::from_binding_fn(|bindings: SymbolBindings| {
+        let args = bindings.args.clone().unwrap_or_else(|| NIL.clone());
+        let args = match args.to_vec() { Some(args) => args, None => return (bindings, Err(BindError::SyntaxExpectingList)) };
+
+        if args.len() < 1 { return (bindings, Err(BindError::MissingArgument)); }
+        if args.len() > 1 { return (bindings, Err(BindError::TooManyArguments)); }
+
+        bind_if(args[0].clone(), SafasCell::list_with_cells(vec![SafasCell::Boolean(false).into()]), SafasCell::list_with_cells(vec![SafasCell::Boolean(true).into()]), bindings)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+    use crate::test_support::{assemble_expr};
+
+    #[test]
+    fn cond_first_clause() {
+        let val = eval("(def x 1) (cond ((= x 1) 10) ((= x 2) 20) (else 30))").unwrap().to_string();
+        assert!(val == "10".to_string());
+    }
+
+    #[test]
+    fn cond_second_clause() {
+        let val = eval("(def x 2) (cond ((= x 1) 10) ((= x 2) 20) (else 30))").unwrap().to_string();
+        assert!(val == "20".to_string());
+    }
+
+    #[test]
+    fn cond_else_clause() {
+        let val = eval("(def x 3) (cond ((= x 1) 10) ((= x 2) 20) (else 30))").unwrap().to_string();
+        assert!(val == "30".to_string());
+    }
+
+    #[test]
+    fn cond_without_match() {
+        let val = eval("(cond ((= 1 2) 10))").unwrap().to_string();
+        assert!(val == "()".to_string());
+    }
+
+    #[test]
+    fn else_must_be_last() {
+        let result = eval("(cond (else 30) ((= 1 1) 10))");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn cond_with_bitcode_clauses() {
+        let bytes = assemble_expr("(def x 2) (d 1u8) (cond ((= x 1) (d 10u8)) ((= x 2) (d 20u8) (d 21u8)) (else 3)) (d 4u8)");
+        assert!(bytes == "01 14 15 04".to_string());
+    }
+
+    #[test]
+    fn cond_with_label_test() {
+        let bytes = assemble_expr("(d (bits 8 later)) (cond ((< later 16) (d 1u8)) (else (d 2u8 3u8))) (label later)");
+        assert!(bytes == "18 02 03".to_string());
+    }
+
+    #[test]
+    fn case_number() {
+        let val = eval("(def x 3) (case x ((1 2) 10) ((3 4) 20) (else 30))").unwrap().to_string();
+        assert!(val == "20".to_string());
+    }
+
+    #[test]
+    fn case_atom() {
+        let val = eval("(def mode (quote absolute)) (case mode ((zero_page) 2) ((absolute absolute_x) 3) (else 1))").unwrap().to_string();
+        assert!(val == "3".to_string());
+    }
+
+    #[test]
+    fn case_else() {
+        let val = eval("(case 5 ((1 2) 10) (else 30))").unwrap().to_string();
+        assert!(val == "30".to_string());
+    }
+
+    #[test]
+    fn case_with_bitcode_clauses() {
+        let bytes = assemble_expr("(case 2 ((1) (d 10u8)) ((2) (d 20u8)) (else (d 30u8)))");
+        assert!(bytes == "14".to_string());
+    }
+
+    #[test]
+    fn and_values() {
+        let val = eval("(list (and) (and =t =t) (and =t =f) (and =f =t))").unwrap().to_string();
+        assert!(val == "(=t =t =f =f)".to_string());
+    }
+
+    #[test]
+    fn or_values() {
+        let val = eval("(list (or) (or =f =t) (or =f =f) (or =t =f))").unwrap().to_string();
+        assert!(val == "(=f =t =f =t)".to_string());
+    }
+
+    #[test]
+    fn not_values() {
+        let val = eval("(list (not =t) (not =f))").unwrap().to_string();
+        assert!(val == "(=f =t)".to_string());
+    }
+
+    #[test]
+    fn and_short_circuits() {
+        // Dividing by 0 would fail if the second value was evaluated
+        let val = eval("(def f (fun (x) (and (!= x 0) (= (/ 10 x) 5)))) (list (f 0) (f 2))").unwrap().to_string();
+        assert!(val == "(=f =t)".to_string());
+    }
+
+    #[test]
+    fn or_short_circuits() {
+        // Dividing by 0 would fail if the second value was evaluated
+        let val = eval("(def f (fun (x) (or (= x 0) (= (/ 10 x) 5)))) (list (f 0) (f 2) (f 1))").unwrap().to_string();
+        assert!(val == "(=t =t =f)".to_string());
+    }
+}
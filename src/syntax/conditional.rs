@@ -8,7 +8,7 @@ use std::collections::{HashMap};
 ///
 /// Binds a list of statements
 ///
-fn bind_several_statements(statements: CellRef, bindings: SymbolBindings) -> BindResult<(CellRef, ReferenceType)> {
+pub fn bind_several_statements(statements: CellRef, bindings: SymbolBindings) -> BindResult<(CellRef, ReferenceType)> {
     // Build up the list of results
     let mut result          = vec![];
     let mut pos             = &*statements;
@@ -32,7 +32,7 @@ fn bind_several_statements(statements: CellRef, bindings: SymbolBindings) -> Bin
 ///
 /// Compiles a list of statements
 ///
-fn compile_several_statements(statements: CellRef) -> Result<CompiledActions, BindError> {
+pub fn compile_several_statements(statements: CellRef) -> Result<CompiledActions, BindError> {
     // Start with an empty set of actions
     let mut result          = CompiledActions::empty();
 
@@ -297,6 +297,12 @@ mod test {
         assert!(val == "1".to_string());
     }
 
+    #[test]
+    fn if_as_argument() {
+        let val = eval("(list (if (=t) (1) (2)) (if (=f) (1) (2)))").unwrap().to_string();
+        assert!(val == "(1 2)".to_string());
+    }
+
     #[test]
     fn if_false() {
         let val = eval("(if (=f) (1) (2) )").unwrap().to_string();
@@ -161,7 +161,7 @@ pub fn define_symbol_value<Atom: Into<AtomId>, Cell: Into<CellRef>>(atom: Atom,
 
 #[cfg(test)]
 mod test {
-    use crate::*;
+    use crate::interactive::*;
 
     #[test]
     fn define_and_read_atom() {
@@ -231,7 +231,8 @@ pub fn def_syntax_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
 
 #[cfg(test)]
 mod test {
-    use crate::*;
+    use crate::meta::*;
+    use crate::interactive::*;
 
     #[test]
     fn evaluate_def_syntax() {
@@ -297,7 +297,7 @@ impl BindingMonad for FunBinder {
 
 #[cfg(test)]
 mod test {
-    use crate::*;
+    use crate::interactive::*;
 
     #[test]
     fn define_and_call_function() {
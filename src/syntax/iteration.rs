@@ -0,0 +1,287 @@
+use super::fun::*;
+
+use crate::bind::*;
+use crate::meta::*;
+use crate::exec::*;
+use crate::functions::*;
+
+lazy_static! {
+    /// The atom that separates the variable from the list in a `for` statement
+    static ref IN_ATOM: u64 = get_id_for_atom_with_name("in");
+
+    /// The variable used for the index of a `repeat` statement that doesn't name one
+    static ref REPEAT_INDEX_ATOM: u64 = get_id_for_atom_with_name("##repeat_index##");
+}
+
+///
+/// Frame monad that calls a function for each item in a list: `(for_each (items) function)`
+///
+/// When the function returns monads, the results are flat_mapped together (so any bitcode is generated in order, and the
+/// result is the value of the last monad). Otherwise the result is a list of the values returned by the function.
+///
+struct ForEach {
+    /// True if the function returns a monad
+    returns_monad: bool
+}
+
+impl ForEach {
+    ///
+    /// Chains a list of monads together so they are evaluated in order
+    ///
+    fn chain_monads(&self, results: Vec<CellRef>, frame: Frame) -> (Frame, RuntimeResult) {
+        let mut frame   = frame;
+        let mut chained = None;
+
+        for result in results {
+            // Results that aren't monads are wrapped
+            let result = match &*result {
+                SafasCell::Monad(_, _)  => result,
+                _                       => wrap_value(result)
+            };
+
+            chained = match chained {
+                None        => Some(result),
+                Some(prev)  => {
+                    match &*prev {
+                        SafasCell::Monad(value, monad_type) => {
+                            // flat_map to the next monad in the same way as the 'Next' action
+                            let map_fn                  = SafasCell::FrameMonad(Box::new(wrap_frame(Ok(result)))).into();
+                            let (new_frame, next)       = monad_type.flat_map(value.clone(), map_fn, frame);
+                            frame                       = new_frame;
+
+                            match next {
+                                Ok(next)    => Some(next),
+                                Err(err)    => return (frame, Err(err))
+                            }
+                        }
+
+                        _ => return (frame, Err(RuntimeError::NotAMonad(prev.clone())))
+                    }
+                }
+            }
+        }
+
+        (frame, Ok(chained.unwrap_or_else(|| wrap_value(NIL.clone()))))
+    }
+}
+
+impl FrameMonad for ForEach {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##for_each##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let mut frame = frame;
+
+        // Arguments are the list of items and the function to call
+        let args                = frame.cells[0].clone();
+        let args                = match args.to_vec() { Some(args) => args, None => return (frame, Err(RuntimeError::NotEnoughArguments(args))) };
+        if args.len() < 2 { return (frame, Err(RuntimeError::NotEnoughArguments(SafasCell::list_with_cells(args)))); }
+        if args.len() > 2 { return (frame, Err(RuntimeError::TooManyArguments(SafasCell::list_with_cells(args)))); }
+
+        let items               = match args[0].to_vec() { Some(items) => items, None => return (frame, Err(RuntimeError::TypeMismatch(args[0].clone()))) };
+        let function            = match &*args[1] { SafasCell::FrameMonad(function) => function, _ => return (frame, Err(RuntimeError::NotAFunction(args[1].clone()))) };
+
+        // Call the function for each item
+        let mut results         = vec![];
+        for item in items {
+            frame.cells[0]              = SafasCell::list_with_cells(vec![item]);
+            let (new_frame, result)     = function.execute(frame);
+            frame                       = new_frame;
+
+            match result {
+                Ok(result)  => results.push(result),
+                Err(err)    => return (frame, Err(err))
+            }
+        }
+
+        if self.returns_monad {
+            self.chain_monads(results, frame)
+        } else {
+            (frame, Ok(SafasCell::list_with_cells(results)))
+        }
+    }
+
+    fn returns_monad(&self) -> bool { self.returns_monad }
+}
+
+///
+/// Binds a loop that evaluates a set of statements with a variable set to each of the items in a list
+///
+fn bind_for_each(variable: CellRef, items: CellRef, statements: CellRef, bindings: SymbolBindings) -> (SymbolBindings, Result<SyntaxCompiler, BindError>) {
+    if statements.is_nil() { return (bindings, Err(BindError::MissingArgument)); }
+
+    // The statements become the body of a function
+    let mut bindings            = bindings;
+    bindings.args               = Some(SafasCell::List(SafasCell::list_with_cells(vec![variable]), statements).into());
+    let (bindings, function)    = fun_keyword().bind(bindings);
+    let function                = match function { Ok(function) => function, Err(err) => return (bindings, Err(err)) };
+
+    // Call the function for each item (the items will be flat_mapped in the usual way if they're a monad)
+    let for_each                = ForEach { returns_monad: function.reference_type() == ReferenceType::ReturnsMonad };
+    let for_each                = SafasCell::FrameMonad(Box::new(for_each)).into();
+    let call                    = SafasCell::list_with_cells(vec![for_each, items, SafasCell::BoundSyntax(function).into()]);
+
+    match bind_statement(call, bindings) {
+        Ok((call, bindings))    => {
+            let reference_type = call.reference_type();
+            (bindings, Ok(SyntaxCompiler::with_compiler_and_reftype(|call| compile_statement(call), call, reference_type)))
+        }
+
+        Err((err, bindings))    => (bindings, Err(err))
+    }
+}
+
+///
+/// The `for` keyword evaluates a series of statements for each item in a list
+///
+/// ```(for <variable> in <list> <statements>)```
+///
+/// If the statements generate bitcode, it's generated in order for each item, and the result is the value of the last
+/// iteration: otherwise the result is the list of values generated by each iteration. For example, this generates a
+/// table of the squares of the numbers from 0 to 15:
+///
+/// ```(for x in (range 16) (d (bits 8 (* x x))))```
+///
+pub fn for_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    BindingFn::from_binding_fn(|bindings: SymbolBindings| {
+        let args = bindings.args.clone().and_then(|args| args.to_vec()).unwrap_or_else(|| vec![]);
+        if args.len() < 4 { return (bindings, Err(BindError::MissingArgument)); }
+
+        // Syntax is <variable> in <list> <statements>
+        if args[0].to_atom_id().is_none()           { return (bindings, Err(BindError::VariablesMustBeAtoms)); }
+        if args[1].to_atom_id() != Some(*IN_ATOM)   { return (bindings, Err(BindError::SyntaxExpectingAtom)); }
+
+        let statements = SafasCell::list_with_cells(args[3..].iter().cloned());
+        bind_for_each(args[0].clone(), args[2].clone(), statements, bindings)
+    })
+}
+
+///
+/// The `repeat` keyword evaluates a series of statements a number of times
+///
+/// ```(repeat <count> (<index>) <statements>)```
+///
+/// The index variable is set to the number of the current iteration, starting at 0, and can be left out by using `()`. The
+/// results are combined in the same way as for `for`: for example, this unrolls a loop 4 times:
+///
+/// ```(repeat 4 () (asl a))```
+///
+pub fn repeat_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    BindingFn::from_binding_fn(|bindings: SymbolBindings| {
+        let args = bindings.args.clone().and_then(|args| args.to_vec()).unwrap_or_else(|| vec![]);
+        if args.len() < 3 { return (bindings, Err(BindError::MissingArgument)); }
+
+        // Syntax is <count> (<index>) <statements>
+        let index = match args[1].to_vec() {
+            Some(index) => index,
+            None        => return (bindings, Err(BindError::SyntaxExpectingList))
+        };
+        let index = match index.len() {
+            0   => SafasCell::Atom(*REPEAT_INDEX_ATOM).into(),
+            1   => index[0].clone(),
+            _   => return (bindings, Err(BindError::TooManyArguments))
+        };
+
+        // Repeating is the same as iterating over (range <count>)
+        let range       = SafasCell::FrameMonad(Box::new(range_fn())).into();
+        let items       = SafasCell::list_with_cells(vec![range, args[0].clone()]);
+        let statements  = SafasCell::list_with_cells(args[2..].iter().cloned());
+
+        bind_for_each(index, items, statements, bindings)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+    use crate::test_support::{assemble_expr, cpu_session};
+
+    #[test]
+    fn range_values() {
+        let val = eval("(list (range 4) (range 2 5) (range 0 10 3) (range 5 5))").unwrap().to_string();
+        assert!(val == "((0 1 2 3) (2 3 4) (0 3 6 9) ())".to_string());
+    }
+
+    #[test]
+    fn range_counting_down() {
+        let val = eval("(range 3i8 0i8 (- 0i8 1i8))").unwrap().to_string();
+        assert!(val == "(3i8 2i8 1i8)".to_string());
+    }
+
+    #[test]
+    fn range_step_zero() {
+        let result = eval("(range 0 10 0)");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn for_values() {
+        let val = eval("(for x in (list 1 2 3) (* x 2))").unwrap().to_string();
+        assert!(val == "(2 4 6)".to_string());
+    }
+
+    #[test]
+    fn repeat_values() {
+        let val = eval("(repeat 4 (i) (+ i 10))").unwrap().to_string();
+        assert!(val == "(10 11 12 13)".to_string());
+    }
+
+    #[test]
+    fn repeat_captures_symbols() {
+        let val = eval("(def y 5) (repeat 3 (i) (+ i y))").unwrap().to_string();
+        assert!(val == "(5 6 7)".to_string());
+    }
+
+    #[test]
+    fn repeat_without_index() {
+        let val = eval("(repeat 2 () 1)").unwrap().to_string();
+        assert!(val == "(1 1)".to_string());
+    }
+
+    #[test]
+    fn for_needs_in() {
+        let result = eval("(for x (list 1 2 3) x)");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn for_bitcode_table() {
+        let bytes = assemble_expr("(d 1u8) (for x in (range 4) (d (bits 8 (* x x)))) (d 2u8)");
+        assert!(bytes == "01 00 01 04 09 02".to_string());
+    }
+
+    #[test]
+    fn repeat_bitcode() {
+        let bytes = assemble_expr("(repeat 3 (i) (d $aau8) (d (bits 8 i)))");
+        assert!(bytes == "aa 00 aa 01 aa 02".to_string());
+    }
+
+    #[test]
+    fn repeat_zero_times() {
+        let bytes = assemble_expr("(d 1u8) (repeat 0 (i) (d $aau8)) (d 2u8)");
+        assert!(bytes == "01 02".to_string());
+    }
+
+    #[test]
+    fn repeat_reads_bit_pos() {
+        // Without a label_value function, label values are bit positions
+        let bytes = assemble_expr("(repeat 3 (i) (label here) (d (bits 8 here)))");
+        assert!(bytes == "00 08 10".to_string());
+    }
+
+    #[test]
+    fn repeat_forward_label() {
+        let bytes = assemble_expr("(repeat 2 (i) (d (bits 8 next)) (d $ffu8) (label next))");
+        assert!(bytes == "10 ff 20 ff".to_string());
+    }
+
+    #[test]
+    fn unrolled_loop_6502() {
+        // Each iteration has its own label, and can read ip
+        let mut session = cpu_session("6502");
+        let assembly    = session.assemble("(assemble_6502 (set_ip $200) (repeat 2 (i) (label loop) (dex) (bne loop)) (for x in (range 2) (d (bits 8 ip))))").unwrap();
+
+        assert!(assembly.bytes() == vec![0xca, 0xd0, 0xfd, 0xca, 0xd0, 0xfd, 0x06, 0x07]);
+    }
+}
@@ -0,0 +1,444 @@
+use super::conditional::*;
+
+use crate::bind::*;
+use crate::meta::*;
+use crate::exec::*;
+
+use std::sync::*;
+use std::convert::*;
+
+///
+/// The ways that the values in a `let` statement can be bound
+///
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum LetKind {
+    /// `let`: the values are bound before any of the symbols are defined
+    Parallel,
+
+    /// `let*`: each value can refer to the symbols defined before it
+    Sequential,
+
+    /// `letrec`: each value can refer to any of the symbols (so functions can call themselves and each other)
+    Recursive
+}
+
+///
+/// Binding monad for the let keywords
+///
+struct Let {
+    kind: LetKind
+}
+
+///
+/// Frame monad that calls a function defined by a `letrec` statement
+///
+/// Closures capture the values of their symbols when they're created, so a function defined by `letrec` can't capture
+/// itself directly. Instead, it captures one of these, which is updated to point at the function once it exists.
+///
+struct RecursiveReference(Arc<RwLock<CellRef>>);
+
+impl FrameMonad for RecursiveReference {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { self.0.read().unwrap().to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let target = self.0.read().unwrap().clone();
+
+        match &*target {
+            SafasCell::FrameMonad(action)   => action.execute(frame),
+            _                               => (frame, Err(RuntimeError::NotAFunction(target)))
+        }
+    }
+
+    fn returns_monad(&self) -> bool {
+        match &*self.0.read().unwrap().clone() {
+            SafasCell::FrameMonad(action)   => action.returns_monad(),
+            _                               => false
+        }
+    }
+}
+
+///
+/// Frame monad that stores a new recursive reference in a cell (and its target in the slot cell)
+///
+struct AllocRecursiveReference {
+    cell: usize,
+    slot: usize
+}
+
+impl FrameMonad for AllocRecursiveReference {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##alloc_recursive_reference##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        let mut frame           = frame;
+        let target              = Arc::new(RwLock::new(NIL.clone()));
+
+        frame.cells[self.cell]  = SafasCell::FrameMonad(Box::new(RecursiveReference(Arc::clone(&target)))).into();
+        frame.cells[self.slot]  = SafasCell::Any(Box::new(target)).into();
+
+        (frame, Ok(NIL.clone()))
+    }
+}
+
+///
+/// Frame monad that points the recursive reference in a slot cell at the value that's now in its cell
+///
+struct SetRecursiveReference {
+    cell: usize,
+    slot: usize
+}
+
+impl FrameMonad for SetRecursiveReference {
+    type Binding = RuntimeResult;
+
+    fn description(&self) -> String { "##set_recursive_reference##".to_string() }
+
+    fn execute(&self, frame: Frame) -> (Frame, RuntimeResult) {
+        if let SafasCell::Any(target) = &*frame.cells[self.slot] {
+            if let Some(target) = target.downcast_ref::<Arc<RwLock<CellRef>>>() {
+                *target.write().unwrap() = frame.cells[self.cell].clone();
+            }
+        }
+
+        (frame, Ok(NIL.clone()))
+    }
+}
+
+///
+/// Reads the arguments to a let statement: `((<name> <value>) ...) <statements>`
+///
+fn let_arguments(args: Option<CellRef>) -> Result<(Vec<(u64, CellRef)>, CellRef), BindError> {
+    let args                        = args.ok_or(BindError::MissingArgument)?;
+    let (definitions, statements)   = match &*args {
+        SafasCell::List(definitions, statements)    => (definitions.clone(), statements.clone()),
+        _                                           => return Err(BindError::MissingArgument)
+    };
+
+    let definitions = definitions.to_vec().ok_or(BindError::SyntaxExpectingList)?;
+    let definitions = definitions.into_iter()
+        .map(|definition| {
+            let ListTuple((name, value)): ListTuple<(AtomId, CellRef)> = definition.try_into()?;
+            let AtomId(name) = name;
+
+            Ok((name, value))
+        })
+        .collect::<Result<Vec<_>, BindError>>()?;
+
+    Ok((definitions, statements))
+}
+
+///
+/// Binds the values for a let statement
+///
+fn bind_values(values: &[CellRef], bindings: SymbolBindings) -> BindResult<Vec<CellRef>> {
+    let mut bindings    = bindings;
+    let mut bound       = vec![];
+
+    for value in values.iter() {
+        let (bound_value, new_bindings) = bind_statement(value.clone(), bindings)?;
+
+        bindings = new_bindings;
+        bound.push(bound_value);
+    }
+
+    Ok((bound, bindings))
+}
+
+impl Let {
+    ///
+    /// Binds the definitions in a let statement, returning a list of `(cell slot value)` for each one
+    ///
+    fn bind_definitions(&self, definitions: Vec<(u64, CellRef)>, bindings: SymbolBindings) -> BindResult<Vec<CellRef>> {
+        let mut bindings    = bindings;
+        let values          = definitions.iter().map(|(_name, value)| value.clone()).collect::<Vec<_>>();
+
+        match self.kind {
+            LetKind::Parallel => {
+                // Bind all of the values before defining any of the symbols
+                let (values, new_bindings)  = bind_values(&values, bindings)?;
+                bindings                    = new_bindings;
+
+                let definitions = definitions.iter().zip(values)
+                    .map(|((name, _), value)| {
+                        let cell_id         = bindings.alloc_cell();
+                        let cell: CellRef   = SafasCell::FrameReference(cell_id, 0, value.reference_type()).into();
+                        bindings.symbols.insert(*name, cell.clone());
+
+                        SafasCell::list_with_cells(vec![cell, NIL.clone(), value])
+                    })
+                    .collect();
+
+                Ok((definitions, bindings))
+            }
+
+            LetKind::Sequential => {
+                // Define each symbol after its value is bound
+                let mut bound = vec![];
+
+                for (name, value) in definitions.iter() {
+                    let (value, new_bindings)   = bind_statement(value.clone(), bindings)?;
+                    bindings                    = new_bindings;
+
+                    let cell_id                 = bindings.alloc_cell();
+                    let cell: CellRef           = SafasCell::FrameReference(cell_id, 0, value.reference_type()).into();
+                    bindings.symbols.insert(*name, cell.clone());
+
+                    bound.push(SafasCell::list_with_cells(vec![cell, NIL.clone(), value]));
+                }
+
+                Ok((bound, bindings))
+            }
+
+            LetKind::Recursive => {
+                // Define all of the symbols before binding the values: each symbol also gets a slot cell for its recursive reference
+                let cells           = definitions.iter().map(|_| (bindings.alloc_cell(), bindings.alloc_cell())).collect::<Vec<_>>();
+                let mut ref_types   = definitions.iter().map(|_| ReferenceType::Value).collect::<Vec<_>>();
+
+                // The reference types of the symbols aren't known until the values are bound, so rebind until they stop changing
+                let mut iteration = 0;
+                loop {
+                    for ((name, _), ((cell_id, _), ref_type)) in definitions.iter().zip(cells.iter().zip(ref_types.iter())) {
+                        bindings.symbols.insert(*name, SafasCell::FrameReference(*cell_id, 0, *ref_type).into());
+                    }
+
+                    let (bound, new_bindings)   = bind_values(&values, bindings)?;
+                    bindings                    = new_bindings;
+                    iteration                   += 1;
+
+                    let new_ref_types           = bound.iter().map(|value| value.reference_type()).collect::<Vec<_>>();
+                    if new_ref_types == ref_types || iteration > definitions.len() {
+                        let definitions = bound.into_iter().zip(cells.iter())
+                            .map(|(value, (cell_id, slot_id))| {
+                                let cell: CellRef = SafasCell::FrameReference(*cell_id, 0, value.reference_type()).into();
+                                let slot: CellRef = SafasCell::FrameReference(*slot_id, 0, ReferenceType::Value).into();
+
+                                SafasCell::list_with_cells(vec![cell, slot, value])
+                            })
+                            .collect();
+
+                        return Ok((definitions, bindings));
+                    }
+
+                    ref_types = new_ref_types;
+                }
+            }
+        }
+    }
+}
+
+impl BindingMonad for Let {
+    type Binding = SyntaxCompiler;
+
+    fn description(&self) -> String {
+        match self.kind {
+            LetKind::Parallel   => "##let##".to_string(),
+            LetKind::Sequential => "##let*##".to_string(),
+            LetKind::Recursive  => "##letrec##".to_string()
+        }
+    }
+
+    fn pre_bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Self::Binding) {
+        // The symbols defined by a let statement aren't visible outside of it
+        (bindings, SyntaxCompiler::default())
+    }
+
+    fn bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Result<Self::Binding, BindError>) {
+        // Arguments are the list of definitions and the statements
+        let (definitions, statements) = match let_arguments(bindings.args.clone()) {
+            Ok(args)    => args,
+            Err(err)    => return (bindings, Err(err))
+        };
+
+        // The definitions and statements are bound in their own frame so the symbols don't leak out
+        let let_bindings = bindings.push_interior_frame();
+        let (definitions, mut let_bindings) = match self.bind_definitions(definitions, let_bindings) {
+            Ok(result)              => result,
+            Err((err, let_bindings)) => {
+                let (bindings, _imports) = let_bindings.pop();
+                return (bindings, Err(err));
+            }
+        };
+
+        // Pre-bind the statements so they can refer to labels declared later on
+        let mut pos = &*statements;
+        while let SafasCell::List(statement, next) = pos {
+            let (new_bindings, _)   = pre_bind_statement(statement.clone(), let_bindings);
+            let_bindings            = new_bindings;
+            pos                     = &*next;
+        }
+
+        // Bind the statements
+        let (statements, reference_type, let_bindings) = match bind_several_statements(statements, let_bindings) {
+            Ok(((statements, reference_type), let_bindings))    => (statements, reference_type, let_bindings),
+            Err((err, let_bindings))                            => {
+                let (bindings, _imports) = let_bindings.pop();
+                return (bindings, Err(err));
+            }
+        };
+        let (bindings, _imports) = let_bindings.pop();
+
+        // Compile the definitions, then the statements
+        let compile = |bound: CellRef| {
+            let ListTuple((definitions, statements)): ListTuple<(CellRef, CellRef)> = bound.try_into()?;
+            let definitions = definitions.to_vec().unwrap_or_else(|| vec![]);
+            let definitions = definitions.into_iter()
+                .map(|definition| {
+                    let ListTuple((FrameReference(cell, _, _), slot, value)): ListTuple<(FrameReference, CellRef, CellRef)> = definition.try_into()?;
+                    let slot = match &*slot {
+                        SafasCell::FrameReference(slot, _, _)   => Some(*slot),
+                        _                                       => None
+                    };
+
+                    Ok((cell, slot, value))
+                })
+                .collect::<Result<Vec<_>, BindError>>()?;
+
+            let mut actions = CompiledActions::empty();
+
+            // Recursive definitions start out as references to the values that are yet to be evaluated
+            for (cell, slot, _value) in definitions.iter() {
+                if let Some(slot) = slot {
+                    actions.push(Action::Value(SafasCell::FrameMonad(Box::new(AllocRecursiveReference { cell: *cell, slot: *slot })).into()));
+                    actions.push(Action::Call);
+                }
+            }
+
+            // Evaluate each value and store it in its cell
+            for (cell, slot, value) in definitions.into_iter() {
+                actions.extend(compile_statement(value)?);
+                actions.push(Action::StoreCell(cell));
+
+                if let Some(slot) = slot {
+                    actions.push(Action::Value(SafasCell::FrameMonad(Box::new(SetRecursiveReference { cell: cell, slot: slot })).into()));
+                    actions.push(Action::Call);
+                }
+            }
+
+            // The value of the let statement is the value of the statements
+            actions.extend(compile_several_statements(statements)?);
+
+            Ok(actions)
+        };
+
+        (bindings, Ok(SyntaxCompiler::with_compiler_and_reftype(compile, SafasCell::list_with_cells(vec![SafasCell::list_with_cells(definitions), statements]), reference_type)))
+    }
+
+    fn reference_type(&self, bound_value: CellRef) -> ReferenceType {
+        // Let statements have the same reference type as their list of statements
+        let statements = bound_value.to_vec().and_then(|bound_value| bound_value.get(1).cloned());
+
+        let mut reference_type  = ReferenceType::Value;
+        let mut pos             = statements.unwrap_or_else(|| NIL.clone());
+        while let SafasCell::List(statement, next) = &*pos.clone() {
+            if reference_type != ReferenceType::Monad { reference_type = statement.reference_type(); }
+            pos = next.clone();
+        }
+
+        reference_type
+    }
+}
+
+///
+/// The `let` keyword evaluates a series of statements with some local symbols
+///
+/// ```(let ((<name> <value>) ...) <statements>)```
+///
+/// The values are evaluated before any of the symbols are defined, so they can't refer to each other. The symbols are only
+/// visible to the statements in the `let` statement, for example:
+///
+/// ```(let ((a 1u8) (b 2u8)) (d a) (d b))```
+///
+pub fn let_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    Let { kind: LetKind::Parallel }
+}
+
+///
+/// The `let*` keyword works like `let`, except each value can refer to the symbols defined before it
+///
+/// ```(let* ((a 1) (b (+ a 1))) b)```
+///
+pub fn let_star_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    Let { kind: LetKind::Sequential }
+}
+
+///
+/// The `letrec` keyword works like `let`, except each value can refer to any of the symbols
+///
+/// This is intended for defining functions that call themselves or each other, for example:
+///
+/// ```(letrec ((count (fun (x) (if ((= x 0)) (0) ((count (- x 1))))))) (count 10))```
+///
+/// A value that refers to a symbol whose value is not a function will see it as a function that hasn't been defined yet.
+///
+pub fn letrec_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    Let { kind: LetKind::Recursive }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+    use crate::test_support::{assemble_expr};
+
+    #[test]
+    fn let_value() {
+        let val = eval("(let ((x 1) (y 2)) (+ x y))").unwrap().to_string();
+        assert!(val == "3".to_string());
+    }
+
+    #[test]
+    fn let_values_are_bound_in_parallel() {
+        let val = eval("(def x 1) (let ((x 2) (y x)) y)").unwrap().to_string();
+        assert!(val == "1".to_string());
+    }
+
+    #[test]
+    fn shadow_symbol_in_nested_let() {
+        let val = eval("(let ((x 1)) (let ((x (+ x 1))) x))").unwrap().to_string();
+        assert!(val == "2".to_string());
+    }
+
+    #[test]
+    fn let_symbols_do_not_leak() {
+        let result = eval("(let ((x 1)) x) x");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn let_star_values_are_bound_in_sequence() {
+        let val = eval("(let* ((x 1) (y (+ x 1))) (list x y))").unwrap().to_string();
+        assert!(val == "(1 2)".to_string());
+    }
+
+    #[test]
+    fn letrec_recursive_function() {
+        let val = eval("(letrec ((count (fun (x total) (if ((= x 0)) (total) ((count (- x 1) (+ total x))))))) (count 10 0))").unwrap().to_string();
+        assert!(val == "55".to_string());
+    }
+
+    #[test]
+    fn letrec_mutually_recursive_functions() {
+        let val = eval("
+            (letrec (
+                (is_even (fun (x) (if ((= x 0)) (=t) ((is_odd (- x 1))))))
+                (is_odd (fun (x) (if ((= x 0)) (=f) ((is_even (- x 1))))))
+            )
+                (list (is_even 10) (is_odd 7) (is_even 3)))
+        ").unwrap().to_string();
+        assert!(val == "(=t =t =f)".to_string());
+    }
+
+    #[test]
+    fn let_in_bitcode_block() {
+        let bytes = assemble_expr("(d 1u8) (let ((a 2u8) (b 3u8)) (d a) (d b)) (d 4u8)");
+        assert!(bytes == "01 02 03 04".to_string());
+    }
+
+    #[test]
+    fn let_in_function() {
+        let bytes = assemble_expr("(def f (fun (x) (let ((y (+ x 1u8))) (d x) (d y)))) (f 1u8) (f 3u8)");
+        assert!(bytes == "01 02 03 04".to_string());
+    }
+}
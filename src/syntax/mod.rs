@@ -5,11 +5,16 @@ mod syntax_symbol;
 mod syntax_closure;
 mod assemble_syntax;
 mod fun;
+mod scope;
+mod let_syntax;
 mod quote;
 mod conditional;
+mod cond;
+mod iteration;
 mod monad;
 mod export;
 mod pattern_match;
+mod options;
 mod standard_syntax;
 
 pub use self::def::*;
@@ -17,8 +22,13 @@ pub use self::def_syntax::*;
 pub use self::extend_syntax::*;
 pub use self::assemble_syntax::*;
 pub use self::fun::*;
+pub use self::scope::*;
+pub use self::let_syntax::*;
 pub use self::quote::*;
 pub use self::conditional::*;
+pub use self::cond::*;
+pub use self::iteration::*;
 pub use self::monad::*;
 pub use self::pattern_match::*;
+pub use self::options::*;
 pub use self::standard_syntax::*;
@@ -0,0 +1,45 @@
+use crate::bind::*;
+use crate::meta::*;
+
+///
+/// Syntax that calls a function that accepts keyword options
+///
+/// `(run_6502 program :load $200 :x 7)` binds `program`, `$200` and `7` as normal, but passes `:load` and `:x` to the
+/// function as atoms so it can read them as the names of its options.
+///
+pub fn function_with_options_keyword(function: CellRef) -> impl BindingMonad<Binding=SyntaxCompiler> {
+    BindingFn::from_binding_fn(move |bindings| {
+        let args = bindings.args.clone().unwrap_or_else(|| NIL.clone());
+
+        match bind_call_with_options(function.clone(), args, bindings) {
+            Ok((call, bindings))    => (bindings, Ok(call)),
+            Err((err, bindings))    => (bindings, Err(err))
+        }
+    }).map(|call: CellRef| {
+        let compiler = |call: CellRef| compile_statement(call);
+
+        SyntaxCompiler::with_compiler_and_reftype(compiler, call.clone(), call.reference_type())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+
+    #[test]
+    fn options_are_not_symbols_elsewhere() {
+        let result = eval("(d :lable)");
+
+        assert!(format!("{:?}", result).contains("UnknownSymbol(\":lable\")"));
+    }
+
+    #[test]
+    fn options_inside_a_function() {
+        let val = eval("
+            (def run_with_x (fun (x) (run_6502 (assemble (d $8au8 $00u8)) :x x)))
+            (btree_lookup (run_with_x 9) (quote a))
+        ").unwrap().to_string();
+
+        assert!(val == "$9u8".to_string());
+    }
+}
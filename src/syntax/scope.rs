@@ -0,0 +1,238 @@
+use super::conditional::*;
+
+use crate::bind::*;
+use crate::meta::*;
+
+lazy_static! {
+    /// The atom that stores the name of the scope that statements are being bound in
+    static ref SCOPE_NAME: u64 = get_id_for_atom_with_name("##scope_name##");
+}
+
+///
+/// Returns the atom ID of the full name of the scope that is being bound (eg, `outer.inner`), if there is one
+///
+pub fn current_scope_name(bindings: &SymbolBindings) -> Option<u64> {
+    bindings.look_up(*SCOPE_NAME).and_then(|(scope_name, _level)| scope_name.to_atom_id())
+}
+
+///
+/// Creates the bindings for the statements in a scope (scopes declared inside other scopes are named after their parents)
+///
+fn push_scope(scope_name: u64, bindings: SymbolBindings) -> SymbolBindings {
+    let full_name           = match current_scope_name(&bindings) {
+        Some(parent_name)   => scoped_atom_id(parent_name, scope_name),
+        None                => scope_name
+    };
+
+    let mut scope_bindings  = bindings.push_interior_frame();
+    scope_bindings.symbols.insert(*SCOPE_NAME, SafasCell::Atom(full_name).into());
+
+    scope_bindings
+}
+
+///
+/// Returns the symbols defined in a scope (ignoring any internal symbols, such as those used for anonymous labels)
+///
+fn scope_symbols(scope_bindings: &SymbolBindings) -> Vec<(u64, CellRef)> {
+    scope_bindings.symbols.iter()
+        .filter(|(atom_id, _value)| {
+            let name = name_for_atom_with_id(**atom_id);
+            !name.starts_with(':') && !name.starts_with('#')
+        })
+        .map(|(atom_id, value)| (*atom_id, value.clone()))
+        .collect()
+}
+
+///
+/// Returns the atom ID for a symbol in a scope (eg, `name.loop`)
+///
+fn scoped_atom_id(scope_name: u64, atom_id: u64) -> u64 {
+    get_id_for_atom_with_name(&format!("{}.{}", name_for_atom_with_id(scope_name), name_for_atom_with_id(atom_id)))
+}
+
+///
+/// Makes the symbols defined in a scope available in the parent bindings as `name.symbol`
+///
+fn export_scope_symbols(scope_name: u64, symbols: Vec<(u64, CellRef)>, bindings: &mut SymbolBindings) {
+    for (atom_id, value) in symbols {
+        let scoped_atom_id = scoped_atom_id(scope_name, atom_id);
+
+        bindings.symbols.insert(scoped_atom_id, value);
+        bindings.export(scoped_atom_id);
+    }
+}
+
+///
+/// Binding monad for the scope keyword
+///
+struct Scope;
+
+impl BindingMonad for Scope {
+    type Binding = SyntaxCompiler;
+
+    fn description(&self) -> String { "##scope##".to_string() }
+
+    fn pre_bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Self::Binding) {
+        // Arguments are the name of the scope and the statements
+        let args = bindings.args.clone().unwrap_or_else(|| NIL.clone());
+        let (scope_name, statements) = match &*args {
+            SafasCell::List(name, statements)   => match name.to_atom_id() { Some(name) => (name, statements.clone()), None => return (bindings, SyntaxCompiler::default()) },
+            _                                   => return (bindings, SyntaxCompiler::default())
+        };
+
+        // Pre-bind the statements in their own frame
+        let mut scope_bindings  = push_scope(scope_name, bindings);
+        let mut pos             = &*statements;
+        while let SafasCell::List(statement, next) = pos {
+            let (new_bindings, _)   = pre_bind_statement(statement.clone(), scope_bindings);
+            scope_bindings          = new_bindings;
+            pos                     = &*next;
+        }
+
+        // Any labels declared in the scope can be forward-referenced from outside as `name.label`
+        let symbols             = scope_symbols(&scope_bindings);
+        let (mut bindings, _)   = scope_bindings.pop();
+        export_scope_symbols(scope_name, symbols, &mut bindings);
+
+        (bindings, SyntaxCompiler::default())
+    }
+
+    fn bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Result<Self::Binding, BindError>) {
+        // Arguments are the name of the scope and the statements
+        let args = bindings.args.clone().unwrap_or_else(|| NIL.clone());
+        let (scope_name, statements) = match &*args {
+            SafasCell::List(name, statements)   => match name.to_atom_id() { Some(name) => (name, statements.clone()), None => return (bindings, Err(BindError::SyntaxExpectingAtom)) },
+            _                                   => return (bindings, Err(BindError::MissingArgument))
+        };
+
+        // Pre-bind the statements in their own frame
+        let mut scope_bindings  = push_scope(scope_name, bindings);
+        let mut pos             = &*statements;
+        while let SafasCell::List(statement, next) = pos {
+            let (new_bindings, _)   = pre_bind_statement(statement.clone(), scope_bindings);
+            scope_bindings          = new_bindings;
+            pos                     = &*next;
+        }
+
+        // Use the same values for the labels as when the scope was pre-bound (so any forward references to `name.label` refer to the same label)
+        for (atom_id, _value) in scope_symbols(&scope_bindings) {
+            if let Some((pre_bound_value, 0)) = scope_bindings.look_up(scoped_atom_id(scope_name, atom_id)) {
+                scope_bindings.symbols.insert(atom_id, pre_bound_value);
+            }
+        }
+
+        // Bind the statements
+        let (bound, reference_type, scope_bindings) = match bind_several_statements(statements, scope_bindings) {
+            Ok(((bound, reference_type), new_bindings)) => (bound, reference_type, new_bindings),
+            Err((err, new_bindings))                    => {
+                let (bindings, _imports) = new_bindings.pop();
+                return (bindings, Err(err));
+            }
+        };
+
+        // Symbols defined in the scope are available outside it as `name.symbol`
+        let symbols             = scope_symbols(&scope_bindings);
+        let (mut bindings, _)   = scope_bindings.pop();
+        export_scope_symbols(scope_name, symbols, &mut bindings);
+
+        // Compile the statements one after the other
+        let compile             = |statements: CellRef| compile_several_statements(statements);
+
+        (bindings, Ok(SyntaxCompiler::with_compiler_and_reftype(compile, bound, reference_type)))
+    }
+
+    fn reference_type(&self, bound_value: CellRef) -> ReferenceType {
+        // Scopes that contain a monad statement evaluate to a monad
+        let mut pos = &*bound_value;
+
+        while let SafasCell::List(statement, next) = pos {
+            if statement.reference_type() == ReferenceType::Monad {
+                return ReferenceType::Monad;
+            }
+
+            pos = &*next;
+        }
+
+        ReferenceType::Value
+    }
+}
+
+///
+/// The `scope` keyword evaluates a series of statements with their own set of symbols
+///
+/// ```(scope <name> <statements>)```
+///
+/// Labels and other symbols defined in a scope are not visible to the statements outside of it, so the same label name
+/// can be used in more than one scope. They can be accessed from outside the scope as `<name>.<symbol>`, for example:
+///
+/// ```(scope wait (label loop) (dex) (bne loop)) (jmp wait.loop)```
+///
+/// Labels can be referenced in this way before the scope is declared.
+///
+pub fn scope_keyword() -> impl BindingMonad<Binding=SyntaxCompiler> {
+    Scope
+}
+
+#[cfg(test)]
+mod test {
+    use crate::interactive::*;
+    use crate::test_support::{assemble_expr, cpu_session};
+
+    #[test]
+    fn same_label_in_two_scopes() {
+        // Without a label_value function, label values are bit positions
+        let bytes = assemble_expr("
+            (scope first (label loop) (d 1u8) (d (bits 8 loop)))
+            (scope second (label loop) (d 2u8) (d (bits 8 loop)))
+        ");
+        assert!(bytes == "01 00 02 10".to_string());
+    }
+
+    #[test]
+    fn read_label_from_outside_scope() {
+        let bytes = assemble_expr("
+            (d 1u8)
+            (scope first (d 2u8) (label loop) (d 3u8))
+            (d (bits 8 first.loop))
+        ");
+        assert!(bytes == "01 02 03 10".to_string());
+    }
+
+    #[test]
+    fn forward_reference_to_scoped_label() {
+        let bytes = assemble_expr("
+            (d (bits 8 first.loop))
+            (scope first (d 2u8) (label loop) (d 3u8))
+        ");
+        assert!(bytes == "10 02 03".to_string());
+    }
+
+    #[test]
+    fn scoped_label_is_not_visible_outside() {
+        let result = eval("
+            (scope first (label loop) (d 1u8))
+            (d (bits 8 loop))
+        ");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn scope_value() {
+        let val = eval("(scope values (def x 1) (def y 2) (+ x y))").unwrap().to_string();
+        assert!(val == "3".to_string());
+    }
+
+    #[test]
+    fn read_value_from_scope() {
+        let val = eval("(scope values (def x 1) (def y 2)) (list values.x values.y)").unwrap().to_string();
+        assert!(val == "(1 2)".to_string());
+    }
+
+    #[test]
+    fn labels_in_scopes_6502() {
+        let mut session = cpu_session("6502");
+        let assembly    = session.assemble("(assemble_6502 (set_ip $200) (jmp second.loop) (scope first (label loop) (dex) (bne loop)) (scope second (label loop) (dey) (bne loop)))").unwrap();
+
+        assert!(assembly.bytes() == vec![0x4c, 0x06, 0x02, 0xca, 0xd0, 0xfd, 0x88, 0xd0, 0xfd]);
+    }
+}
@@ -2,10 +2,14 @@ use super::def::*;
 use super::def_syntax::*;
 use super::extend_syntax::*;
 use super::fun::*;
+use super::scope::*;
+use super::let_syntax::*;
 use super::quote::*;
 use super::export::*;
 use super::assemble_syntax::*;
 use super::conditional::*;
+use super::cond::*;
+use super::iteration::*;
 
 use crate::io::*;
 use crate::meta::*;
@@ -25,6 +29,8 @@ pub fn standard_syntax() -> impl BindingMonad<Binding=SmallVec<[Action; 8]>> {
     let syntax  = flat_map_binding_actions(move || define_symbol_value("import",        SafasCell::Syntax(Box::new(import_keyword()), NIL.clone())), syntax);
     let syntax  = flat_map_binding_actions(move || define_symbol_value("export",        SafasCell::Syntax(Box::new(export_keyword()), NIL.clone())), syntax);
     let syntax  = flat_map_binding_actions(move || define_symbol_value("re_export",     SafasCell::Syntax(Box::new(re_export_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("incbin",        SafasCell::Syntax(Box::new(incbin_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("file_size",     SafasCell::Syntax(Box::new(file_size_keyword()), NIL.clone())), syntax);
 
     let syntax: Box<dyn BindingMonad<Binding=_>> = Box::new(syntax);
 
@@ -33,16 +39,43 @@ pub fn standard_syntax() -> impl BindingMonad<Binding=SmallVec<[Action; 8]>> {
     let syntax  = flat_map_binding_actions(move || define_symbol_value("def_syntax",    SafasCell::Syntax(Box::new(def_syntax_keyword()), NIL.clone())), syntax);
     let syntax  = flat_map_binding_actions(move || define_symbol_value("extend_syntax", SafasCell::Syntax(Box::new(extend_syntax_keyword()), NIL.clone())), syntax);
     let syntax  = flat_map_binding_actions(move || define_symbol_value("fun",           SafasCell::Syntax(Box::new(fun_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("scope",         SafasCell::Syntax(Box::new(scope_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("let",           SafasCell::Syntax(Box::new(let_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("let*",          SafasCell::Syntax(Box::new(let_star_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("letrec",        SafasCell::Syntax(Box::new(letrec_keyword()), NIL.clone())), syntax);
     let syntax  = flat_map_binding_actions(move || define_symbol_value("quote",         SafasCell::Syntax(Box::new(quote_keyword()), NIL.clone())), syntax);
 
     let syntax: Box<dyn BindingMonad<Binding=_>> = Box::new(syntax);
 
     // Conditional syntax
     let syntax  = flat_map_binding_actions(move || define_symbol_value("if",            SafasCell::Syntax(Box::new(if_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("cond",          SafasCell::Syntax(Box::new(cond_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("case",          SafasCell::Syntax(Box::new(case_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("and",           SafasCell::Syntax(Box::new(and_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("or",            SafasCell::Syntax(Box::new(or_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("not",           SafasCell::Syntax(Box::new(not_keyword()), NIL.clone())), syntax);
+
+    let syntax: Box<dyn BindingMonad<Binding=_>> = Box::new(syntax);
+
+    // Iteration syntax
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("for",           SafasCell::Syntax(Box::new(for_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("repeat",        SafasCell::Syntax(Box::new(repeat_keyword()), NIL.clone())), syntax);
 
     // Bitcode syntax
     let syntax  = flat_map_binding_actions(move || define_symbol_value("label",         SafasCell::Syntax(Box::new(label_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value("section",       SafasCell::Syntax(Box::new(section_keyword()), NIL.clone())), syntax);
     let syntax  = flat_map_binding_actions(move || define_symbol_value("assemble",      SafasCell::Syntax(Box::new(assemble_keyword()), NIL.clone())), syntax);
 
+    let syntax: Box<dyn BindingMonad<Binding=_>> = Box::new(syntax);
+
+    // Anonymous labels
+    let syntax  = flat_map_binding_actions(move || define_symbol_value(":",             SafasCell::Syntax(Box::new(anonymous_label_keyword()), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value(":+",            SafasCell::Syntax(Box::new(anonymous_label_reference(1)), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value(":++",           SafasCell::Syntax(Box::new(anonymous_label_reference(2)), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value(":+++",          SafasCell::Syntax(Box::new(anonymous_label_reference(3)), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value(":-",            SafasCell::Syntax(Box::new(anonymous_label_reference(-1)), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value(":--",           SafasCell::Syntax(Box::new(anonymous_label_reference(-2)), NIL.clone())), syntax);
+    let syntax  = flat_map_binding_actions(move || define_symbol_value(":---",          SafasCell::Syntax(Box::new(anonymous_label_reference(-3)), NIL.clone())), syntax);
+
     syntax
 }
@@ -193,6 +193,15 @@ impl BindingMonad for SyntaxClosure {
         }
         interior_bindings.symbols.insert(get_id_for_atom_with_name("syntax"), self.syntax_btree.clone());
 
+        // Pre-bind the statements so the labels they declare can be used anywhere in the block (when the syntax was defined by
+        // an earlier statement, the pre-binding for the statement that uses it doesn't reach them)
+        let mut pos                 = &*args;
+        while let SafasCell::List(argument, next) = pos {
+            let (new_bindings, _)   = pre_bind_statement(argument.clone(), interior_bindings);
+            interior_bindings       = new_bindings;
+            pos                     = &*next;
+        }
+
         // The arguments are the statements for these macros: compile them one after the other
         let mut pos                 = &*args;
         let mut bound               = vec![];
@@ -336,3 +345,18 @@ pub (super) fn rebind_imported_bindings(imported_bindings: Arc<HashMap<usize, Ce
 
     (bindings, Some(rebound_imported_bindings))
 }
+
+#[cfg(test)]
+mod test {
+    use crate::session::*;
+
+    #[test]
+    fn labels_in_syntax_block() {
+        // The syntax is defined by an earlier evaluation, so the labels in the block are only pre-bound when the block itself is bound
+        let mut session = Session::without_default_library();
+        session.eval("(def_syntax block ((pad) ((d $ffu8)) (jump <addr>) ((d (bits 8 addr)))))").unwrap();
+        let assembly    = session.assemble("(block (jump end) (label start) (pad) (jump start) (label end))").unwrap();
+
+        assert!(assembly.bytes() == vec![0x18, 0xff, 0x08]);
+    }
+}
@@ -173,6 +173,50 @@ impl BindingMonad for Arc<SyntaxSymbol> {
     }
 
     fn bind(&self, bindings: SymbolBindings) -> (SymbolBindings, Result<Self::Binding, BindError>) {
+        // The arguments to a macro can use other macros, so limit how deeply the expansions can nest
+        let max_depth   = bindings.limits.get().max_macro_depth;
+        let depth       = bindings.macro_depth;
+        if depth >= max_depth {
+            return (bindings, Err(BindError::MacroDepthLimitReached(max_depth)));
+        }
+
+        let mut bindings                = bindings;
+        bindings.macro_depth            = depth + 1;
+        let (mut bindings, result)      = stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, move || self.bind_expansion(bindings));
+        bindings.macro_depth            = depth;
+
+        (bindings, result)
+    }
+
+    fn reference_type(&self, _bound_value: CellRef) -> ReferenceType {
+        self.reference_type
+    }
+
+    fn rebind_from_outer_frame(&self, bindings: SymbolBindings, parameter: CellRef, frame_depth: u32) -> (SymbolBindings, Option<(Box<dyn BindingMonad<Binding=Self::Binding>>, CellRef)>) {
+        // Rebind the imported bindings to the new frame
+        let (bindings, rebound_imported_bindings)   = rebind_imported_bindings(Arc::clone(&self.imported_bindings), bindings, frame_depth);
+        let (bindings, fallback_syntax)             = match self.fallback_syntax { Some(ref fallback) => rebind_cell(fallback, bindings, frame_depth), None => (bindings, None) };
+
+        // Map to a new syntax symbol
+        let rebound_syntax                          = rebound_imported_bindings.map(|rebound_imported_bindings| {
+            SyntaxSymbol {
+                patterns:           self.patterns.clone(),
+                imported_bindings:  rebound_imported_bindings,
+                reference_type:     self.reference_type,
+                fallback_syntax:    fallback_syntax
+            }
+        });
+        let rebound_syntax                          = rebound_syntax.map(move |rebound_syntax| -> (Box<dyn BindingMonad<Binding=Self::Binding>>, _) { (Box::new(Arc::new(rebound_syntax)), parameter) });
+
+        (bindings, rebound_syntax)
+    }
+}
+
+impl SyntaxSymbol {
+    ///
+    /// Binds the arguments to this symbol against its patterns, expanding the first macro that matches
+    ///
+    fn bind_expansion(&self, bindings: SymbolBindings) -> (SymbolBindings, Result<SyntaxBindingResult, BindError>) {
         // Get the arguments for this symbol
         let args            = bindings.args.clone().unwrap_or_else(|| NIL.clone());
         let mut bindings    = bindings;
@@ -244,29 +288,6 @@ impl BindingMonad for Arc<SyntaxSymbol> {
             (bindings, Err(BindError::SyntaxMatchFailed))
         }
     }
-
-    fn reference_type(&self, _bound_value: CellRef) -> ReferenceType {
-        self.reference_type
-    }
-
-    fn rebind_from_outer_frame(&self, bindings: SymbolBindings, parameter: CellRef, frame_depth: u32) -> (SymbolBindings, Option<(Box<dyn BindingMonad<Binding=Self::Binding>>, CellRef)>) {
-        // Rebind the imported bindings to the new frame
-        let (bindings, rebound_imported_bindings)   = rebind_imported_bindings(Arc::clone(&self.imported_bindings), bindings, frame_depth);
-        let (bindings, fallback_syntax)             = match self.fallback_syntax { Some(ref fallback) => rebind_cell(fallback, bindings, frame_depth), None => (bindings, None) };
-
-        // Map to a new syntax symbol
-        let rebound_syntax                          = rebound_imported_bindings.map(|rebound_imported_bindings| {
-            SyntaxSymbol {
-                patterns:           self.patterns.clone(),
-                imported_bindings:  rebound_imported_bindings,
-                reference_type:     self.reference_type,
-                fallback_syntax:    fallback_syntax
-            }
-        });
-        let rebound_syntax                          = rebound_syntax.map(move |rebound_syntax| -> (Box<dyn BindingMonad<Binding=Self::Binding>>, _) { (Box::new(Arc::new(rebound_syntax)), parameter) });
-
-        (bindings, rebound_syntax)
-    }
 }
 
 ///
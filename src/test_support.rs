@@ -0,0 +1,47 @@
+use crate::exec::*;
+use crate::bitcode::*;
+use crate::session::*;
+use crate::interactive::*;
+
+///
+/// Evaluates an expression using the standard bindings and assembles the result, also returning any diagnostics
+///
+pub fn assembly_with_diagnostics(expr: &str) -> Result<(Assembly, Vec<Diagnostic>), RuntimeError> {
+    let result                              = eval(expr)?;
+    let monad                               = BitCodeMonad::from_cell(&result).unwrap();
+    let mut diagnostics                     = vec![];
+    let (value, bitcode, labels, listing)   = assemble_with_listing(&monad, &Limits::default(), &mut diagnostics)?;
+
+    let assembly = Assembly {
+        value:      value,
+        bitcode:    bitcode,
+        labels:     labels,
+        listing:    listing
+    };
+
+    Ok((assembly, diagnostics))
+}
+
+///
+/// Evaluates an expression using the standard bindings and assembles the result
+///
+pub fn assembly_for(expr: &str) -> Result<Assembly, RuntimeError> {
+    assembly_with_diagnostics(expr).map(|(assembly, _diagnostics)| assembly)
+}
+
+///
+/// Evaluates and assembles an expression, returning the bytes it generates as a string of hex values (eg, `01 02 03`)
+///
+pub fn assemble_expr(expr: &str) -> String {
+    assembly_for(expr).unwrap().bytes().into_iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+///
+/// Creates a session with one of the CPU libraries imported (eg, `cpu_session("6502")`)
+///
+pub fn cpu_session(cpu_name: &str) -> Session {
+    let mut session = Session::new().unwrap();
+    session.eval(&format!("(import \"cpu/{}\")", cpu_name)).unwrap();
+
+    session
+}